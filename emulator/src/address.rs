@@ -0,0 +1,40 @@
+/// Parses a hexadecimal address, optionally prefixed with `0x` or `$`
+pub fn parse_address(address: &str) -> Result<u16, String> {
+    let digits = address
+        .strip_prefix("0x")
+        .or_else(|| address.strip_prefix('$'))
+        .unwrap_or(address);
+    u16::from_str_radix(digits, 16).map_err(|err| format!("Invalid address {address}: {err}"))
+}
+
+/**
+Slice of `program`, loaded at `origin`, between the `start` and `end` addresses
+included, with the address it starts at.
+*/
+pub fn address_range(
+    program: &[u8],
+    origin: u16,
+    start: Option<u16>,
+    end: Option<u16>,
+) -> Result<(&[u8], u16), String> {
+    if program.is_empty() {
+        return Err("The input is empty".to_owned());
+    }
+    let last = origin as usize + program.len() - 1;
+    if last > u16::MAX as usize {
+        return Err(format!(
+            "{} bytes loaded at {origin:04x} do not fit in the address space",
+            program.len()
+        ));
+    }
+
+    let start = start.unwrap_or(origin) as usize;
+    let end = end.map_or(last, |end| end as usize);
+    if start < origin as usize || end > last || start > end {
+        return Err(format!(
+            "Range {start:04x}-{end:04x} is not within the input, loaded at {origin:04x}-{last:04x}"
+        ));
+    }
+    let offset = start - origin as usize;
+    Ok((&program[offset..=end - origin as usize], start as u16))
+}
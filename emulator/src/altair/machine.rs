@@ -0,0 +1,77 @@
+use crate::{console::Console, cpu::CPU};
+
+use super::serial::SerialBoards;
+
+/// The most RAM the 8080 can address, in KiB
+const MAX_RAM_SIZE: usize = 64;
+
+/**
+The MITS Altair 8800: an 8080 with RAM from address 0, the 88-SIO and 88-2SIO
+serial boards on a [`Console`] and the front panel sense switches.
+
+Addresses past the end of the RAM read FF, as on an empty bus, unless a ROM is
+loaded there.
+*/
+pub struct Altair<C: Console> {
+    cpu: CPU,
+    serial: SerialBoards<C>,
+}
+
+impl<C: Console> Altair<C> {
+    /// A machine with `ram_size` KiB of RAM, between 1 and 64
+    pub fn new(ram_size: usize, console: C) -> Result<Altair<C>, String> {
+        if !(1..=MAX_RAM_SIZE).contains(&ram_size) {
+            return Err(format!(
+                "The RAM size is {ram_size} KiB, it must be between 1 and {MAX_RAM_SIZE}"
+            ));
+        }
+
+        let mut cpu = CPU::new();
+        let ram_end = ram_size * 1024;
+        if ram_end < cpu.memory().len() {
+            cpu.memory_mut()[ram_end..].fill(0xff);
+            cpu.set_read_only(ram_end as u16..=0xffff);
+        }
+        Ok(Altair {
+            cpu,
+            serial: SerialBoards::new(console),
+        })
+    }
+
+    /// Copies `bytes` to `address`, the bytes past the end of the RAM being a read only ROM
+    pub fn load(&mut self, address: u16, bytes: &[u8]) -> Result<(), String> {
+        self.cpu.load_at(address, bytes)
+    }
+
+    /// Sets the sense switches A8 to A15 of the front panel, read from port FF
+    pub fn set_sense_switches(&mut self, switches: u8) {
+        self.serial.sense_switches = switches;
+    }
+
+    /// Starts execution at `address`, as examining it on the front panel and pressing run does
+    pub fn set_program_counter(&mut self, address: u16) {
+        self.cpu.set_program_counter(address);
+    }
+
+    pub fn cpu(&self) -> &CPU {
+        &self.cpu
+    }
+
+    pub fn console(&self) -> &C {
+        &self.serial.console
+    }
+
+    /// Executes one instruction, returning whether the machine is still running
+    pub fn step(&mut self) -> bool {
+        if self.serial.closed || self.cpu.is_halted() {
+            return false;
+        }
+        self.cpu.step(&mut self.serial);
+        true
+    }
+
+    /// Runs until the program halts or waits for input after the console is closed
+    pub fn run(&mut self) {
+        while self.step() {}
+    }
+}
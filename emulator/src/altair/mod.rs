@@ -0,0 +1,3 @@
+mod machine;
+mod serial;
+pub use machine::Altair;
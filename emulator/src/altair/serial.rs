@@ -0,0 +1,97 @@
+use crate::{console::Console, cpu::Bus};
+
+/// Status and data ports of the 88-SIO board
+const SIO_STATUS: u8 = 0x00;
+const SIO_DATA: u8 = 0x01;
+
+/// Control/status and data ports of the first channel of the 88-2SIO board
+const TWO_SIO_STATUS: u8 = 0x10;
+const TWO_SIO_DATA: u8 = 0x11;
+
+/// The front panel switches A8 to A15, which programs read as a port
+const SENSE_SWITCHES: u8 = 0xff;
+
+/// The 88-SIO reports a character received and the transmitter busy with bits that are low when set
+const SIO_INPUT_EMPTY: u8 = 0x01;
+
+/// The 6850 ACIA of the 88-2SIO, receive register full and transmit register empty
+const RECEIVE_FULL: u8 = 0x01;
+const TRANSMIT_EMPTY: u8 = 0x02;
+
+/**
+The serial boards of the Altair, both bridged to the same [`Console`], and the
+sense switches.
+
+Output loses the parity bit, which terminals of the time ignored.
+*/
+pub(super) struct SerialBoards<C: Console> {
+    pub(super) console: C,
+    pub(super) sense_switches: u8,
+
+    /// The data register keeps the last character received until the next one
+    last_received: u8,
+
+    /// Set when a program waits for input that will never come
+    pub(super) closed: bool,
+}
+
+impl<C: Console> SerialBoards<C> {
+    pub(super) fn new(console: C) -> SerialBoards<C> {
+        SerialBoards {
+            console,
+            sense_switches: 0,
+            last_received: 0,
+            closed: false,
+        }
+    }
+
+    fn input_ready(&mut self) -> bool {
+        let ready = self.console.status();
+        if !ready && self.console.closed() {
+            self.closed = true;
+        }
+        ready
+    }
+
+    fn receive(&mut self) -> u8 {
+        if self.console.status() {
+            match self.console.read() {
+                Some(byte) => self.last_received = byte,
+                None => self.closed = true,
+            }
+        }
+        self.last_received
+    }
+}
+
+impl<C: Console> Bus for SerialBoards<C> {
+    fn input(&mut self, port: u8) -> u8 {
+        match port {
+            SIO_STATUS => {
+                if self.input_ready() {
+                    0
+                } else {
+                    SIO_INPUT_EMPTY
+                }
+            }
+            TWO_SIO_STATUS => {
+                if self.input_ready() {
+                    RECEIVE_FULL | TRANSMIT_EMPTY
+                } else {
+                    TRANSMIT_EMPTY
+                }
+            }
+            SIO_DATA | TWO_SIO_DATA => self.receive(),
+            SENSE_SWITCHES => self.sense_switches,
+            // Nothing drives the data bus, which floats high
+            _ => 0xff,
+        }
+    }
+
+    fn output(&mut self, port: u8, value: u8) {
+        // Writes to the status ports set up the boards, which needs no emulation
+        if port == SIO_DATA || port == TWO_SIO_DATA {
+            self.console.write(value & 0x7f);
+        }
+    }
+}
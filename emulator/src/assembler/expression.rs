@@ -0,0 +1,301 @@
+use super::lexer::{Token, TokenKind};
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub(super) enum UnaryOperator {
+    Negate,
+    Not,
+    High,
+    Low,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub(super) enum BinaryOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    ShiftLeft,
+    ShiftRight,
+    And,
+    Or,
+    Xor,
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+/// An operand expression, evaluated once every symbol is known
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub(super) enum Expression {
+    Number(i32),
+    Symbol {
+        name: String,
+        column: usize,
+    },
+    /// `$`, the address of the line
+    Location,
+    Unary(UnaryOperator, Box<Expression>),
+    Binary {
+        operator: BinaryOperator,
+        column: usize,
+        left: Box<Expression>,
+        right: Box<Expression>,
+    },
+}
+
+/// Operators by increasing precedence, the tightest binding ones parsed last
+const BINARY_LEVELS: [&[(&str, BinaryOperator)]; 5] = [
+    &[("OR", BinaryOperator::Or), ("XOR", BinaryOperator::Xor)],
+    &[("AND", BinaryOperator::And)],
+    &[
+        ("EQ", BinaryOperator::Equal),
+        ("NE", BinaryOperator::NotEqual),
+        ("LT", BinaryOperator::Less),
+        ("LE", BinaryOperator::LessOrEqual),
+        ("GT", BinaryOperator::Greater),
+        ("GE", BinaryOperator::GreaterOrEqual),
+    ],
+    &[("+", BinaryOperator::Add), ("-", BinaryOperator::Subtract)],
+    &[
+        ("*", BinaryOperator::Multiply),
+        ("/", BinaryOperator::Divide),
+        ("MOD", BinaryOperator::Modulo),
+        ("SHL", BinaryOperator::ShiftLeft),
+        ("SHR", BinaryOperator::ShiftRight),
+    ],
+];
+
+/// Whether `name`, in upper case, is an operator written as a word, such as `AND` or `HIGH`
+pub(super) fn is_operator_word(name: &str) -> bool {
+    ["NOT", "HIGH", "LOW"].contains(&name)
+        || BINARY_LEVELS
+            .iter()
+            .flat_map(|level| level.iter())
+            .any(|(operator, _)| {
+                *operator == name && operator.chars().all(|char| char.is_ascii_alphabetic())
+            })
+}
+
+/// Level of `BINARY_LEVELS` after which `NOT` binds, as in Intel's assembler
+const NOT_LEVEL: usize = 2;
+
+/// Text of an operator token, keywords in upper case
+fn operator_text(token: &Token) -> Option<String> {
+    match &token.kind {
+        TokenKind::Identifier(name) => Some(name.to_ascii_uppercase()),
+        TokenKind::Plus => Some("+".to_owned()),
+        TokenKind::Minus => Some("-".to_owned()),
+        TokenKind::Star => Some("*".to_owned()),
+        TokenKind::Slash => Some("/".to_owned()),
+        _ => None,
+    }
+}
+
+/// Parses an expression from the start of `tokens`, stopping at the first token that cannot continue it
+pub(super) struct ExpressionParser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+    /// Column reported when the tokens end too early
+    end_column: usize,
+}
+
+impl<'a> ExpressionParser<'a> {
+    pub fn new(tokens: &'a [Token], end_column: usize) -> Self {
+        ExpressionParser {
+            tokens,
+            position: 0,
+            end_column,
+        }
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    fn column(&self) -> usize {
+        self.tokens
+            .get(self.position)
+            .map_or(self.end_column, |token| token.column)
+    }
+
+    pub fn parse(&mut self) -> Result<Expression, (usize, String)> {
+        self.parse_level(0)
+    }
+
+    fn parse_level(&mut self, level: usize) -> Result<Expression, (usize, String)> {
+        if level == NOT_LEVEL {
+            if let Some(token) = self.tokens.get(self.position) {
+                if operator_text(token).as_deref() == Some("NOT") {
+                    self.position += 1;
+                    let operand = self.parse_level(level)?;
+                    return Ok(Expression::Unary(UnaryOperator::Not, Box::new(operand)));
+                }
+            }
+        }
+        if level == BINARY_LEVELS.len() {
+            return self.parse_unary();
+        }
+
+        let mut left = self.parse_level(level + 1)?;
+        while let Some(token) = self.tokens.get(self.position) {
+            let operator = operator_text(token).and_then(|text| {
+                BINARY_LEVELS[level]
+                    .iter()
+                    .find(|(name, _)| *name == text)
+                    .map(|(_, operator)| *operator)
+            });
+            let Some(operator) = operator else {
+                break;
+            };
+            let column = token.column;
+            self.position += 1;
+            let right = self.parse_level(level + 1)?;
+            left = Expression::Binary {
+                operator,
+                column,
+                left: Box::new(left),
+                right: Box::new(right),
+            };
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expression, (usize, String)> {
+        let Some(token) = self.tokens.get(self.position) else {
+            return Err((self.end_column, "Expected an expression".to_owned()));
+        };
+        let operator = match operator_text(token).as_deref() {
+            Some("-") => Some(UnaryOperator::Negate),
+            Some("HIGH") => Some(UnaryOperator::High),
+            Some("LOW") => Some(UnaryOperator::Low),
+            Some("+") => {
+                self.position += 1;
+                return self.parse_unary();
+            }
+            _ => None,
+        };
+        if let Some(operator) = operator {
+            self.position += 1;
+            let operand = self.parse_unary()?;
+            return Ok(Expression::Unary(operator, Box::new(operand)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expression, (usize, String)> {
+        let column = self.column();
+        let Some(token) = self.tokens.get(self.position) else {
+            return Err((column, "Expected an expression".to_owned()));
+        };
+        self.position += 1;
+
+        match &token.kind {
+            TokenKind::Number(value) => Ok(Expression::Number(*value)),
+            TokenKind::Location => Ok(Expression::Location),
+            TokenKind::Identifier(name) => Ok(Expression::Symbol {
+                name: name.clone(),
+                column,
+            }),
+            TokenKind::String(text) => {
+                // One or two characters make a number, the first one being the high byte
+                let bytes = text.as_bytes();
+                match bytes.len() {
+                    1 => Ok(Expression::Number(bytes[0] as i32)),
+                    2 => Ok(Expression::Number(
+                        ((bytes[0] as i32) << 8) | bytes[1] as i32,
+                    )),
+                    _ => Err((
+                        column,
+                        "Only strings of one or two characters have a value".to_owned(),
+                    )),
+                }
+            }
+            TokenKind::LeftParen => {
+                let expression = self.parse()?;
+                match self.tokens.get(self.position) {
+                    Some(Token {
+                        kind: TokenKind::RightParen,
+                        ..
+                    }) => {
+                        self.position += 1;
+                        Ok(expression)
+                    }
+                    _ => Err((self.column(), "Expected `)`".to_owned())),
+                }
+            }
+            _ => Err((column, "Expected an expression".to_owned())),
+        }
+    }
+}
+
+impl Expression {
+    /**
+    Computes the value of the expression on 16 bits, `location` being the value of
+    `$` and `symbol` resolving names.
+
+    Comparisons give `0FFFFH` when true and `0` when false.
+    */
+    pub fn evaluate(
+        &self,
+        location: u16,
+        symbol: &mut impl FnMut(&str, usize) -> Result<i32, (usize, String)>,
+    ) -> Result<i32, (usize, String)> {
+        let value = match self {
+            Expression::Number(value) => *value,
+            Expression::Location => location as i32,
+            Expression::Symbol { name, column } => symbol(name, *column)?,
+            Expression::Unary(operator, operand) => {
+                let operand = operand.evaluate(location, symbol)?;
+                match operator {
+                    UnaryOperator::Negate => operand.wrapping_neg(),
+                    UnaryOperator::Not => !operand,
+                    UnaryOperator::High => (operand >> 8) & 0xff,
+                    UnaryOperator::Low => operand & 0xff,
+                }
+            }
+            Expression::Binary {
+                operator,
+                column,
+                left,
+                right,
+            } => {
+                let left = left.evaluate(location, symbol)?;
+                let right = right.evaluate(location, symbol)?;
+                let boolean = |condition: bool| if condition { 0xffff } else { 0 };
+                match operator {
+                    BinaryOperator::Add => left.wrapping_add(right),
+                    BinaryOperator::Subtract => left.wrapping_sub(right),
+                    BinaryOperator::Multiply => left.wrapping_mul(right),
+                    BinaryOperator::Divide | BinaryOperator::Modulo if right == 0 => {
+                        return Err((*column, "Division by zero".to_owned()));
+                    }
+                    BinaryOperator::Divide => left / right,
+                    BinaryOperator::Modulo => left % right,
+                    BinaryOperator::ShiftLeft => left.checked_shl(right as u32).unwrap_or(0),
+                    BinaryOperator::ShiftRight => {
+                        (left & 0xffff).checked_shr(right as u32).unwrap_or(0)
+                    }
+                    BinaryOperator::And => left & right,
+                    BinaryOperator::Or => left | right,
+                    BinaryOperator::Xor => left ^ right,
+                    BinaryOperator::Equal => boolean(left & 0xffff == right & 0xffff),
+                    BinaryOperator::NotEqual => boolean(left & 0xffff != right & 0xffff),
+                    BinaryOperator::Less => boolean(left < right),
+                    BinaryOperator::LessOrEqual => boolean(left <= right),
+                    BinaryOperator::Greater => boolean(left > right),
+                    BinaryOperator::GreaterOrEqual => boolean(left >= right),
+                }
+            }
+        };
+        // Keep the sign of small negative numbers, so that -1 fits in a byte
+        Ok(if (-0x10000..=0xffff).contains(&value) {
+            value
+        } else {
+            value & 0xffff
+        })
+    }
+}
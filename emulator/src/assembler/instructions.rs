@@ -0,0 +1,206 @@
+use crate::{
+    condition::Condition,
+    instruction::Instruction,
+    register::{Register, RegisterPair},
+};
+
+use super::{expression::Expression, Operand};
+
+/// Mnemonics without a condition code
+const MNEMONICS: &[&str] = &[
+    "MOV", "MVI", "LXI", "LDA", "STA", "LHLD", "SHLD", "LDAX", "STAX", "XCHG", "ADD", "ADI", "ADC",
+    "ACI", "SUB", "SUI", "SBB", "SBI", "INR", "DCR", "INX", "DCX", "DAD", "ANA", "ANI", "ORA",
+    "ORI", "XRA", "XRI", "CMP", "CPI", "DAA", "RLC", "RRC", "RAL", "RAR", "CMA", "CMC", "STC",
+    "JMP", "CALL", "RET", "RST", "PUSH", "POP", "PCHL", "XTHL", "SPHL", "IN", "OUT", "EI", "DI",
+    "HLT", "NOP",
+];
+
+fn condition(text: &str) -> Option<Condition> {
+    text.parse().ok()
+}
+
+/// Whether `mnemonic`, in upper case, names an instruction
+pub(super) fn is_mnemonic(mnemonic: &str) -> bool {
+    if MNEMONICS.contains(&mnemonic) {
+        return true;
+    }
+    match mnemonic.split_at_checked(1) {
+        Some(("J" | "C" | "R", rest)) => condition(rest).is_some(),
+        _ => false,
+    }
+}
+
+/// The name of an operand made of a single symbol, in upper case
+fn name(operand: &Operand) -> Option<String> {
+    match &operand.expression {
+        Expression::Symbol { name, .. } => Some(name.to_ascii_uppercase()),
+        _ => None,
+    }
+}
+
+fn register(operand: &Operand) -> Result<Register, (usize, String)> {
+    name(operand)
+        .and_then(|name| name.parse().ok())
+        .ok_or_else(|| {
+            (
+                operand.column,
+                "Expected a register: A, B, C, D, E, H, L or M".to_owned(),
+            )
+        })
+}
+
+/// A register pair among `allowed`, `PSW` standing for [`RegisterPair::SP`]
+fn register_pair(operand: &Operand, allowed: &[&str]) -> Result<RegisterPair, (usize, String)> {
+    name(operand)
+        .filter(|name| allowed.contains(&name.as_str()))
+        .and_then(|name| name.parse().ok())
+        .ok_or_else(|| {
+            (
+                operand.column,
+                format!("Expected a register pair: {}", allowed.join(", ")),
+            )
+        })
+}
+
+/// The value of `operand`, which must fit in a byte
+fn byte(
+    operand: &Operand,
+    value: &mut impl FnMut(&Operand) -> Result<i32, (usize, String)>,
+) -> Result<u8, (usize, String)> {
+    let value = value(operand)?;
+    if !(-0x100..=0xff).contains(&value) {
+        return Err((
+            operand.column,
+            format!("Value {value} does not fit in a byte"),
+        ));
+    }
+    Ok(value as u8)
+}
+
+/**
+Builds the instruction named by `mnemonic`, in upper case, from its operands.
+
+`value` evaluates numeric operands. Returns `None` when `mnemonic` is not an
+instruction.
+*/
+pub(super) fn build(
+    mnemonic: &str,
+    column: usize,
+    operands: &[Operand],
+    value: &mut impl FnMut(&Operand) -> Result<i32, (usize, String)>,
+) -> Result<Option<Instruction>, (usize, String)> {
+    if !is_mnemonic(mnemonic) {
+        return Ok(None);
+    }
+
+    let expected = match mnemonic {
+        "MOV" | "MVI" | "LXI" => 2,
+        "XCHG" | "DAA" | "RLC" | "RRC" | "RAL" | "RAR" | "CMA" | "CMC" | "STC" | "RET" | "PCHL"
+        | "XTHL" | "SPHL" | "EI" | "DI" | "HLT" | "NOP" => 0,
+        _ if mnemonic.starts_with('R') && condition(&mnemonic[1..]).is_some() => 0,
+        _ => 1,
+    };
+    if operands.len() != expected {
+        let column = operands
+            .get(expected)
+            .map_or(column, |operand| operand.column);
+        return Err((
+            column,
+            format!(
+                "{mnemonic} takes {expected} operand(s), not {}",
+                operands.len()
+            ),
+        ));
+    }
+
+    let pairs = ["B", "D", "H", "SP"];
+
+    let instruction = match mnemonic {
+        "MOV" => match (register(&operands[0])?, register(&operands[1])?) {
+            // Its opcode is HLT
+            (Register::M, Register::M) => {
+                return Err((
+                    operands[1].column,
+                    "MOV M,M is not an instruction".to_owned(),
+                ))
+            }
+            (destination, source) => Instruction::MOV(destination, source),
+        },
+        "MVI" => Instruction::MVI(register(&operands[0])?, byte(&operands[1], value)?),
+        "LXI" => Instruction::LXI(
+            register_pair(&operands[0], &pairs)?,
+            value(&operands[1])? as u16,
+        ),
+        "LDA" => Instruction::LDA(value(&operands[0])? as u16),
+        "STA" => Instruction::STA(value(&operands[0])? as u16),
+        "LHLD" => Instruction::LHLD(value(&operands[0])? as u16),
+        "SHLD" => Instruction::SHLD(value(&operands[0])? as u16),
+        "LDAX" => Instruction::LDAX(register_pair(&operands[0], &["B", "D"])?),
+        "STAX" => Instruction::STAX(register_pair(&operands[0], &["B", "D"])?),
+        "XCHG" => Instruction::XCHG,
+        "ADD" => Instruction::ADD(register(&operands[0])?),
+        "ADI" => Instruction::ADI(byte(&operands[0], value)?),
+        "ADC" => Instruction::ADC(register(&operands[0])?),
+        "ACI" => Instruction::ACI(byte(&operands[0], value)?),
+        "SUB" => Instruction::SUB(register(&operands[0])?),
+        "SUI" => Instruction::SUI(byte(&operands[0], value)?),
+        "SBB" => Instruction::SBB(register(&operands[0])?),
+        "SBI" => Instruction::SBI(byte(&operands[0], value)?),
+        "INR" => Instruction::INR(register(&operands[0])?),
+        "DCR" => Instruction::DCR(register(&operands[0])?),
+        "INX" => Instruction::INX(register_pair(&operands[0], &pairs)?),
+        "DCX" => Instruction::DCX(register_pair(&operands[0], &pairs)?),
+        "DAD" => Instruction::DAD(register_pair(&operands[0], &pairs)?),
+        "ANA" => Instruction::ANA(register(&operands[0])?),
+        "ANI" => Instruction::ANI(byte(&operands[0], value)?),
+        "ORA" => Instruction::ORA(register(&operands[0])?),
+        "ORI" => Instruction::ORI(byte(&operands[0], value)?),
+        "XRA" => Instruction::XRA(register(&operands[0])?),
+        "XRI" => Instruction::XRI(byte(&operands[0], value)?),
+        "CMP" => Instruction::CMP(register(&operands[0])?),
+        "CPI" => Instruction::CPI(byte(&operands[0], value)?),
+        "DAA" => Instruction::DAA,
+        "RLC" => Instruction::RLC,
+        "RRC" => Instruction::RRC,
+        "RAL" => Instruction::RAL,
+        "RAR" => Instruction::RAR,
+        "CMA" => Instruction::CMA,
+        "CMC" => Instruction::CMC,
+        "STC" => Instruction::STC,
+        "JMP" => Instruction::JMP(value(&operands[0])? as u16),
+        "CALL" => Instruction::CALL(value(&operands[0])? as u16),
+        "RET" => Instruction::RET,
+        "RST" => {
+            let vector = value(&operands[0])?;
+            if !(0..8).contains(&vector) {
+                return Err((
+                    operands[0].column,
+                    "RST takes a number from 0 to 7".to_owned(),
+                ));
+            }
+            Instruction::RST(vector as u8)
+        }
+        "PUSH" => Instruction::PUSH(register_pair(&operands[0], &["B", "D", "H", "PSW"])?),
+        "POP" => Instruction::POP(register_pair(&operands[0], &["B", "D", "H", "PSW"])?),
+        "PCHL" => Instruction::PCHL,
+        "XTHL" => Instruction::XTHL,
+        "SPHL" => Instruction::SPHL,
+        "IN" => Instruction::IN(byte(&operands[0], value)?),
+        "OUT" => Instruction::OUT(byte(&operands[0], value)?),
+        "EI" => Instruction::EI,
+        "DI" => Instruction::DI,
+        "HLT" => Instruction::HLT,
+        "NOP" => Instruction::NOP,
+        _ => {
+            let (kind, rest) = mnemonic.split_at(1);
+            // is_mnemonic checked that the rest is a condition
+            let condition = condition(rest).unwrap();
+            match kind {
+                "J" => Instruction::J(condition, value(&operands[0])? as u16),
+                "C" => Instruction::C(condition, value(&operands[0])? as u16),
+                _ => Instruction::R(condition),
+            }
+        }
+    };
+    Ok(Some(instruction))
+}
@@ -0,0 +1,146 @@
+use super::AssemblyError;
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub(super) enum TokenKind {
+    Identifier(String),
+    Number(i32),
+    /// Text in single or double quotes, the quote doubled standing for itself
+    String(String),
+    /// `$` alone, the address of the current line
+    Location,
+    Comma,
+    Colon,
+    LeftParen,
+    RightParen,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub(super) struct Token {
+    pub kind: TokenKind,
+
+    /// Column of the first character, starting from 1
+    pub column: usize,
+}
+
+pub(super) fn is_identifier_start(char: char) -> bool {
+    char.is_ascii_alphabetic() || matches!(char, '_' | '?' | '@' | '.')
+}
+
+pub(super) fn is_identifier_char(char: char) -> bool {
+    is_identifier_start(char) || char.is_ascii_digit()
+}
+
+/// Parses a number with an Intel radix suffix: `H` hex, `B` binary, `O`/`Q` octal, `D` or none decimal, or a `0x` hex prefix
+fn parse_number(text: &str) -> Option<i32> {
+    let upper = text.to_ascii_uppercase();
+    let (digits, radix) = match upper.chars().last()? {
+        _ if upper.starts_with("0X") => (&upper[2..], 16),
+        'H' => (&upper[..upper.len() - 1], 16),
+        'B' => (&upper[..upper.len() - 1], 2),
+        'O' | 'Q' => (&upper[..upper.len() - 1], 8),
+        'D' => (&upper[..upper.len() - 1], 10),
+        _ => (upper.as_str(), 10),
+    };
+    let value = u32::from_str_radix(digits, radix).ok()?;
+    if value > u16::MAX as u32 {
+        return None;
+    }
+    Some(value as i32)
+}
+
+/// Splits one source line, up to its `;` comment, in tokens
+pub(super) fn tokenize(line: &str, line_number: usize) -> Result<Vec<Token>, AssemblyError> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut tokens = vec![];
+    let mut index = 0;
+
+    while index < chars.len() {
+        let char = chars[index];
+        let column = index + 1;
+        let error = |message: String| AssemblyError::new(line_number, column, message);
+
+        if char.is_whitespace() {
+            index += 1;
+            continue;
+        }
+        if char == ';' {
+            break;
+        }
+
+        let kind = if is_identifier_start(char) {
+            let end = chars[index..]
+                .iter()
+                .position(|char| !is_identifier_char(*char))
+                .map_or(chars.len(), |length| index + length);
+            let identifier: String = chars[index..end].iter().collect();
+            index = end;
+            TokenKind::Identifier(identifier)
+        } else if char.is_ascii_digit() {
+            let end = chars[index..]
+                .iter()
+                .position(|char| !char.is_ascii_alphanumeric())
+                .map_or(chars.len(), |length| index + length);
+            let text: String = chars[index..end].iter().collect();
+            index = end;
+            let value = parse_number(&text).ok_or(error(format!("Invalid number `{text}`")))?;
+            TokenKind::Number(value)
+        } else if char == '\'' || char == '"' {
+            let quote = char;
+            let mut text = String::new();
+            index += 1;
+            loop {
+                match (chars.get(index), chars.get(index + 1)) {
+                    (Some(first), Some(second)) if *first == quote && *second == quote => {
+                        text.push(quote);
+                        index += 2;
+                    }
+                    (Some(first), _) if *first == quote => {
+                        index += 1;
+                        break;
+                    }
+                    (Some(char), _) => {
+                        text.push(*char);
+                        index += 1;
+                    }
+                    (None, _) => return Err(error("Unterminated string".to_owned())),
+                }
+            }
+            TokenKind::String(text)
+        } else if char == '$' {
+            // `$` followed by hex digits is a number, as printed by `Instruction`'s `Display`
+            let end = chars[index + 1..]
+                .iter()
+                .position(|char| !char.is_ascii_alphanumeric())
+                .map_or(chars.len(), |length| index + 1 + length);
+            let digits: String = chars[index + 1..end].iter().collect();
+            if !digits.is_empty() && digits.chars().all(|char| char.is_ascii_hexdigit()) {
+                index = end;
+                let value = parse_number(&format!("{digits}H"))
+                    .ok_or(error(format!("Invalid number `${digits}`")))?;
+                TokenKind::Number(value)
+            } else {
+                index += 1;
+                TokenKind::Location
+            }
+        } else {
+            index += 1;
+            match char {
+                ',' => TokenKind::Comma,
+                ':' => TokenKind::Colon,
+                '(' => TokenKind::LeftParen,
+                ')' => TokenKind::RightParen,
+                '+' => TokenKind::Plus,
+                '-' => TokenKind::Minus,
+                '*' => TokenKind::Star,
+                '/' => TokenKind::Slash,
+                _ => return Err(error(format!("Unexpected character `{char}`"))),
+            }
+        };
+        tokens.push(Token { kind, column });
+    }
+    Ok(tokens)
+}
@@ -0,0 +1,733 @@
+mod expression;
+mod instructions;
+mod lexer;
+mod preprocessor;
+
+use std::{
+    collections::HashMap,
+    fmt::{Display, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    instruction::Instruction,
+    register::{Register, RegisterPair},
+};
+
+use expression::{Expression, ExpressionParser};
+use lexer::{tokenize, Token, TokenKind};
+use preprocessor::{Preprocessor, SourceLine};
+
+/// Assembled bytes printed on each line of the listing
+const LISTING_BYTES_PER_LINE: usize = 4;
+
+/// An error in the source, at a line and column starting from 1, or at line 0 when the file cannot be read
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct AssemblyError {
+    /// The file the line is in, `None` for the source given as text
+    pub file: Option<PathBuf>,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl AssemblyError {
+    fn new(line: usize, column: usize, message: String) -> Self {
+        AssemblyError {
+            file: None,
+            line,
+            column,
+            message,
+        }
+    }
+}
+
+impl Display for AssemblyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}:", file.display())?;
+        }
+        if self.line == 0 {
+            return write!(f, " {}", self.message);
+        }
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+/// Machine code assembled from a source
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Assembly {
+    /// Address of the first byte of `bytes`
+    pub origin: u16,
+
+    /// Assembled bytes from the lowest to the highest address, gaps filled with zeros
+    pub bytes: Vec<u8>,
+
+    /// Operand of `END`, the address execution starts at
+    pub start: Option<u16>,
+
+    /// Lines of the listing, with the address, the bytes and the source of each line
+    pub listing: Vec<String>,
+}
+
+/// An expression operand with the column it starts at
+#[derive(Debug, Clone)]
+struct Operand {
+    expression: Expression,
+    column: usize,
+}
+
+#[derive(Debug, Clone)]
+enum Data {
+    /// A string of any length
+    Bytes(Vec<u8>),
+    Value(Operand),
+}
+
+#[derive(Debug, Clone)]
+enum Operation {
+    Instruction {
+        mnemonic: String,
+        column: usize,
+        operands: Vec<Operand>,
+    },
+    Org(Operand),
+    Equ(Operand),
+    Db(Vec<Data>),
+    Dw(Vec<Operand>),
+    Ds(Operand),
+    End(Option<Operand>),
+}
+
+/// A parsed source line
+#[derive(Debug, Clone)]
+struct Statement {
+    label: Option<(String, usize)>,
+    operation: Option<Operation>,
+}
+
+const DIRECTIVES: [&str; 6] = ["ORG", "EQU", "DB", "DW", "DS", "END"];
+
+/// Whether `name` is an instruction or a directive, which cannot start with a label
+fn is_keyword(name: &str) -> bool {
+    let name = name.to_ascii_uppercase();
+    DIRECTIVES.contains(&name.as_str()) || instructions::is_mnemonic(&name)
+}
+
+/**
+Whether `name` belongs to the syntax of the assembler: a register, an
+instruction, a directive or an operator, which other tools cannot take for a symbol.
+*/
+pub(crate) fn is_reserved_word(name: &str) -> bool {
+    let name = name.to_ascii_uppercase();
+    is_keyword(&name)
+        || preprocessor::DIRECTIVES.contains(&name.as_str())
+        || expression::is_operator_word(&name)
+        || name.parse::<Register>().is_ok()
+        || name.parse::<RegisterPair>().is_ok()
+}
+
+fn is_identifier(token: Option<&Token>, expected: &str) -> bool {
+    matches!(token, Some(Token { kind: TokenKind::Identifier(name), .. }) if name.eq_ignore_ascii_case(expected))
+}
+
+/// Parses the whole of `tokens` as one expression
+fn parse_operand(
+    tokens: &[Token],
+    line: usize,
+    end_column: usize,
+) -> Result<Operand, AssemblyError> {
+    let column = tokens.first().map_or(end_column, |token| token.column);
+    let mut parser = ExpressionParser::new(tokens, end_column);
+    let expression = parser
+        .parse()
+        .map_err(|(column, message)| AssemblyError::new(line, column, message))?;
+    if let Some(token) = tokens.get(parser.position()) {
+        return Err(AssemblyError::new(
+            line,
+            token.column,
+            "Expected `,` or the end of the line".to_owned(),
+        ));
+    }
+    Ok(Operand { expression, column })
+}
+
+/// Splits `tokens` at the commas outside of parentheses
+fn split_operands(tokens: &[Token]) -> Vec<&[Token]> {
+    if tokens.is_empty() {
+        return vec![];
+    }
+    let mut operands = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+    for (index, token) in tokens.iter().enumerate() {
+        match token.kind {
+            TokenKind::LeftParen => depth += 1,
+            TokenKind::RightParen => depth -= 1,
+            TokenKind::Comma if depth == 0 => {
+                operands.push(&tokens[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    operands.push(&tokens[start..]);
+    operands
+}
+
+/**
+Parses a line made of an optional label, an optional instruction or directive
+and an optional `;` comment.
+
+Labels end with `:` or start at the first column; the name of an `EQU` needs
+neither.
+*/
+fn parse_statement(text: &str, line: usize) -> Result<Statement, AssemblyError> {
+    let tokens = tokenize(text, line)?;
+    let end_column = text.chars().count() + 1;
+
+    let mut rest = &tokens[..];
+    let mut label = None;
+    if let Some(Token {
+        kind: TokenKind::Identifier(name),
+        column,
+    }) = rest.first()
+    {
+        let colon = matches!(
+            rest.get(1),
+            Some(Token {
+                kind: TokenKind::Colon,
+                ..
+            })
+        );
+        if colon {
+            label = Some((name.clone(), *column));
+            rest = &rest[2..];
+        } else if is_identifier(rest.get(1), "EQU") || (*column == 1 && !is_keyword(name)) {
+            label = Some((name.clone(), *column));
+            rest = &rest[1..];
+        }
+    }
+
+    let Some(first) = rest.first() else {
+        return Ok(Statement {
+            label,
+            operation: None,
+        });
+    };
+    let TokenKind::Identifier(mnemonic) = &first.kind else {
+        return Err(AssemblyError::new(
+            line,
+            first.column,
+            "Expected an instruction or a directive".to_owned(),
+        ));
+    };
+    let mnemonic = mnemonic.to_ascii_uppercase();
+    let column = first.column;
+    let operand_tokens = split_operands(&rest[1..]);
+
+    let operands = |count: usize| -> Result<Vec<Operand>, AssemblyError> {
+        if operand_tokens.len() != count {
+            return Err(AssemblyError::new(
+                line,
+                column,
+                format!("{mnemonic} takes {count} operand(s)"),
+            ));
+        }
+        operand_tokens
+            .iter()
+            .map(|tokens| parse_operand(tokens, line, end_column))
+            .collect()
+    };
+    let single = |operands: Vec<Operand>| operands.into_iter().next().unwrap();
+
+    let operation = match mnemonic.as_str() {
+        "ORG" => Operation::Org(single(operands(1)?)),
+        "DS" => Operation::Ds(single(operands(1)?)),
+        "EQU" => {
+            if label.is_none() {
+                return Err(AssemblyError::new(
+                    line,
+                    column,
+                    "EQU needs a name".to_owned(),
+                ));
+            }
+            Operation::Equ(single(operands(1)?))
+        }
+        "DB" | "DW" if operand_tokens.is_empty() => {
+            return Err(AssemblyError::new(
+                line,
+                column,
+                format!("{mnemonic} takes at least one operand"),
+            ));
+        }
+        "DB" => {
+            let mut data = vec![];
+            for tokens in &operand_tokens {
+                match tokens {
+                    [Token {
+                        kind: TokenKind::String(text),
+                        ..
+                    }] => data.push(Data::Bytes(text.as_bytes().to_vec())),
+                    tokens => data.push(Data::Value(parse_operand(tokens, line, end_column)?)),
+                }
+            }
+            Operation::Db(data)
+        }
+        "DW" => Operation::Dw(operands(operand_tokens.len())?),
+        "END" if operand_tokens.is_empty() => Operation::End(None),
+        "END" => Operation::End(Some(single(operands(1)?))),
+        _ if instructions::is_mnemonic(&mnemonic) => Operation::Instruction {
+            operands: operands(operand_tokens.len())?,
+            mnemonic,
+            column,
+        },
+        _ => {
+            return Err(AssemblyError::new(
+                line,
+                column,
+                format!("Unknown instruction `{mnemonic}`"),
+            ))
+        }
+    };
+
+    Ok(Statement {
+        label,
+        operation: Some(operation),
+    })
+}
+
+#[derive(Debug, Clone)]
+enum Definition {
+    Address(u16),
+
+    /// An `EQU` expression, `location` being the value of `$` on its line
+    Equate {
+        expression: Expression,
+        location: u16,
+    },
+}
+
+#[derive(Default)]
+struct Symbols {
+    definitions: HashMap<String, Definition>,
+}
+
+impl Symbols {
+    fn define(&mut self, name: &str, definition: Definition) -> Result<(), String> {
+        if self.definitions.contains_key(name) {
+            return Err(format!("`{name}` is already defined"));
+        }
+        self.definitions.insert(name.to_owned(), definition);
+        Ok(())
+    }
+
+    fn evaluate(&self, expression: &Expression, location: u16) -> Result<i32, (usize, String)> {
+        self.evaluate_resolving(expression, location, &mut vec![])
+    }
+
+    /// Evaluates `expression`, `resolving` holding the equates being evaluated to detect cycles
+    fn evaluate_resolving(
+        &self,
+        expression: &Expression,
+        location: u16,
+        resolving: &mut Vec<String>,
+    ) -> Result<i32, (usize, String)> {
+        expression.evaluate(
+            location,
+            &mut |name, column| match self.definitions.get(name) {
+                None => Err((column, format!("`{name}` is not defined"))),
+                Some(Definition::Address(address)) => Ok(*address as i32),
+                Some(Definition::Equate {
+                    expression,
+                    location,
+                }) => {
+                    if resolving.iter().any(|resolved| resolved == name) {
+                        return Err((column, format!("`{name}` is defined in terms of itself")));
+                    }
+                    resolving.push(name.to_owned());
+                    let value = self.evaluate_resolving(expression, *location, resolving);
+                    resolving.pop();
+                    value
+                }
+            },
+        )
+    }
+}
+
+/// Size in bytes of what `operation` assembles to, with the values known in the first pass
+fn size(
+    operation: &Operation,
+    location: u16,
+    symbols: &Symbols,
+    line: usize,
+) -> Result<usize, AssemblyError> {
+    let error = |(column, message)| AssemblyError::new(line, column, message);
+    let size = match operation {
+        Operation::Instruction {
+            mnemonic,
+            column,
+            operands,
+        } => {
+            // The size of an instruction does not depend on the value of its operands
+            let instruction = instructions::build(mnemonic, *column, operands, &mut |_| Ok(0))
+                .map_err(error)?
+                .unwrap();
+            instruction.size()
+        }
+        Operation::Db(data) => data
+            .iter()
+            .map(|data| match data {
+                Data::Bytes(bytes) => bytes.len(),
+                Data::Value(_) => 1,
+            })
+            .sum(),
+        Operation::Dw(values) => 2 * values.len(),
+        Operation::Ds(operand) => {
+            let size = symbols
+                .evaluate(&operand.expression, location)
+                .map_err(error)?;
+            if size < 0 {
+                return Err(error((operand.column, format!("Negative size {size}"))));
+            }
+            size as usize
+        }
+        Operation::Org(_) | Operation::Equ(_) | Operation::End(_) => 0,
+    };
+    Ok(size)
+}
+
+/// Parses one instruction whose operands are constant, for the `FromStr` of [`Instruction`]
+pub(crate) fn parse_instruction(text: &str) -> Result<Instruction, String> {
+    // `#` only marks the immediate operands of the `Display` syntax
+    let text = text.trim().replace("#$", "$");
+    // Indented so that the mnemonic is not taken for a label
+    let statement = parse_statement(&format!(" {text}"), 1).map_err(|error| error.message)?;
+    let Statement {
+        label: None,
+        operation:
+            Some(Operation::Instruction {
+                mnemonic,
+                column,
+                operands,
+            }),
+    } = statement
+    else {
+        return Err(format!("`{text}` is not an instruction"));
+    };
+
+    let symbols = Symbols::default();
+    let instruction = instructions::build(&mnemonic, column, &operands, &mut |operand| {
+        symbols.evaluate(&operand.expression, 0)
+    })
+    .map_err(|(_, message)| message)?;
+    // parse_statement only gives mnemonics as instructions
+    Ok(instruction.unwrap())
+}
+
+/// Bytes of one listing line as `C3 D4 18`
+fn hex_bytes(bytes: &[u8]) -> String {
+    let mut text = String::new();
+    for (index, byte) in bytes.iter().enumerate() {
+        if index > 0 {
+            text.push(' ');
+        }
+        let _ = write!(text, "{byte:02X}");
+    }
+    text
+}
+
+/**
+Assembles Intel 8080 `source` in two passes: the first one gives an address to
+every label, the second one evaluates the operands and encodes the instructions.
+
+Besides the instructions, the `ORG`, `EQU`, `DB`, `DW`, `DS` and `END`
+directives are supported. Numbers are decimal, suffixed with `H`, `B`, `O`/`Q`
+or `D` or prefixed with `0x` or `$`, and strings are in single or double
+quotes. `$` alone is the address of the current line and expressions may use
+`+ - * / MOD SHL SHR NOT AND OR XOR HIGH LOW` and `EQ NE LT LE GT GE`.
+
+Sources are preprocessed while the first pass reads them:
+- `name MACRO params` ... `ENDM` defines a macro, used like an instruction.
+  Its parameters are replaced by the arguments of each use and the labels
+  listed by `LOCAL` are renamed in every expansion.
+- `REPT count` ... `ENDM` repeats its lines.
+- `IF expr` ... `ELSE` ... `ENDIF` assembles the lines of the first branch when
+  the expression is not zero. It may only use the symbols defined above it.
+- `INCLUDE file` reads another source, relative to the current directory.
+*/
+pub fn assemble(source: &str) -> Result<Assembly, AssemblyError> {
+    assemble_lines(Preprocessor::new(source, None))
+}
+
+/// Assembles the source in the file at `path`, including files relative to its directory
+pub fn assemble_file(path: &Path) -> Result<Assembly, AssemblyError> {
+    let source = std::fs::read_to_string(path).map_err(|err| AssemblyError {
+        file: Some(path.to_path_buf()),
+        line: 0,
+        column: 0,
+        message: format!("Cannot read the file: {err}"),
+    })?;
+    assemble_lines(Preprocessor::new(&source, Some(path)))
+}
+
+/**
+Assembles `statements` separated by `;`, as written in [`asm8080!`](crate::asm8080).
+Line breaks count as spaces, there are no comments and labels need a colon.
+*/
+pub fn assemble_statements(statements: &str) -> Result<Assembly, AssemblyError> {
+    let mut source = String::new();
+    let mut quote = None;
+    // Indented so that a misspelled instruction is not taken for a label
+    source.push(' ');
+    for char in statements.chars() {
+        match char {
+            '\'' | '"' if quote.is_none_or(|quote| quote == char) => {
+                quote = if quote.is_some() { None } else { Some(char) };
+            }
+            ';' if quote.is_none() => {
+                source.push_str("\n ");
+                continue;
+            }
+            // `stringify!` breaks long lines anywhere
+            '\n' if quote.is_none() => {
+                source.push(' ');
+                continue;
+            }
+            _ => {}
+        }
+        source.push(char);
+    }
+    assemble(&source)
+}
+
+/**
+Assembles 8080 statements into the bytes of the program, from its lowest
+address, panicking on errors. Handy to write test programs:
+
+```
+use emulator::{asm8080, cpu::CPU};
+
+let program = asm8080! {
+    MVI A, 0x10;
+    MVI B, 2;
+    loop: ADD A; DCR B; JNZ loop;
+    STA 0x100;
+    HLT
+};
+let mut cpu = CPU::new();
+cpu.load_program(&program);
+while !cpu.is_halted() {
+    cpu.fetch_decode_execute();
+}
+assert_eq!(cpu.memory()[0x100], 0x40);
+```
+
+Rust only accepts one character between single quotes, longer strings are
+written in double quotes.
+*/
+#[macro_export]
+macro_rules! asm8080 {
+    ($($statements:tt)*) => {
+        match $crate::assembler::assemble_statements(stringify!($($statements)*)) {
+            Ok(assembly) => assembly.bytes,
+            Err(err) => panic!("asm8080!: {err}"),
+        }
+    };
+}
+
+fn assemble_lines(mut preprocessor: Preprocessor) -> Result<Assembly, AssemblyError> {
+    let mut symbols = Symbols::default();
+    // Origin, text, address and parsed statement of each line
+    let mut statements = vec![];
+
+    // First pass
+    let mut location: usize = 0;
+    loop {
+        let start = location as u16;
+        let mut evaluate = |tokens: &[Token], source: &SourceLine| {
+            let line = source.origin.line;
+            let operand = parse_operand(tokens, line, source.text.chars().count() + 1)?;
+            symbols
+                .evaluate(&operand.expression, start)
+                .map_err(|(column, message)| AssemblyError::new(line, column, message))
+        };
+        let Some(source) = preprocessor.next_line(&mut evaluate) else {
+            break;
+        };
+        let source = source?;
+        let origin = source.origin;
+        let text = source.text;
+        let locate = |error: AssemblyError| origin.locate(error);
+        let line = origin.line;
+
+        let statement = parse_statement(&text, line).map_err(locate)?;
+
+        if let Some((name, column)) = &statement.label {
+            let definition = match &statement.operation {
+                Some(Operation::Equ(operand)) => Definition::Equate {
+                    expression: operand.expression.clone(),
+                    location: start,
+                },
+                _ => Definition::Address(start),
+            };
+            symbols
+                .define(name, definition)
+                .map_err(|message| locate(AssemblyError::new(line, *column, message)))?;
+        }
+
+        let mut end = false;
+        match &statement.operation {
+            Some(Operation::Org(operand)) => {
+                let origin =
+                    symbols
+                        .evaluate(&operand.expression, start)
+                        .map_err(|(column, message)| {
+                            locate(AssemblyError::new(line, column, message))
+                        })?;
+                location = (origin & 0xffff) as usize;
+            }
+            Some(Operation::End(_)) => end = true,
+            Some(operation) => {
+                location += size(operation, start, &symbols, line).map_err(locate)?
+            }
+            None => {}
+        }
+        if location > 0x10000 {
+            return Err(locate(AssemblyError::new(
+                line,
+                1,
+                "The program goes past address 0FFFFH".to_owned(),
+            )));
+        }
+
+        statements.push((origin, text, start, statement));
+        if end {
+            break;
+        }
+    }
+
+    // Report the errors of equates on their own line, even when they are not used
+    for (origin, _, start, statement) in &statements {
+        if let Some(Operation::Equ(operand)) = &statement.operation {
+            symbols
+                .evaluate(&operand.expression, *start)
+                .map_err(|(column, message)| {
+                    origin.locate(AssemblyError::new(origin.line, column, message))
+                })?;
+        }
+    }
+
+    // Second pass
+    let mut image: Vec<Option<u8>> = vec![None; 0x10000];
+    let mut start_address = None;
+    let mut listing = vec![];
+    for (origin, text, start, statement) in &statements {
+        let (line, start) = (origin.line, *start);
+        let error = |(column, message)| origin.locate(AssemblyError::new(line, column, message));
+        let mut value = |operand: &Operand| symbols.evaluate(&operand.expression, start);
+
+        let mut bytes: Vec<u8> = vec![];
+        let mut address = match statement.label {
+            Some(_) => format!("{start:04X}"),
+            None => String::new(),
+        };
+        match &statement.operation {
+            Some(Operation::Instruction {
+                mnemonic,
+                column,
+                operands,
+            }) => {
+                let instruction = instructions::build(mnemonic, *column, operands, &mut value)
+                    .map_err(error)?
+                    .unwrap();
+                bytes.extend(instruction.encode());
+                address = format!("{start:04X}");
+            }
+            Some(Operation::Db(data)) => {
+                for data in data {
+                    match data {
+                        Data::Bytes(text) => bytes.extend(text),
+                        Data::Value(operand) => {
+                            let byte = value(operand).map_err(error)?;
+                            if !(-0x100..=0xff).contains(&byte) {
+                                return Err(error((
+                                    operand.column,
+                                    format!("Value {byte} does not fit in a byte"),
+                                )));
+                            }
+                            bytes.push(byte as u8);
+                        }
+                    }
+                }
+                address = format!("{start:04X}");
+            }
+            Some(Operation::Dw(operands)) => {
+                for operand in operands {
+                    let word = value(operand).map_err(error)? as u16;
+                    bytes.extend(word.to_le_bytes());
+                }
+                address = format!("{start:04X}");
+            }
+            Some(Operation::Equ(operand)) => {
+                address = format!("={:04X}", value(operand).map_err(error)? as u16);
+            }
+            Some(Operation::Org(operand)) => {
+                address = format!("{:04X}", value(operand).map_err(error)? as u16);
+            }
+            Some(Operation::Ds(_)) => address = format!("{start:04X}"),
+            Some(Operation::End(Some(operand))) => {
+                start_address = Some(value(operand).map_err(error)? as u16);
+            }
+            Some(Operation::End(None)) | None => {}
+        }
+
+        for (offset, byte) in bytes.iter().enumerate() {
+            let slot = &mut image[start as usize + offset];
+            if slot.is_some() {
+                return Err(origin.locate(AssemblyError::new(
+                    line,
+                    1,
+                    format!(
+                        "Overwrites the byte already assembled at {:04X}H",
+                        start as usize + offset
+                    ),
+                )));
+            }
+            *slot = Some(*byte);
+        }
+
+        let mut chunks = bytes.chunks(LISTING_BYTES_PER_LINE);
+        let first = chunks.next().unwrap_or(&[]);
+        // Expanded lines are marked with a `+` after their line number
+        let expanded = if origin.expansion.is_some() { '+' } else { ' ' };
+        listing.push(format!(
+            "{address:<5} {:<11} {line:>5}{expanded} {text}",
+            hex_bytes(first)
+        ));
+        for (index, chunk) in chunks.enumerate() {
+            let address = start as usize + (index + 1) * LISTING_BYTES_PER_LINE;
+            listing.push(format!("{address:04X}  {}", hex_bytes(chunk)));
+        }
+    }
+
+    let first = image.iter().position(Option::is_some);
+    let last = image.iter().rposition(Option::is_some);
+    let (origin, bytes) = match (first, last) {
+        (Some(first), Some(last)) => (
+            first as u16,
+            image[first..=last]
+                .iter()
+                .map(|byte| byte.unwrap_or(0))
+                .collect(),
+        ),
+        _ => (0, vec![]),
+    };
+
+    Ok(Assembly {
+        origin,
+        bytes,
+        start: start_address,
+        listing,
+    })
+}
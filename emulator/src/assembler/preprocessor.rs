@@ -0,0 +1,507 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use super::{
+    lexer::{is_identifier_char, is_identifier_start, tokenize, Token, TokenKind},
+    AssemblyError,
+};
+
+/// Nested includes and macro expansions allowed before giving up on a recursion
+const MAX_DEPTH: usize = 64;
+
+/// Where a source line comes from
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub(super) struct Origin {
+    /// `None` for the source given as text
+    pub file: Option<PathBuf>,
+    pub line: usize,
+
+    /// The macro or `REPT` block the line was expanded from, as `in macro NAME at line N`
+    pub expansion: Option<String>,
+}
+
+impl Origin {
+    /// Completes `error`, raised on this line, with the file and the expansion it comes from
+    pub fn locate(&self, mut error: AssemblyError) -> AssemblyError {
+        error.file = self.file.clone();
+        if let Some(expansion) = &self.expansion {
+            error.message = format!("{} ({expansion})", error.message);
+        }
+        error
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub(super) struct SourceLine {
+    pub text: String,
+    pub origin: Origin,
+}
+
+struct Macro {
+    parameters: Vec<String>,
+    body: Vec<SourceLine>,
+}
+
+/// State of an `IF` block
+struct Conditional {
+    /// Whether the lines of the current branch are assembled
+    active: bool,
+
+    /// Whether a branch of the block was already assembled, or the whole block is skipped
+    taken: bool,
+
+    in_else: bool,
+}
+
+/// Directives handled while reading the lines, before they are assembled
+pub(super) const DIRECTIVES: [&str; 10] = [
+    "MACRO", "ENDM", "LOCAL", "REPT", "ENDR", "IF", "ELSE", "ENDIF", "INCLUDE", "EXITM",
+];
+
+/**
+Reads the lines of a source, expanding macros, `REPT` blocks and includes and
+skipping the branches of `IF` blocks whose condition is false.
+
+Lines are read one at a time so that conditions see the symbols defined by the
+previous lines.
+*/
+pub(super) struct Preprocessor {
+    /// Lines being read, the innermost include or expansion last, with the index of the next line
+    frames: Vec<(Vec<SourceLine>, usize)>,
+    conditionals: Vec<(Conditional, Origin)>,
+    macros: HashMap<String, Macro>,
+
+    /// Number of local labels made so far, keeping their names unique
+    locals: usize,
+}
+
+/// The lines of `text`, read from `file`
+fn lines_of(text: &str, file: Option<&Path>) -> Vec<SourceLine> {
+    text.lines()
+        .enumerate()
+        .map(|(index, text)| SourceLine {
+            text: text.to_owned(),
+            origin: Origin {
+                file: file.map(Path::to_path_buf),
+                line: index + 1,
+                expansion: None,
+            },
+        })
+        .collect()
+}
+
+/// Splits the arguments of a macro call at the commas outside of quotes and parentheses
+fn split_arguments(text: &str) -> Vec<String> {
+    let mut arguments = vec![];
+    let mut current = String::new();
+    let mut depth = 0;
+    let mut quote = None;
+    for char in text.chars() {
+        let quoted = quote.is_some();
+        match char {
+            '\'' | '"' if quote.is_none_or(|quote| quote == char) => {
+                quote = if quoted { None } else { Some(char) }
+            }
+            '(' if !quoted => depth += 1,
+            ')' if !quoted => depth -= 1,
+            ';' if !quoted => break,
+            ',' if !quoted && depth == 0 => {
+                arguments.push(current.trim().to_owned());
+                current.clear();
+                continue;
+            }
+            _ => {}
+        }
+        current.push(char);
+    }
+    if !current.trim().is_empty() || !arguments.is_empty() {
+        arguments.push(current.trim().to_owned());
+    }
+    arguments
+}
+
+/// Replaces the identifiers of `text` found in `replacements`, outside of strings and comments
+fn substitute(text: &str, replacements: &HashMap<String, String>) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut result = String::new();
+    let mut index = 0;
+    let mut quote = None;
+    while index < chars.len() {
+        let char = chars[index];
+        if char == ';' && quote.is_none() {
+            result.extend(&chars[index..]);
+            break;
+        }
+        if matches!(char, '\'' | '"') && quote.is_none_or(|quote| quote == char) {
+            quote = if quote.is_some() { None } else { Some(char) };
+        }
+        if quote.is_some() || !is_identifier_start(char) {
+            result.push(char);
+            index += 1;
+            continue;
+        }
+
+        let end = chars[index..]
+            .iter()
+            .position(|char| !is_identifier_char(*char))
+            .map_or(chars.len(), |length| index + length);
+        let identifier: String = chars[index..end].iter().collect();
+        match replacements.get(&identifier) {
+            Some(replacement) => result.push_str(replacement),
+            None => result.push_str(&identifier),
+        }
+        index = end;
+    }
+    result
+}
+
+/// The label of a line, the directive or instruction after it in upper case, and its index in `tokens`
+fn split_label<'a>(
+    tokens: &'a [Token],
+    macros: &HashMap<String, Macro>,
+) -> (Option<&'a str>, Option<(String, usize)>) {
+    let identifier = |index: usize| match tokens.get(index) {
+        Some(Token {
+            kind: TokenKind::Identifier(name),
+            ..
+        }) => Some(name.as_str()),
+        _ => None,
+    };
+    let is_colon = matches!(
+        tokens.get(1),
+        Some(Token {
+            kind: TokenKind::Colon,
+            ..
+        })
+    );
+
+    let word_index = match identifier(0) {
+        Some(_) if is_colon => 2,
+        Some(_) if identifier(1).is_some_and(|word| word.eq_ignore_ascii_case("MACRO")) => 1,
+        Some(name) if tokens[0].column == 1 && !is_reserved(name, macros) => 1,
+        _ => 0,
+    };
+    let label = if word_index > 0 { identifier(0) } else { None };
+    let word = identifier(word_index).map(|word| (word.to_ascii_uppercase(), word_index));
+    (label, word)
+}
+
+/// Whether `name` starts a statement rather than labelling it
+fn is_reserved(name: &str, macros: &HashMap<String, Macro>) -> bool {
+    let name = name.to_ascii_uppercase();
+    DIRECTIVES.contains(&name.as_str()) || macros.contains_key(&name) || super::is_keyword(&name)
+}
+
+impl Preprocessor {
+    pub fn new(source: &str, file: Option<&Path>) -> Self {
+        Preprocessor {
+            frames: vec![(lines_of(source, file), 0)],
+            conditionals: vec![],
+            macros: HashMap::new(),
+            locals: 0,
+        }
+    }
+
+    fn active(&self) -> bool {
+        self.conditionals
+            .last()
+            .is_none_or(|(conditional, _)| conditional.active)
+    }
+
+    /**
+    Takes the lines up to the `ENDM` (or `ENDR` for `REPT`) closing the block
+    opened at `origin`, from the innermost frame.
+    */
+    fn take_block(
+        &mut self,
+        origin: &Origin,
+        opener: &str,
+    ) -> Result<Vec<SourceLine>, AssemblyError> {
+        let mut depth = 0;
+        let mut block = vec![];
+        let (lines, index) = self
+            .frames
+            .last_mut()
+            .expect("blocks are opened by a line of a frame");
+        while let Some(line) = lines.get(*index).cloned() {
+            *index += 1;
+
+            let tokens = tokenize(&line.text, line.origin.line)
+                .map_err(|error| line.origin.locate(error))?;
+            let (_, word) = split_label(&tokens, &self.macros);
+            match word.as_ref().map(|(word, _)| word.as_str()) {
+                Some("MACRO" | "REPT") => depth += 1,
+                Some("ENDM" | "ENDR") if depth == 0 => return Ok(block),
+                Some("ENDM" | "ENDR") => depth -= 1,
+                _ => {}
+            }
+            block.push(line);
+        }
+        Err(origin.locate(AssemblyError::new(
+            origin.line,
+            1,
+            format!("{opener} without ENDM"),
+        )))
+    }
+
+    fn push_frame(&mut self, lines: Vec<SourceLine>, origin: &Origin) -> Result<(), AssemblyError> {
+        if self.frames.len() >= MAX_DEPTH {
+            return Err(origin.locate(AssemblyError::new(
+                origin.line,
+                1,
+                "Too many nested includes or macro expansions".to_owned(),
+            )));
+        }
+        self.frames.push((lines, 0));
+        Ok(())
+    }
+
+    /// Expands a call of `name` whose arguments are `arguments`
+    fn expand(
+        &mut self,
+        name: &str,
+        arguments: &str,
+        origin: &Origin,
+    ) -> Result<(), AssemblyError> {
+        let definition = &self.macros[name];
+        let arguments = split_arguments(arguments);
+        if arguments.len() > definition.parameters.len() {
+            return Err(origin.locate(AssemblyError::new(
+                origin.line,
+                1,
+                format!(
+                    "{name} takes {} argument(s), not {}",
+                    definition.parameters.len(),
+                    arguments.len()
+                ),
+            )));
+        }
+
+        let mut replacements: HashMap<String, String> = definition
+            .parameters
+            .iter()
+            .cloned()
+            .zip(
+                arguments
+                    .into_iter()
+                    .chain(std::iter::repeat(String::new())),
+            )
+            .collect();
+
+        let expansion = format!("in macro {name} at line {}", origin.line);
+        let mut lines = vec![];
+        for line in &definition.body {
+            let tokens = tokenize(&line.text, line.origin.line)
+                .map_err(|error| line.origin.locate(error))?;
+            let (_, word) = split_label(&tokens, &self.macros);
+            if let Some(("LOCAL", index)) =
+                word.as_ref().map(|(word, index)| (word.as_str(), *index))
+            {
+                // Every expansion gets its own copy of the local labels
+                for token in &tokens[index + 1..] {
+                    if let TokenKind::Identifier(local) = &token.kind {
+                        self.locals += 1;
+                        replacements.insert(local.clone(), format!("??{:04}", self.locals));
+                    }
+                }
+                continue;
+            }
+            lines.push(SourceLine {
+                text: line.text.clone(),
+                origin: Origin {
+                    expansion: Some(expansion.clone()),
+                    ..line.origin.clone()
+                },
+            });
+        }
+        for line in &mut lines {
+            line.text = substitute(&line.text, &replacements);
+        }
+        self.push_frame(lines, origin)
+    }
+
+    /**
+    The next line to assemble, `None` at the end of the source.
+
+    `evaluate` gives the value of the expression made of the tokens of an `IF` or
+    a `REPT`, on the line at the given origin.
+    */
+    pub fn next_line(
+        &mut self,
+        evaluate: &mut impl FnMut(&[Token], &SourceLine) -> Result<i32, AssemblyError>,
+    ) -> Option<Result<SourceLine, AssemblyError>> {
+        loop {
+            let (lines, index) = self.frames.last_mut()?;
+            let Some(line) = lines.get(*index).cloned() else {
+                self.frames.pop();
+                if self.frames.is_empty() {
+                    if let Some((_, origin)) = self.conditionals.last() {
+                        return Some(Err(origin.locate(AssemblyError::new(
+                            origin.line,
+                            1,
+                            "IF without ENDIF".to_owned(),
+                        ))));
+                    }
+                }
+                continue;
+            };
+            *index += 1;
+
+            match self.process(line, evaluate) {
+                Ok(Some(line)) => return Some(Ok(line)),
+                Ok(None) => continue,
+                Err(error) => return Some(Err(error)),
+            }
+        }
+    }
+
+    /// Handles the directives of `line`, returning it when it is to be assembled
+    fn process(
+        &mut self,
+        line: SourceLine,
+        evaluate: &mut impl FnMut(&[Token], &SourceLine) -> Result<i32, AssemblyError>,
+    ) -> Result<Option<SourceLine>, AssemblyError> {
+        let origin = line.origin.clone();
+        let error = |column: usize, message: String| {
+            origin.locate(AssemblyError::new(origin.line, column, message))
+        };
+        let tokens = tokenize(&line.text, origin.line).map_err(|error| origin.locate(error))?;
+        let (label, word) = split_label(&tokens, &self.macros);
+        let Some((word, index)) = word else {
+            return Ok(self.active().then_some(line));
+        };
+        let column = tokens[index].column;
+        let rest = &tokens[index + 1..];
+
+        // Blocks are tracked in skipped branches too, to find their end
+        match word.as_str() {
+            "IF" => {
+                let conditional = if self.active() {
+                    let condition = evaluate(rest, &line)? != 0;
+                    Conditional {
+                        active: condition,
+                        taken: condition,
+                        in_else: false,
+                    }
+                } else {
+                    Conditional {
+                        active: false,
+                        taken: true,
+                        in_else: false,
+                    }
+                };
+                self.conditionals.push((conditional, origin));
+                return Ok(None);
+            }
+            "ELSE" => {
+                let Some((conditional, _)) = self.conditionals.last_mut() else {
+                    return Err(error(column, "ELSE without IF".to_owned()));
+                };
+                if conditional.in_else {
+                    return Err(error(column, "Second ELSE in the same IF".to_owned()));
+                }
+                conditional.in_else = true;
+                conditional.active = !conditional.taken;
+                return Ok(None);
+            }
+            "ENDIF" => {
+                if self.conditionals.pop().is_none() {
+                    return Err(error(column, "ENDIF without IF".to_owned()));
+                }
+                return Ok(None);
+            }
+            _ => {}
+        }
+        if !self.active() {
+            return Ok(None);
+        }
+
+        match word.as_str() {
+            "MACRO" => {
+                let Some(name) = label else {
+                    return Err(error(column, "MACRO needs a name".to_owned()));
+                };
+                let mut parameters = vec![];
+                for token in rest {
+                    match &token.kind {
+                        TokenKind::Identifier(parameter) => parameters.push(parameter.clone()),
+                        TokenKind::Comma => {}
+                        _ => {
+                            return Err(error(token.column, "Expected a parameter name".to_owned()))
+                        }
+                    }
+                }
+                let name = name.to_ascii_uppercase();
+                let body = self.take_block(&origin, "MACRO")?;
+                self.macros.insert(name, Macro { parameters, body });
+                Ok(None)
+            }
+            "REPT" => {
+                if label.is_some() {
+                    return Err(error(1, "REPT cannot have a label".to_owned()));
+                }
+                let count = evaluate(rest, &line)?;
+                if count < 0 {
+                    return Err(error(column, format!("Negative repeat count {count}")));
+                }
+                let body = self.take_block(&origin, "REPT")?;
+                let expansion = format!("in REPT at line {}", origin.line);
+                let mut lines = vec![];
+                for _ in 0..count {
+                    lines.extend(body.iter().map(|line| SourceLine {
+                        text: line.text.clone(),
+                        origin: Origin {
+                            expansion: Some(expansion.clone()),
+                            ..line.origin.clone()
+                        },
+                    }));
+                }
+                self.push_frame(lines, &origin)?;
+                Ok(None)
+            }
+            "INCLUDE" => {
+                let name = match rest {
+                    [Token {
+                        kind: TokenKind::String(name),
+                        ..
+                    }] => name.clone(),
+                    _ => {
+                        let start: usize =
+                            line.text.chars().take(column - 1).map(char::len_utf8).sum();
+                        let text = &line.text[start + "INCLUDE".len()..];
+                        text.split(';').next().unwrap_or_default().trim().to_owned()
+                    }
+                };
+                if name.is_empty() {
+                    return Err(error(column, "INCLUDE needs a file name".to_owned()));
+                }
+                let path = match &origin.file {
+                    Some(file) => file.parent().unwrap_or(Path::new("")).join(&name),
+                    None => PathBuf::from(&name),
+                };
+                let text = std::fs::read_to_string(&path).map_err(|err| {
+                    error(column, format!("Cannot include {}: {err}", path.display()))
+                })?;
+                self.push_frame(lines_of(&text, Some(&path)), &origin)?;
+                Ok(None)
+            }
+            "ENDM" | "ENDR" => Err(error(column, format!("{word} without MACRO or REPT"))),
+            "LOCAL" => Err(error(column, "LOCAL outside of a macro".to_owned())),
+            // Leaving a macro early is not supported, the rest of its body is assembled
+            "EXITM" => Err(error(column, "EXITM is not supported".to_owned())),
+            _ if self.macros.contains_key(&word) => {
+                let start: usize = line.text.chars().take(column - 1).map(char::len_utf8).sum();
+                let arguments = &line.text[start + word.len()..];
+                self.expand(&word, arguments, &origin)?;
+                // The label names the first address of the expansion
+                Ok(label.map(|label| SourceLine {
+                    text: format!("{label}:"),
+                    origin,
+                }))
+            }
+            _ => Ok(Some(line)),
+        }
+    }
+}
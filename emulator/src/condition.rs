@@ -1,4 +1,4 @@
-use std::fmt::Display;
+use std::{fmt::Display, str::FromStr};
 
 #[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy)]
 pub enum Condition {
@@ -42,6 +42,24 @@ impl Display for Condition {
     }
 }
 
+impl FromStr for Condition {
+    type Err = &'static str;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text.to_ascii_uppercase().as_str() {
+            "NZ" => Ok(Condition::NZ),
+            "Z" => Ok(Condition::Z),
+            "NC" => Ok(Condition::NC),
+            "C" => Ok(Condition::C),
+            "PO" => Ok(Condition::PO),
+            "PE" => Ok(Condition::PE),
+            "P" => Ok(Condition::P),
+            "M" => Ok(Condition::M),
+            _ => Err("Invalid condition code"),
+        }
+    }
+}
+
 impl TryFrom<u8> for Condition {
     type Error = &'static str;
 
@@ -59,3 +77,18 @@ impl TryFrom<u8> for Condition {
         }
     }
 }
+
+impl From<Condition> for u8 {
+    fn from(condition: Condition) -> u8 {
+        match condition {
+            Condition::NZ => 0,
+            Condition::Z => 1,
+            Condition::NC => 2,
+            Condition::C => 3,
+            Condition::PO => 4,
+            Condition::PE => 5,
+            Condition::P => 6,
+            Condition::M => 7,
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use std::collections::VecDeque;
+
+/// A character terminal wired to a machine, such as a serial console
+pub trait Console {
+    /// Whether a character is waiting to be read
+    fn status(&mut self) -> bool;
+
+    /// The next character typed, waiting for one, or `None` once the input is closed
+    fn read(&mut self) -> Option<u8>;
+
+    fn write(&mut self, byte: u8);
+
+    /// Whether the input has ended, so that a machine polling the status can stop
+    fn closed(&self) -> bool {
+        false
+    }
+}
+
+/// A console typing the characters of `input` and collecting the output, for tests and scripts
+#[derive(Default)]
+pub struct BufferConsole {
+    pub input: VecDeque<u8>,
+    pub output: Vec<u8>,
+}
+
+impl BufferConsole {
+    pub fn new(input: &[u8]) -> BufferConsole {
+        BufferConsole {
+            input: input.iter().copied().collect(),
+            output: vec![],
+        }
+    }
+}
+
+impl Console for BufferConsole {
+    fn status(&mut self) -> bool {
+        !self.input.is_empty()
+    }
+
+    fn read(&mut self) -> Option<u8> {
+        self.input.pop_front()
+    }
+
+    fn write(&mut self, byte: u8) {
+        self.output.push(byte);
+    }
+
+    fn closed(&self) -> bool {
+        self.input.is_empty()
+    }
+}
@@ -0,0 +1,228 @@
+use log::warn;
+
+use crate::{
+    console::Console,
+    register::{Register, RegisterPair},
+};
+
+use super::{Cpm, ALLOCATION_VECTOR, CURRENT_DRIVE, DEFAULT_DMA, DISK_PARAMETER_BLOCK, IOBYTE};
+
+/// CP/M 2.2, returned by BDOS function 12
+const VERSION: u16 = 0x0022;
+
+const CTRL_C: u8 = 0x03;
+const BACKSPACE: u8 = 0x08;
+const CR: u8 = 0x0d;
+const LF: u8 = 0x0a;
+const CTRL_U: u8 = 0x15;
+const CTRL_X: u8 = 0x18;
+const DELETE: u8 = 0x7f;
+
+impl<C: Console> Cpm<C> {
+    /// Serves the BDOS function in C, with its parameter in E or DE
+    pub(super) fn bdos(&mut self) {
+        let function = self.cpu.read_register(Register::C);
+        let parameter = self.cpu.read_register(Register::E);
+        let address = self.cpu.read_register_pair(RegisterPair::DE);
+
+        let result = match function {
+            0 => {
+                self.exited = true;
+                return;
+            }
+            1 => match self.console_read() {
+                Some(byte) => {
+                    if is_echoed(byte) {
+                        self.console.write(byte);
+                    }
+                    byte as u16
+                }
+                None => return,
+            },
+            2 => {
+                self.console.write(parameter);
+                0
+            }
+            // The reader is always at the end of file, the punch and printer discard their output
+            3 => 0x1a,
+            4 | 5 => 0,
+            6 => self.direct_console(parameter),
+            7 => self.cpu.memory()[IOBYTE as usize] as u16,
+            8 => {
+                self.cpu.memory_mut()[IOBYTE as usize] = parameter;
+                0
+            }
+            9 => {
+                self.print_string(address);
+                0
+            }
+            10 => {
+                self.read_buffer(address);
+                0
+            }
+            11 => {
+                if self.console.status() {
+                    0xff
+                } else {
+                    0
+                }
+            }
+            12 => VERSION,
+            13 => {
+                self.drive = 0;
+                self.dma = DEFAULT_DMA;
+                0
+            }
+            14 => {
+                if parameter > 15 {
+                    0xff
+                } else {
+                    self.drive = parameter;
+                    0
+                }
+            }
+            15 => self.open(address),
+            16 => self.close(address),
+            17 => self.search_first(address),
+            18 => self.search_next(),
+            19 => self.delete(address),
+            20 => self.read_sequential(address),
+            21 => self.write_sequential(address),
+            22 => self.make(address),
+            23 => self.rename(address),
+            // Every drive is logged in
+            24 => 0xffff,
+            25 => self.drive as u16,
+            26 => {
+                self.dma = address;
+                0
+            }
+            27 => ALLOCATION_VECTOR,
+            // Drives cannot be write protected
+            28 | 29 => 0,
+            30 => self.set_attributes(address),
+            31 => DISK_PARAMETER_BLOCK,
+            32 => {
+                if parameter == 0xff {
+                    self.user as u16
+                } else {
+                    self.user = parameter & 0x0f;
+                    0
+                }
+            }
+            33 => self.read_random(address),
+            34 | 40 => self.write_random(address),
+            35 => self.file_size(address),
+            36 => self.set_random_record(address),
+            37 => 0,
+            _ => {
+                warn!("Unsupported BDOS function {function}");
+                0xff
+            }
+        };
+
+        self.cpu.memory_mut()[CURRENT_DRIVE as usize] = self.drive;
+        self.set_result(result);
+    }
+
+    /// Function 6: reads without echo when E is FF, FE or FD, writes E otherwise
+    fn direct_console(&mut self, parameter: u8) -> u16 {
+        match parameter {
+            0xff => {
+                if self.console.status() {
+                    self.console_read().unwrap_or(0) as u16
+                } else {
+                    0
+                }
+            }
+            0xfe => {
+                if self.console.status() {
+                    0xff
+                } else {
+                    0
+                }
+            }
+            0xfd => self.console_read().unwrap_or(0) as u16,
+            _ => {
+                self.console.write(parameter);
+                0
+            }
+        }
+    }
+
+    /// Function 9: writes the string at `address` up to a `$`
+    fn print_string(&mut self, address: u16) {
+        let mut address = address;
+        loop {
+            let byte = self.cpu.memory()[address as usize];
+            if byte == b'$' {
+                break;
+            }
+            self.console.write(byte);
+            address = address.wrapping_add(1);
+            if address == 0 {
+                break;
+            }
+        }
+    }
+
+    /**
+    Function 10: reads a line into the buffer at `address`, whose first byte is
+    its size, storing the length in the second byte and the characters after it.
+
+    Backspace and delete erase a character, ^U and ^X the whole line, and ^C at
+    the start of the line ends the program.
+    */
+    fn read_buffer(&mut self, address: u16) {
+        let size = self.cpu.memory()[address as usize] as usize;
+        let mut line: Vec<u8> = vec![];
+
+        while line.len() < size {
+            let Some(byte) = self.console_read() else {
+                return;
+            };
+            match byte {
+                CR | LF => break,
+                CTRL_C if line.is_empty() => {
+                    self.exited = true;
+                    return;
+                }
+                BACKSPACE | DELETE => {
+                    if line.pop().is_some() {
+                        self.erase(1);
+                    }
+                }
+                CTRL_U | CTRL_X => {
+                    self.erase(line.len());
+                    line.clear();
+                }
+                _ => {
+                    self.console.write(byte);
+                    line.push(byte);
+                }
+            }
+        }
+        self.console.write(CR);
+
+        let memory = self.cpu.memory_mut();
+        for (offset, byte) in [line.len() as u8].iter().chain(&line).enumerate() {
+            if let Some(slot) = memory.get_mut(address as usize + 1 + offset) {
+                *slot = *byte;
+            }
+        }
+    }
+
+    /// Erases `count` characters before the cursor
+    fn erase(&mut self, count: usize) {
+        for _ in 0..count {
+            for byte in [BACKSPACE, b' ', BACKSPACE] {
+                self.console.write(byte);
+            }
+        }
+    }
+}
+
+/// Whether function 1 echoes `byte`: printable characters and the line control ones
+fn is_echoed(byte: u8) -> bool {
+    byte.is_ascii_graphic() || matches!(byte, b' ' | CR | LF | BACKSPACE | b'\t')
+}
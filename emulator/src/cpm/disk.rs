@@ -0,0 +1,102 @@
+use std::path::{Path, PathBuf};
+
+/// Tracks of an 8" IBM 3740 single sided, single density disk
+pub const TRACKS: usize = 77;
+
+/// Sectors of a track, numbered from 1
+pub const SECTORS_PER_TRACK: usize = 26;
+
+pub const SECTOR_SIZE: usize = 128;
+
+/// Size of a disk image, its sectors stored in track order
+pub const DISK_SIZE: usize = TRACKS * SECTORS_PER_TRACK * SECTOR_SIZE;
+
+/// Byte filling the sectors of a freshly formatted disk, which CP/M reads as an empty directory
+const FORMATTED: u8 = 0xe5;
+
+/// An 8" IBM 3740 disk, kept in memory and written back to its image file by [`Disk::save`]
+pub struct Disk {
+    bytes: Vec<u8>,
+    path: Option<PathBuf>,
+    changed: bool,
+}
+
+impl Disk {
+    /// A freshly formatted disk
+    pub fn blank() -> Disk {
+        Disk {
+            bytes: vec![FORMATTED; DISK_SIZE],
+            path: None,
+            changed: false,
+        }
+    }
+
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Disk, String> {
+        if bytes.len() != DISK_SIZE {
+            return Err(format!(
+                "The image is {} bytes, an 8\" IBM 3740 disk holds {DISK_SIZE}",
+                bytes.len()
+            ));
+        }
+        Ok(Disk {
+            bytes,
+            path: None,
+            changed: false,
+        })
+    }
+
+    /// Reads the image at `path`, a missing file being a blank disk created when saved
+    pub fn open(path: &Path) -> Result<Disk, String> {
+        let mut disk = if path.exists() {
+            let bytes = std::fs::read(path)
+                .map_err(|err| format!("Could not read {}: {err}", path.display()))?;
+            Disk::from_bytes(bytes).map_err(|err| format!("{}: {err}", path.display()))?
+        } else {
+            let mut disk = Disk::blank();
+            disk.changed = true;
+            disk
+        };
+        disk.path = Some(path.to_owned());
+        Ok(disk)
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// The bytes of `sector` of `track`, `None` when the disk has no such sector
+    pub fn read_sector(&self, track: usize, sector: usize) -> Option<&[u8]> {
+        let offset = sector_offset(track, sector)?;
+        Some(&self.bytes[offset..offset + SECTOR_SIZE])
+    }
+
+    /// Writes `bytes` to `sector` of `track`, returning whether the disk has such a sector
+    pub fn write_sector(&mut self, track: usize, sector: usize, bytes: &[u8; SECTOR_SIZE]) -> bool {
+        let Some(offset) = sector_offset(track, sector) else {
+            return false;
+        };
+        self.bytes[offset..offset + SECTOR_SIZE].copy_from_slice(bytes);
+        self.changed = true;
+        true
+    }
+
+    /// Writes the image back to the file it was opened from, if it changed since
+    pub fn save(&mut self) -> Result<(), String> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if self.changed {
+            std::fs::write(path, &self.bytes)
+                .map_err(|err| format!("Could not write {}: {err}", path.display()))?;
+            self.changed = false;
+        }
+        Ok(())
+    }
+}
+
+fn sector_offset(track: usize, sector: usize) -> Option<usize> {
+    if track >= TRACKS || !(1..=SECTORS_PER_TRACK).contains(&sector) {
+        return None;
+    }
+    Some((track * SECTORS_PER_TRACK + sector - 1) * SECTOR_SIZE)
+}
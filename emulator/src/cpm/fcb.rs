@@ -0,0 +1,219 @@
+use std::path::{Path, PathBuf};
+
+/// Size of a record, the unit of CP/M file I/O
+pub const RECORD_SIZE: usize = 128;
+
+/// Records in one extent, 16 KiB
+pub const RECORDS_PER_EXTENT: usize = 128;
+
+/// Extents in one module, the `S2` byte counting modules
+const EXTENTS_PER_MODULE: usize = 32;
+
+// Offsets in a file control block
+const DRIVE: usize = 0;
+const NAME: usize = 1;
+const EXTENT: usize = 12;
+const MODULE: usize = 14;
+const RECORD_COUNT: usize = 15;
+const CURRENT_RECORD: usize = 32;
+const RANDOM_RECORD: usize = 33;
+
+/// Bytes of a file control block with its random record number
+pub const FCB_SIZE: usize = 36;
+
+/// A name and type as stored in file control blocks and directory entries, padded with spaces
+pub type FileName = [u8; 11];
+
+/// A file control block, copied from memory and written back after the call
+pub struct Fcb {
+    pub bytes: [u8; FCB_SIZE],
+}
+
+impl Fcb {
+    /// Reads the block at `address`, the bytes past the end of memory being 0
+    pub fn read(memory: &[u8], address: u16) -> Fcb {
+        let mut bytes = [0; FCB_SIZE];
+        for (offset, byte) in bytes.iter_mut().enumerate() {
+            *byte = memory.get(address as usize + offset).copied().unwrap_or(0);
+        }
+        Fcb { bytes }
+    }
+
+    /// Writes the block back at `address`, leaving out the random record of a 33 byte block
+    pub fn write(&self, memory: &mut [u8], address: u16, size: usize) {
+        for (offset, byte) in self.bytes[..size].iter().enumerate() {
+            if let Some(slot) = memory.get_mut(address as usize + offset) {
+                *slot = *byte;
+            }
+        }
+    }
+
+    /// Drive of the block, 0 for the current one
+    pub fn drive(&self) -> u8 {
+        self.bytes[DRIVE]
+    }
+
+    /// The name without the attribute bits set in the high bit of each character
+    pub fn name(&self) -> FileName {
+        self.name_at(NAME)
+    }
+
+    /// The second name of the block, as used by rename
+    pub fn new_name(&self) -> FileName {
+        self.name_at(NAME + 16)
+    }
+
+    fn name_at(&self, offset: usize) -> FileName {
+        let mut name = [0; 11];
+        for (index, byte) in name.iter_mut().enumerate() {
+            *byte = self.bytes[offset + index] & 0x7f;
+        }
+        name
+    }
+
+    /// Record of the file the next sequential read or write accesses
+    pub fn sequential_record(&self) -> usize {
+        let extent = (self.bytes[MODULE] as usize & 0x3f) * EXTENTS_PER_MODULE
+            + (self.bytes[EXTENT] as usize & 0x1f);
+        extent * RECORDS_PER_EXTENT + self.bytes[CURRENT_RECORD] as usize
+    }
+
+    /// Sets the extent, module and current record of `record`, and the record count of the extent
+    pub fn set_sequential_record(&mut self, record: usize, file_records: usize) {
+        let extent = record / RECORDS_PER_EXTENT;
+        self.bytes[CURRENT_RECORD] = (record % RECORDS_PER_EXTENT) as u8;
+        self.bytes[EXTENT] = (extent % EXTENTS_PER_MODULE) as u8;
+        self.bytes[MODULE] = (extent / EXTENTS_PER_MODULE) as u8;
+        self.set_record_count(extent, file_records);
+    }
+
+    /// Sets the record count of `extent` for a file of `file_records` records
+    pub fn set_record_count(&mut self, extent: usize, file_records: usize) {
+        let records = file_records
+            .saturating_sub(extent * RECORDS_PER_EXTENT)
+            .min(RECORDS_PER_EXTENT);
+        self.bytes[RECORD_COUNT] = records as u8;
+    }
+
+    pub fn random_record(&self) -> usize {
+        u32::from_le_bytes([
+            self.bytes[RANDOM_RECORD],
+            self.bytes[RANDOM_RECORD + 1],
+            self.bytes[RANDOM_RECORD + 2],
+            0,
+        ]) as usize
+    }
+
+    pub fn set_random_record(&mut self, record: usize) {
+        let bytes = (record as u32).to_le_bytes();
+        self.bytes[RANDOM_RECORD..RANDOM_RECORD + 3].copy_from_slice(&bytes[..3]);
+    }
+
+    /// Clears the extent, module and record fields, as done when a file is opened or made
+    pub fn rewind(&mut self, file_records: usize) {
+        self.bytes[EXTENT] = 0;
+        self.bytes[MODULE] = 0;
+        self.bytes[CURRENT_RECORD] = 0;
+        self.set_record_count(0, file_records);
+    }
+
+    /// Points the block at the extent given in it, keeping the current record as CP/M does
+    pub fn open_extent(&mut self, file_records: usize) {
+        let extent = (self.bytes[MODULE] as usize & 0x3f) * EXTENTS_PER_MODULE
+            + (self.bytes[EXTENT] as usize & 0x1f);
+        self.set_record_count(extent, file_records);
+    }
+}
+
+/// The CP/M name of a host file, when it has one: at most 8 characters, a dot and 3 more
+pub fn file_name(host_name: &str) -> Option<FileName> {
+    let (name, extension) = host_name.split_once('.').unwrap_or((host_name, ""));
+    if name.is_empty() || name.len() > 8 || extension.len() > 3 {
+        return None;
+    }
+    if !host_name
+        .chars()
+        .all(|char| char.is_ascii_graphic() && !"<>,;:=?*[]|".contains(char))
+        || extension.contains('.')
+    {
+        return None;
+    }
+
+    let mut file_name = [b' '; 11];
+    for (slot, byte) in file_name.iter_mut().zip(name.bytes()) {
+        *slot = byte.to_ascii_uppercase();
+    }
+    for (slot, byte) in file_name[8..].iter_mut().zip(extension.bytes()) {
+        *slot = byte.to_ascii_uppercase();
+    }
+    Some(file_name)
+}
+
+/// The host name of a CP/M name, as `NAME.TYP`
+pub fn host_name(name: &FileName) -> String {
+    let text = |bytes: &[u8]| String::from_utf8_lossy(bytes).trim_end().to_owned();
+    let (name, extension) = (text(&name[..8]), text(&name[8..]));
+    if extension.is_empty() {
+        name
+    } else {
+        format!("{name}.{extension}")
+    }
+}
+
+/// Whether `name` matches `pattern`, where `?` matches any character
+pub fn matches(pattern: &FileName, name: &FileName) -> bool {
+    pattern
+        .iter()
+        .zip(name)
+        .all(|(pattern, char)| *pattern == b'?' || pattern.eq_ignore_ascii_case(char))
+}
+
+/// The files of `directory` whose CP/M names match `pattern`, sorted by name
+pub fn find(directory: &Path, pattern: &FileName) -> Vec<(FileName, PathBuf)> {
+    let Ok(entries) = std::fs::read_dir(directory) else {
+        return vec![];
+    };
+    let mut files: Vec<(FileName, PathBuf)> = entries
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_file()))
+        .filter_map(|entry| {
+            let name = file_name(entry.file_name().to_str()?)?;
+            matches(pattern, &name).then(|| (name, entry.path()))
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+/**
+Parses a file name typed on the command line, as `B:NAME.TYP`, into a drive
+(0 for the current one) and a name where `*` fills the rest of the field with `?`.
+*/
+pub fn parse_file_name(text: &str) -> (u8, FileName) {
+    let text = text.to_ascii_uppercase();
+    let (drive, text) = match text.as_bytes() {
+        [letter @ b'A'..=b'P', b':', ..] => (letter - b'A' + 1, &text[2..]),
+        _ => (0, text.as_str()),
+    };
+    let (name, extension) = text.split_once('.').unwrap_or((text, ""));
+
+    let mut file_name = [b' '; 11];
+    let fill = |field: &mut [u8], part: &str| {
+        for (index, byte) in part.bytes().take(field.len()).enumerate() {
+            if byte == b'*' {
+                field[index..].fill(b'?');
+                return;
+            }
+            field[index] = byte;
+        }
+    };
+    let (name_field, extension_field) = file_name.split_at_mut(8);
+    fill(name_field, name);
+    fill(extension_field, extension);
+    (drive, file_name)
+}
+
+/// Number of records in a file of `size` bytes, the last one possibly partial
+pub fn records(size: u64) -> usize {
+    (size as usize).div_ceil(RECORD_SIZE)
+}
@@ -0,0 +1,304 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::console::Console;
+
+use super::{
+    fcb::{self, Fcb, FileName, FCB_SIZE, RECORD_SIZE},
+    Cpm,
+};
+
+/// Size of a directory entry returned by the search functions
+const DIRECTORY_ENTRY_SIZE: usize = 32;
+
+/// Block size without the random record, written back by the sequential functions
+const SEQUENTIAL_FCB_SIZE: usize = 33;
+
+/// Largest random record number of CP/M 2.2
+const LAST_RANDOM_RECORD: usize = 0xffff;
+
+/// Returned for a missing file and other errors
+const ERROR: u16 = 0xff;
+
+/// Returned by reads past the end of a file
+const END_OF_FILE: u16 = 1;
+
+/// Returned by random calls with a record past the last one
+const RECORD_OUT_OF_RANGE: u16 = 6;
+
+/// Pads the last record of a file whose size is not a multiple of the record size
+const PADDING: u8 = 0x1a;
+
+impl<C: Console> Cpm<C> {
+    fn read_fcb(&self, address: u16) -> Fcb {
+        Fcb::read(self.cpu.memory(), address)
+    }
+
+    fn write_fcb(&mut self, fcb: &Fcb, address: u16, size: usize) {
+        fcb.write(self.cpu.memory_mut(), address, size);
+    }
+
+    /// The first host file whose name matches the pattern of a block
+    fn host_file(&self, name: &FileName) -> Option<PathBuf> {
+        fcb::find(&self.directory, name)
+            .into_iter()
+            .next()
+            .map(|(_, path)| path)
+    }
+
+    /// The host file of a block, as resolved when it was opened or made
+    fn open_file(&self, name: &FileName) -> Option<PathBuf> {
+        match self.open_files.get(name) {
+            Some(path) => Some(path.clone()),
+            None => self.host_file(name),
+        }
+    }
+
+    /// Copies a record from the DMA address
+    fn dma_record(&self) -> [u8; RECORD_SIZE] {
+        let mut record = [0; RECORD_SIZE];
+        for (offset, byte) in record.iter_mut().enumerate() {
+            *byte = self.cpu.memory()[(self.dma as usize + offset) & 0xffff];
+        }
+        record
+    }
+
+    fn set_dma_bytes(&mut self, bytes: &[u8]) {
+        let dma = self.dma as usize;
+        for (offset, byte) in bytes.iter().enumerate() {
+            self.cpu.memory_mut()[(dma + offset) & 0xffff] = *byte;
+        }
+    }
+
+    /// Function 15
+    pub(super) fn open(&mut self, address: u16) -> u16 {
+        let mut fcb = self.read_fcb(address);
+        let Some(path) = self.host_file(&fcb.name()) else {
+            return ERROR;
+        };
+        fcb.open_extent(file_records(&path));
+        self.write_fcb(&fcb, address, SEQUENTIAL_FCB_SIZE);
+        self.open_files.insert(fcb.name(), path);
+        0
+    }
+
+    /// Function 16, only checking that the file exists since records are written through
+    pub(super) fn close(&mut self, address: u16) -> u16 {
+        match self.open_file(&self.read_fcb(address).name()) {
+            Some(path) if path.exists() => 0,
+            _ => ERROR,
+        }
+    }
+
+    /// Function 17, the next matches being left for function 18
+    pub(super) fn search_first(&mut self, address: u16) -> u16 {
+        let fcb = self.read_fcb(address);
+        let pattern = if fcb.drive() == b'?' {
+            [b'?'; 11]
+        } else {
+            fcb.name()
+        };
+        self.search = fcb::find(&self.directory, &pattern)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        self.search_next()
+    }
+
+    /// Function 18: writes the entry of the next match to the DMA address, as the first of its record
+    pub(super) fn search_next(&mut self) -> u16 {
+        let Some(name) = self.search.pop_front() else {
+            return ERROR;
+        };
+        let records = self
+            .host_file(&name)
+            .map(|path| file_records(&path))
+            .unwrap_or(0);
+
+        let mut entry = [0; DIRECTORY_ENTRY_SIZE];
+        entry[0] = self.user;
+        entry[1..12].copy_from_slice(&name);
+        entry[15] = records.min(fcb::RECORDS_PER_EXTENT) as u8;
+        self.set_dma_bytes(&entry);
+        0
+    }
+
+    /// Function 19, deleting every match of the pattern
+    pub(super) fn delete(&mut self, address: u16) -> u16 {
+        let files = fcb::find(&self.directory, &self.read_fcb(address).name());
+        if files.is_empty() {
+            return ERROR;
+        }
+        for (_, path) in files {
+            self.open_files.retain(|_, open| *open != path);
+            if std::fs::remove_file(path).is_err() {
+                return ERROR;
+            }
+        }
+        0
+    }
+
+    /// Function 20
+    pub(super) fn read_sequential(&mut self, address: u16) -> u16 {
+        let mut fcb = self.read_fcb(address);
+        let Some(path) = self.open_file(&fcb.name()) else {
+            return ERROR;
+        };
+        let record = fcb.sequential_record();
+        let Some(bytes) = read_record(&path, record) else {
+            return END_OF_FILE;
+        };
+        self.set_dma_bytes(&bytes);
+        fcb.set_sequential_record(record + 1, file_records(&path));
+        self.write_fcb(&fcb, address, SEQUENTIAL_FCB_SIZE);
+        0
+    }
+
+    /// Function 21
+    pub(super) fn write_sequential(&mut self, address: u16) -> u16 {
+        let mut fcb = self.read_fcb(address);
+        let Some(path) = self.open_file(&fcb.name()) else {
+            return ERROR;
+        };
+        let record = fcb.sequential_record();
+        if write_record(&path, record, &self.dma_record()).is_err() {
+            return ERROR;
+        }
+        fcb.set_sequential_record(record + 1, file_records(&path));
+        self.write_fcb(&fcb, address, SEQUENTIAL_FCB_SIZE);
+        0
+    }
+
+    /// Function 22, failing rather than emptying a file that already exists
+    pub(super) fn make(&mut self, address: u16) -> u16 {
+        let mut fcb = self.read_fcb(address);
+        let name = fcb.name();
+        if name.contains(&b'?') || self.host_file(&name).is_some() {
+            return ERROR;
+        }
+        let path = self.directory.join(fcb::host_name(&name));
+        if OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .is_err()
+        {
+            return ERROR;
+        }
+        fcb.rewind(0);
+        self.write_fcb(&fcb, address, SEQUENTIAL_FCB_SIZE);
+        self.open_files.insert(name, path);
+        0
+    }
+
+    /// Function 23, the new name being in the second half of the block
+    pub(super) fn rename(&mut self, address: u16) -> u16 {
+        let fcb = self.read_fcb(address);
+        let new_name = fcb.new_name();
+        if new_name.contains(&b'?') || self.host_file(&new_name).is_some() {
+            return ERROR;
+        }
+        let Some(path) = self.host_file(&fcb.name()) else {
+            return ERROR;
+        };
+        self.open_files.retain(|_, open| *open != path);
+        match std::fs::rename(path, self.directory.join(fcb::host_name(&new_name))) {
+            Ok(_) => 0,
+            Err(_) => ERROR,
+        }
+    }
+
+    /// Function 30, host files having no attributes to set
+    pub(super) fn set_attributes(&mut self, address: u16) -> u16 {
+        self.close(address)
+    }
+
+    /// Function 33, pointing the sequential fields at the record read
+    pub(super) fn read_random(&mut self, address: u16) -> u16 {
+        let mut fcb = self.read_fcb(address);
+        let record = fcb.random_record();
+        if record > LAST_RANDOM_RECORD {
+            return RECORD_OUT_OF_RANGE;
+        }
+        let Some(path) = self.open_file(&fcb.name()) else {
+            return ERROR;
+        };
+        let Some(bytes) = read_record(&path, record) else {
+            return END_OF_FILE;
+        };
+        self.set_dma_bytes(&bytes);
+        fcb.set_sequential_record(record, file_records(&path));
+        self.write_fcb(&fcb, address, FCB_SIZE);
+        0
+    }
+
+    /// Functions 34 and 40, the gap before a record past the end reading as zeros
+    pub(super) fn write_random(&mut self, address: u16) -> u16 {
+        let mut fcb = self.read_fcb(address);
+        let record = fcb.random_record();
+        if record > LAST_RANDOM_RECORD {
+            return RECORD_OUT_OF_RANGE;
+        }
+        let Some(path) = self.open_file(&fcb.name()) else {
+            return ERROR;
+        };
+        if write_record(&path, record, &self.dma_record()).is_err() {
+            return ERROR;
+        }
+        fcb.set_sequential_record(record, file_records(&path));
+        self.write_fcb(&fcb, address, FCB_SIZE);
+        0
+    }
+
+    /// Function 35: sets the random record to the number of records of the file
+    pub(super) fn file_size(&mut self, address: u16) -> u16 {
+        let mut fcb = self.read_fcb(address);
+        let Some(path) = self.open_file(&fcb.name()) else {
+            return ERROR;
+        };
+        fcb.set_random_record(file_records(&path));
+        self.write_fcb(&fcb, address, FCB_SIZE);
+        0
+    }
+
+    /// Function 36: sets the random record to the record of the next sequential call
+    pub(super) fn set_random_record(&mut self, address: u16) -> u16 {
+        let mut fcb = self.read_fcb(address);
+        fcb.set_random_record(fcb.sequential_record());
+        self.write_fcb(&fcb, address, FCB_SIZE);
+        0
+    }
+}
+
+fn file_records(path: &Path) -> usize {
+    std::fs::metadata(path)
+        .map(|metadata| fcb::records(metadata.len()))
+        .unwrap_or(0)
+}
+
+/// Reads `record` of the file, `None` past its end
+fn read_record(path: &Path, record: usize) -> Option<[u8; RECORD_SIZE]> {
+    let mut file = File::open(path).ok()?;
+    file.seek(SeekFrom::Start((record * RECORD_SIZE) as u64))
+        .ok()?;
+
+    let mut bytes = [PADDING; RECORD_SIZE];
+    let mut length = 0;
+    while length < RECORD_SIZE {
+        match file.read(&mut bytes[length..]) {
+            Ok(0) => break,
+            Ok(read) => length += read,
+            Err(_) => return None,
+        }
+    }
+    (length > 0).then_some(bytes)
+}
+
+fn write_record(path: &Path, record: usize, bytes: &[u8; RECORD_SIZE]) -> std::io::Result<()> {
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    file.seek(SeekFrom::Start((record * RECORD_SIZE) as u64))?;
+    file.write_all(bytes)
+}
@@ -0,0 +1,258 @@
+mod bdos;
+mod disk;
+mod fcb;
+mod files;
+mod system;
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    path::{Path, PathBuf},
+};
+
+use log::warn;
+
+use crate::{
+    console::Console,
+    cpu::{NullBus, CPU},
+    register::{Register, RegisterPair},
+};
+
+use fcb::FileName;
+
+pub use disk::{Disk, DISK_SIZE};
+pub use system::{CpmSystem, DRIVES};
+
+/// Address `.COM` programs are loaded at and started from, the start of the transient program area
+pub const TPA: u16 = 0x0100;
+
+/// The BDOS entry the `JMP` at 0005 leads to, also the top of the transient program area
+const BDOS: u16 = 0xfe06;
+
+/// Initial stack pointer, holding the return address to 0000 just below the BDOS page
+const STACK_TOP: u16 = (BDOS & 0xff00) - 2;
+
+/// The BIOS jump table, whose address programs find in the warm boot `JMP` at 0000
+const BIOS: u16 = 0xff00;
+const BIOS_ENTRIES: u16 = 17;
+
+/// Disk description returned by BDOS functions 31 and 27, a 2 MiB drive with 2 KiB blocks
+const DISK_PARAMETER_BLOCK: u16 = 0xff40;
+const ALLOCATION_VECTOR: u16 = 0xfe80;
+const DISK_PARAMETERS: [u8; 15] = [
+    64, 0, // Sectors per track
+    4, 15, 0, // Block shift, mask and extent mask
+    0xff, 0x03, // Last block
+    0xff, 0x01, // Last directory entry
+    0xff, 0x00, // Blocks of the directory
+    0, 0, // Check vector size
+    0, 0, // Reserved tracks
+];
+
+// The zero page
+const IOBYTE: u16 = 0x0003;
+const CURRENT_DRIVE: u16 = 0x0004;
+const BDOS_JUMP: u16 = 0x0005;
+const FCB1: u16 = 0x005c;
+const FCB2: u16 = 0x006c;
+const COMMAND_TAIL: u16 = 0x0080;
+
+/// Default DMA address, where records are read and written
+const DEFAULT_DMA: u16 = 0x0080;
+
+const JMP: u8 = 0xc3;
+const RET: u8 = 0xc9;
+
+/**
+A CP/M 2.2 environment running a `.COM` program, whose BDOS calls are served by
+the emulator: console calls go to a [`Console`] and file calls to the files of a
+host directory, which every drive letter refers to.
+
+The program ends when it jumps to the warm boot vector at 0000, calls BDOS
+function 0, halts, or reads from a closed console.
+*/
+pub struct Cpm<C: Console> {
+    cpu: CPU,
+    console: C,
+    directory: PathBuf,
+    dma: u16,
+    drive: u8,
+    user: u8,
+
+    /// Names left to return by BDOS function 18, search for next
+    search: VecDeque<FileName>,
+
+    /// Host files of the names opened or made, so that record accesses do not search the directory
+    open_files: BTreeMap<FileName, PathBuf>,
+
+    exited: bool,
+}
+
+impl<C: Console> Cpm<C> {
+    /**
+    Loads `program` at 0100 and sets up the zero page for the command line
+    `arguments`: the first two are parsed into the default file control blocks
+    and all of them make the command tail.
+    */
+    pub fn new(
+        program: &[u8],
+        arguments: &[String],
+        directory: &Path,
+        console: C,
+    ) -> Result<Cpm<C>, String> {
+        let available = (STACK_TOP - TPA) as usize;
+        if program.len() > available {
+            return Err(format!(
+                "The program is {} bytes, the transient program area holds {available}",
+                program.len()
+            ));
+        }
+
+        let mut cpu = CPU::new();
+        cpu.load_at(TPA, program)?;
+
+        let [bios_low, bios_high] = (BIOS + 3).to_le_bytes();
+        let [bdos_low, bdos_high] = BDOS.to_le_bytes();
+        cpu.load_at(0, &[JMP, bios_low, bios_high])?;
+        cpu.load_at(BDOS_JUMP, &[JMP, bdos_low, bdos_high])?;
+        cpu.load_at(BDOS, &[RET])?;
+        for entry in 0..BIOS_ENTRIES {
+            cpu.load_at(BIOS + entry * 3, &[RET])?;
+        }
+        cpu.load_at(DISK_PARAMETER_BLOCK, &DISK_PARAMETERS)?;
+        // The blocks of the directory are in use
+        cpu.load_at(ALLOCATION_VECTOR, &[0xff])?;
+
+        for (address, argument) in [(FCB1, arguments.first()), (FCB2, arguments.get(1))] {
+            let (drive, name) = argument
+                .map(|argument| fcb::parse_file_name(argument))
+                .unwrap_or((0, [b' '; 11]));
+            cpu.load_at(address, &[drive])?;
+            cpu.load_at(address + 1, &name)?;
+        }
+
+        let mut tail: Vec<u8> = arguments
+            .iter()
+            .flat_map(|argument| format!(" {argument}").into_bytes())
+            .map(|byte| byte.to_ascii_uppercase())
+            .collect();
+        tail.truncate(127);
+        cpu.load_at(COMMAND_TAIL, &[tail.len() as u8])?;
+        cpu.load_at(COMMAND_TAIL + 1, &tail)?;
+
+        // Returning from the program jumps to the warm boot vector
+        cpu.load_at(STACK_TOP, &[0, 0])?;
+        cpu.write_register_pair(RegisterPair::SP, STACK_TOP);
+        cpu.set_program_counter(TPA);
+
+        Ok(Cpm {
+            cpu,
+            console,
+            directory: directory.to_owned(),
+            dma: DEFAULT_DMA,
+            drive: 0,
+            user: 0,
+            search: VecDeque::new(),
+            open_files: BTreeMap::new(),
+            exited: false,
+        })
+    }
+
+    pub fn cpu(&self) -> &CPU {
+        &self.cpu
+    }
+
+    pub fn console(&self) -> &C {
+        &self.console
+    }
+
+    /// Executes one instruction, serving the BDOS or BIOS call it enters, and returns whether the program is still running
+    pub fn step(&mut self) -> bool {
+        if self.exited || self.cpu.is_halted() {
+            return false;
+        }
+
+        let program_counter = self.cpu.program_counter();
+        if program_counter == BDOS {
+            self.bdos();
+        } else if let Some(entry) = bios_entry(program_counter) {
+            self.bios(entry);
+        }
+        if self.exited {
+            return false;
+        }
+
+        self.cpu.step(&mut NullBus);
+        true
+    }
+
+    /// Runs the program until it ends
+    pub fn run(&mut self) {
+        while self.step() {}
+    }
+
+    /// Returns `value` in HL, and its bytes in A and B as CP/M 1 programs expect
+    fn set_result(&mut self, value: u16) {
+        let [low, high] = value.to_le_bytes();
+        self.cpu.write_register_pair(RegisterPair::HL, value);
+        self.cpu.write_register(Register::A, low);
+        self.cpu.write_register(Register::B, high);
+    }
+
+    /// The next character typed, ending the program when the console is closed
+    fn console_read(&mut self) -> Option<u8> {
+        let byte = self.console.read();
+        if byte.is_none() {
+            self.exited = true;
+        }
+        byte
+    }
+
+    /// Serves the call of a program jumping straight into the BIOS, for the console only
+    fn bios(&mut self, entry: u16) {
+        match entry {
+            // BOOT and WBOOT
+            0 | 1 => self.exited = true,
+            _ => {
+                if !device_call(&mut self.cpu, &mut self.console, entry, &mut self.exited) {
+                    warn!("BIOS disk call {entry} is not supported on a host directory");
+                    self.cpu.write_register(Register::A, 0xff);
+                }
+            }
+        }
+    }
+}
+
+/**
+Serves the console, printer, punch and reader entries of the BIOS, returning
+whether `entry` is one of them. Reading from a closed console sets `exited`.
+*/
+fn device_call(cpu: &mut CPU, console: &mut impl Console, entry: u16, exited: &mut bool) -> bool {
+    match entry {
+        // CONST
+        2 => {
+            let status = if console.status() { 0xff } else { 0 };
+            cpu.write_register(Register::A, status);
+        }
+        // CONIN
+        3 => match console.read() {
+            Some(byte) => cpu.write_register(Register::A, byte & 0x7f),
+            None => *exited = true,
+        },
+        // CONOUT
+        4 => console.write(cpu.read_register(Register::C)),
+        // LIST and PUNCH
+        5 | 6 => {}
+        // READER, always at the end of file
+        7 => cpu.write_register(Register::A, 0x1a),
+        // LISTST, the printer is always ready
+        15 => cpu.write_register(Register::A, 0xff),
+        _ => return false,
+    }
+    true
+}
+
+/// Entry of the BIOS jump table at `address`, if any
+fn bios_entry(address: u16) -> Option<u16> {
+    let offset = address.checked_sub(BIOS)?;
+    (offset.is_multiple_of(3) && offset / 3 < BIOS_ENTRIES).then_some(offset / 3)
+}
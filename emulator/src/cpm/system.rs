@@ -0,0 +1,319 @@
+use log::warn;
+
+use crate::{
+    console::Console,
+    cpu::{NullBus, CPU},
+    register::{Register, RegisterPair},
+};
+
+use super::{
+    device_call,
+    disk::{Disk, SECTORS_PER_TRACK, SECTOR_SIZE},
+    BDOS_JUMP, CURRENT_DRIVE, DEFAULT_DMA, IOBYTE, JMP, RET,
+};
+
+/// Drives A to D
+pub const DRIVES: usize = 4;
+
+/// Sectors of the CCP and BDOS, reloaded from the second sector of track 0 at every boot
+const SYSTEM_SECTORS: usize = 44;
+
+/// Where the first `JMP` of a CP/M 2.2 CCP leads, from the start of the CCP
+const CCP_START: u16 = 0x035c;
+
+/// Offsets of the BDOS entry and the BIOS jump table from the start of the CCP
+const BDOS_ENTRY: u16 = 0x0806;
+const BIOS: u16 = 0x1600;
+const BIOS_ENTRIES: u16 = 17;
+
+// Offsets in the BIOS, past the jump table
+const STUBS: u16 = 0x33;
+const TRANSLATION_TABLE: u16 = 0x48;
+const DISK_PARAMETER_BLOCK: u16 = 0x68;
+const DISK_PARAMETER_HEADERS: u16 = 0x80;
+const DIRECTORY_BUFFER: u16 = 0x100;
+const CHECK_VECTORS: u16 = 0x180;
+const ALLOCATION_VECTORS: u16 = 0x1c0;
+const BIOS_SIZE: usize = 0x240;
+
+const CHECK_VECTOR_SIZE: u16 = 16;
+const ALLOCATION_VECTOR_SIZE: u16 = 32;
+
+/// The sector skew of the standard format, logical sector to physical sector
+const TRANSLATION: [u8; SECTORS_PER_TRACK] = [
+    1, 7, 13, 19, 25, 5, 11, 17, 23, 3, 9, 15, 21, 2, 8, 14, 20, 26, 6, 12, 18, 24, 4, 10, 16, 22,
+];
+
+/// The standard format: 243 blocks of 1 KiB and 64 directory entries after the reserved tracks
+const DISK_PARAMETERS: [u8; 15] = [
+    26, 0, // Sectors per track
+    3, 7, 0, // Block shift, mask and extent mask
+    242, 0, // Last block
+    63, 0, // Last directory entry
+    0xc0, 0x00, // Blocks of the directory
+    16, 0, // Check vector size
+    2, 0, // Reserved tracks
+];
+
+/**
+A CP/M 2.2 system booted from 8" IBM 3740 disk images: the CCP and BDOS are
+loaded from the system tracks of drive A and run as they are, while the BIOS
+they call is served by the emulator.
+
+Sectors are read and written in the disk images in memory, [`CpmSystem::save`]
+writing them back to their files. Warm boots save them too.
+*/
+pub struct CpmSystem<C: Console> {
+    cpu: CPU,
+    console: C,
+    disks: Vec<Option<Disk>>,
+
+    /// Address the CCP is loaded at, which the system was generated for
+    ccp: u16,
+
+    // The sector the next read or write accesses
+    drive: usize,
+    track: u16,
+    sector: u16,
+    dma: u16,
+
+    exited: bool,
+}
+
+impl<C: Console> CpmSystem<C> {
+    /// Cold boots from the first of `disks`, which go in drives A, B and so on
+    pub fn new(disks: Vec<Disk>, console: C) -> Result<CpmSystem<C>, String> {
+        if disks.is_empty() || disks.len() > DRIVES {
+            return Err(format!("Between 1 and {DRIVES} disks are needed"));
+        }
+
+        let system = system_sectors(&disks[0]);
+        let ccp = match system[..3] {
+            [JMP, low, high] => u16::from_le_bytes([low, high]).wrapping_sub(CCP_START),
+            _ => 0xffff,
+        };
+        if ccp & 0x00ff != 0 || ccp as usize + BIOS as usize + BIOS_SIZE > 0x10000 {
+            return Err("The system tracks of drive A do not hold a CP/M 2.2 CCP".to_owned());
+        }
+
+        let mut disks: Vec<Option<Disk>> = disks.into_iter().map(Some).collect();
+        disks.resize_with(DRIVES, || None);
+        let mut system = CpmSystem {
+            cpu: CPU::new(),
+            console,
+            disks,
+            ccp,
+            drive: 0,
+            track: 0,
+            sector: 1,
+            dma: DEFAULT_DMA,
+            exited: false,
+        };
+        system.boot(true);
+        Ok(system)
+    }
+
+    pub fn cpu(&self) -> &CPU {
+        &self.cpu
+    }
+
+    pub fn console(&self) -> &C {
+        &self.console
+    }
+
+    /// The disk in `drive`, 0 being A
+    pub fn disk(&self, drive: usize) -> Option<&Disk> {
+        self.disks.get(drive)?.as_ref()
+    }
+
+    /// Writes the disks changed since the last save back to their image files
+    pub fn save(&mut self) -> Result<(), String> {
+        for disk in self.disks.iter_mut().flatten() {
+            disk.save()?;
+        }
+        Ok(())
+    }
+
+    /// Executes one instruction, serving the BIOS call it enters, and returns whether the system is still running
+    pub fn step(&mut self) -> bool {
+        if self.exited || self.cpu.is_halted() {
+            return false;
+        }
+
+        let stubs = self.ccp + BIOS + STUBS;
+        let program_counter = self.cpu.program_counter();
+        if (stubs..stubs + BIOS_ENTRIES).contains(&program_counter) {
+            self.bios(program_counter - stubs);
+        }
+        if self.exited {
+            return false;
+        }
+
+        self.cpu.step(&mut NullBus);
+        true
+    }
+
+    /// Runs the system until the console is closed
+    pub fn run(&mut self) {
+        while self.step() {}
+    }
+
+    /**
+    Loads the CCP and BDOS from drive A, sets up the BIOS and the zero page and
+    starts the CCP on the current drive, drive A after a cold boot.
+    */
+    fn boot(&mut self, cold: bool) {
+        let system = system_sectors(self.disks[0].as_ref().expect("Drive A has a disk"));
+        let ccp = self.ccp;
+        let bios = ccp + BIOS;
+        let memory = self.cpu.memory_mut();
+        let address = |offset: u16| (bios + offset).to_le_bytes();
+
+        memory[ccp as usize..ccp as usize + system.len()].copy_from_slice(&system);
+
+        // Each entry of the jump table leads to a RET, where the call is trapped
+        for entry in 0..BIOS_ENTRIES {
+            let [low, high] = address(STUBS + entry);
+            let slot = (bios + entry * 3) as usize;
+            memory[slot..slot + 3].copy_from_slice(&[JMP, low, high]);
+            memory[(bios + STUBS + entry) as usize] = RET;
+        }
+
+        let translation = (bios + TRANSLATION_TABLE) as usize;
+        memory[translation..translation + TRANSLATION.len()].copy_from_slice(&TRANSLATION);
+        let parameters = (bios + DISK_PARAMETER_BLOCK) as usize;
+        memory[parameters..parameters + DISK_PARAMETERS.len()].copy_from_slice(&DISK_PARAMETERS);
+
+        for drive in 0..DRIVES as u16 {
+            // The three scratch words are the BDOS's own
+            let words = [
+                address(TRANSLATION_TABLE),
+                [0, 0],
+                [0, 0],
+                [0, 0],
+                address(DIRECTORY_BUFFER),
+                address(DISK_PARAMETER_BLOCK),
+                address(CHECK_VECTORS + drive * CHECK_VECTOR_SIZE),
+                address(ALLOCATION_VECTORS + drive * ALLOCATION_VECTOR_SIZE),
+            ];
+            let header = (bios + DISK_PARAMETER_HEADERS + drive * 16) as usize;
+            memory[header..header + 16].copy_from_slice(words.as_flattened());
+        }
+
+        let [wboot_low, wboot_high] = address(3);
+        let [bdos_low, bdos_high] = (ccp + BDOS_ENTRY).to_le_bytes();
+        memory[..3].copy_from_slice(&[JMP, wboot_low, wboot_high]);
+        let jump = BDOS_JUMP as usize;
+        memory[jump..jump + 3].copy_from_slice(&[JMP, bdos_low, bdos_high]);
+        if cold {
+            memory[IOBYTE as usize] = 0;
+            memory[CURRENT_DRIVE as usize] = 0;
+        }
+        let current_drive = memory[CURRENT_DRIVE as usize];
+
+        self.dma = DEFAULT_DMA;
+        self.cpu.write_register(Register::C, current_drive);
+        self.cpu.write_register_pair(RegisterPair::SP, DEFAULT_DMA);
+        self.cpu.set_program_counter(ccp);
+    }
+
+    fn bios(&mut self, entry: u16) {
+        let bc = self.cpu.read_register_pair(RegisterPair::BC);
+        match entry {
+            // BOOT
+            0 => self.boot(true),
+            // WBOOT
+            1 => {
+                if let Err(err) = self.save() {
+                    warn!("{err}");
+                }
+                self.boot(false);
+            }
+            // HOME
+            8 => self.track = 0,
+            // SELDSK
+            9 => {
+                let drive = self.cpu.read_register(Register::C) as usize;
+                let header = if self.disk(drive).is_some() {
+                    self.drive = drive;
+                    self.ccp + BIOS + DISK_PARAMETER_HEADERS + drive as u16 * 16
+                } else {
+                    0
+                };
+                self.cpu.write_register_pair(RegisterPair::HL, header);
+            }
+            // SETTRK
+            10 => self.track = bc,
+            // SETSEC
+            11 => self.sector = bc,
+            // SETDMA
+            12 => self.dma = bc,
+            // READ
+            13 => {
+                let result = self.read_sector();
+                self.cpu.write_register(Register::A, result);
+            }
+            // WRITE
+            14 => {
+                let result = self.write_sector();
+                self.cpu.write_register(Register::A, result);
+            }
+            // SECTRAN
+            16 => {
+                let table = self.cpu.read_register_pair(RegisterPair::DE);
+                let sector = if table == 0 {
+                    bc
+                } else {
+                    self.cpu.memory()[table.wrapping_add(bc) as usize] as u16
+                };
+                self.cpu.write_register_pair(RegisterPair::HL, sector);
+            }
+            _ => {
+                device_call(&mut self.cpu, &mut self.console, entry, &mut self.exited);
+            }
+        }
+    }
+
+    /// Copies the current sector to the DMA address, returning 0 or 1 for an error as READ does
+    fn read_sector(&mut self) -> u8 {
+        let Some(disk) = self.disks[self.drive].as_ref() else {
+            return 1;
+        };
+        let Some(bytes) = disk.read_sector(self.track as usize, self.sector as usize) else {
+            return 1;
+        };
+        let dma = self.dma as usize;
+        let memory = self.cpu.memory_mut();
+        for (offset, byte) in bytes.iter().enumerate() {
+            memory[(dma + offset) & 0xffff] = *byte;
+        }
+        0
+    }
+
+    fn write_sector(&mut self) -> u8 {
+        let mut bytes = [0; SECTOR_SIZE];
+        for (offset, byte) in bytes.iter_mut().enumerate() {
+            *byte = self.cpu.memory()[(self.dma as usize + offset) & 0xffff];
+        }
+        let Some(disk) = self.disks[self.drive].as_mut() else {
+            return 1;
+        };
+        if disk.write_sector(self.track as usize, self.sector as usize, &bytes) {
+            0
+        } else {
+            1
+        }
+    }
+}
+
+/// The CCP and BDOS, in the sectors following the cold boot loader
+fn system_sectors(disk: &Disk) -> Vec<u8> {
+    (1..=SYSTEM_SECTORS)
+        .flat_map(|index| {
+            let (track, sector) = (index / SECTORS_PER_TRACK, index % SECTORS_PER_TRACK + 1);
+            disk.read_sector(track, sector)
+                .expect("The system tracks are on every disk")
+                .to_vec()
+        })
+        .collect()
+}
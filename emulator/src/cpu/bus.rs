@@ -0,0 +1,22 @@
+/// Port-mapped I/O as seen by the CPU through the `IN` and `OUT` instructions.
+///
+/// Every machine built around the 8080 wires its own hardware to the 256
+/// available ports, so the CPU only knows how to talk to this trait.
+pub trait Bus {
+    /// Value read by `IN port`
+    fn input(&mut self, port: u8) -> u8;
+
+    /// Value written by `OUT port`
+    fn output(&mut self, port: u8, value: u8);
+}
+
+/// A bus with nothing attached: reads return 0 and writes are discarded.
+pub struct NullBus;
+
+impl Bus for NullBus {
+    fn input(&mut self, _port: u8) -> u8 {
+        0
+    }
+
+    fn output(&mut self, _port: u8, _value: u8) {}
+}
@@ -1,14 +1,16 @@
-use std::ops::BitAnd;
+use std::ops::{BitAnd, BitOr, BitXor, RangeInclusive};
 
 use log::trace;
 
 use crate::{
     condition::Condition,
-    instruction::Instruction,
+    instruction::{opcode_cycles, Instruction},
     parser::InstructionParser,
     register::{Register, RegisterPair},
 };
 
+use super::bus::{Bus, NullBus};
+
 pub struct CPU {
     /// Registers stored in this order:
     ///
@@ -17,26 +19,39 @@ pub struct CPU {
     stack_pointer: u16,
     program_counter: u16,
     memory: Vec<u8>,
+    /// Ranges of ROM or missing RAM the program cannot write
+    read_only: Vec<RangeInclusive<u16>>,
+    interrupts_enabled: bool,
+    halted: bool,
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
 enum FlagMask {
     S = 0x80,
     Z = 0x40,
-    _A = 0x10,
+    A = 0x10,
     P = 0x04,
     C = 0x01,
 }
 
+impl Default for CPU {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl CPU {
     pub fn new() -> Self {
-        let mut memory = Vec::with_capacity(1024 * 64);
-        memory.resize(1024 * 64, 0);
+        let memory = vec![0; 1024 * 64];
         CPU {
-            registers: [0; 8],
+            // Bit 1 of the flags register always reads as 1
+            registers: [0x02, 0, 0, 0, 0, 0, 0, 0],
             stack_pointer: 0xffff,
             program_counter: 0,
             memory,
+            read_only: vec![],
+            interrupts_enabled: false,
+            halted: false,
         }
     }
 
@@ -68,35 +83,105 @@ impl CPU {
     fn set_register(&mut self, register: Register, value: u8, insn: &Instruction) {
         if register == Register::M {
             let addr = self.register_pair(RegisterPair::HL, insn);
-            self.memory[addr as usize] = value;
+            self.write_byte(addr, value);
             return;
         }
         self.registers[self.register_to_internal_index(register)] = value;
     }
 
-    pub fn load_program(&mut self, program: &Vec<u8>) {
-        for (index, value) in program.iter().enumerate() {
-            self.memory[index] = *value;
+    /// Copies `program` to address 0, panicking when it does not fit in memory
+    pub fn load_program(&mut self, program: &[u8]) {
+        if let Err(err) = self.load_at(0, program) {
+            panic!("{err}");
         }
     }
 
-    fn stack_push(&mut self, value: u16) {
-        let high_byte = ((value & 0xff00) >> 8) as u8;
-        let low_byte = (value & 0x00ff) as u8;
-        let stack_pointer = self.stack_pointer as usize;
-        self.memory[stack_pointer - 1] = high_byte;
-        self.memory[stack_pointer - 2] = low_byte;
-        self.stack_pointer -= 2;
+    /// Copies `bytes` to memory from `address`, failing when they go past the end of memory
+    pub fn load_at(&mut self, address: u16, bytes: &[u8]) -> Result<(), String> {
+        let start = address as usize;
+        let Some(destination) = self.memory.get_mut(start..start + bytes.len()) else {
+            return Err(format!(
+                "{} bytes loaded at {address:04X} do not fit in the 64 KiB address space",
+                bytes.len()
+            ));
+        };
+        destination.copy_from_slice(bytes);
+        Ok(())
     }
 
-    fn stack_pop(&mut self) -> u16 {
-        let stack_pointer = self.stack_pointer as usize;
+    /**
+    Makes `range` read only to the program, as ROM and the addresses no memory
+    answers to are. Loading and [`CPU::memory_mut`] can still change it.
+    */
+    pub fn set_read_only(&mut self, range: RangeInclusive<u16>) {
+        self.read_only.push(range);
+    }
 
-        let low_byte = self.memory[stack_pointer] as u16;
-        let high_byte = self.memory[stack_pointer + 1] as u16;
+    /// The whole 64 KiB address space
+    pub fn memory(&self) -> &[u8] {
+        &self.memory
+    }
+
+    pub fn memory_mut(&mut self) -> &mut [u8] {
+        &mut self.memory
+    }
+
+    /// Value of `register`, `M` reading the memory at HL
+    pub fn read_register(&self, register: Register) -> u8 {
+        self.register(register, &Instruction::NOP)
+    }
+
+    pub fn write_register(&mut self, register: Register, value: u8) {
+        self.set_register(register, value, &Instruction::NOP);
+    }
+
+    /// Value of `pair`, [`RegisterPair::SP`] being the stack pointer
+    pub fn read_register_pair(&self, pair: RegisterPair) -> u16 {
+        self.register_pair(pair, &Instruction::NOP)
+    }
+
+    pub fn write_register_pair(&mut self, pair: RegisterPair, value: u16) {
+        self.set_register_pair(pair, value, &Instruction::NOP);
+    }
+
+    pub fn program_counter(&self) -> u16 {
+        self.program_counter
+    }
+
+    /// Makes execution continue at `address`
+    pub fn set_program_counter(&mut self, address: u16) {
+        self.program_counter = address;
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    fn read_u16(&self, address: u16) -> u16 {
+        let low_byte = self.memory[address as usize] as u16;
+        let high_byte = self.memory[address.wrapping_add(1) as usize] as u16;
+        (high_byte << 8) | low_byte
+    }
+
+    fn write_byte(&mut self, address: u16, value: u8) {
+        if !self.read_only.iter().any(|range| range.contains(&address)) {
+            self.memory[address as usize] = value;
+        }
+    }
+
+    fn write_u16(&mut self, address: u16, value: u16) {
+        self.write_byte(address, (value & 0x00ff) as u8);
+        self.write_byte(address.wrapping_add(1), ((value & 0xff00) >> 8) as u8);
+    }
+
+    fn stack_push(&mut self, value: u16) {
+        self.stack_pointer = self.stack_pointer.wrapping_sub(2);
+        self.write_u16(self.stack_pointer, value);
+    }
 
-        let value: u16 = (high_byte << 8) + low_byte;
-        self.stack_pointer += 2;
+    fn stack_pop(&mut self) -> u16 {
+        let value = self.read_u16(self.stack_pointer);
+        self.stack_pointer = self.stack_pointer.wrapping_add(2);
         value
     }
 
@@ -104,7 +189,8 @@ impl CPU {
         let (high_register, low_register) = match pair {
             RegisterPair::SP => match insn {
                 Instruction::POP(_) | Instruction::PUSH(_) => {
-                    todo!("Get PSW");
+                    // PSW is A as the high byte and the flags as the low byte
+                    return ((self.registers[1] as u16) << 8) | self.registers[0] as u16;
                 }
                 _ => {
                     return self.stack_pointer;
@@ -117,18 +203,20 @@ impl CPU {
 
         let low_byte = self.register(low_register, insn) as u16;
         let high_byte = self.register(high_register, insn) as u16;
-        ((high_byte << 8) | low_byte).into()
+        (high_byte << 8) | low_byte
     }
 
     fn set_register_pair(&mut self, pair: RegisterPair, value: u16, insn: &Instruction) {
-        // TODO: Verify that this function handles endianness correctly
         let low_byte = (value & 0x00ff) as u8;
-        let high_byte = (value & 0xff00 >> 8) as u8;
+        let high_byte = ((value & 0xff00) >> 8) as u8;
 
         let (high_register, low_register) = match pair {
             RegisterPair::SP => match insn {
                 Instruction::POP(_) | Instruction::PUSH(_) => {
-                    todo!("Set PSW to #${value:04x}");
+                    // Bits 3 and 5 of the flags always read as 0, bit 1 as 1
+                    self.registers[0] = (low_byte & 0xd7) | 0x02;
+                    self.registers[1] = high_byte;
+                    return;
                 }
                 _ => {
                     self.stack_pointer = value;
@@ -172,6 +260,14 @@ impl CPU {
         *flag_byte &= 0xff - flag_mask as u8;
     }
 
+    fn assign_flag(&mut self, flag_mask: FlagMask, value: bool) {
+        if value {
+            self.set_flag(flag_mask);
+        } else {
+            self.unset_flag(flag_mask);
+        }
+    }
+
     fn update_flags_u8(&mut self, value: u8) {
         // Zero flag
         if value == 0 {
@@ -197,37 +293,151 @@ impl CPU {
             mask <<= 1;
         }
 
-        if bits % 2 == 0 {
+        if bits.is_multiple_of(2) {
             self.set_flag(FlagMask::P);
         } else {
             self.unset_flag(FlagMask::P);
         }
     }
 
-    pub fn fetch_decode_execute(&mut self) {
+    /// Adds `value` and the optional carry to A, updating all flags
+    fn add_to_accumulator(&mut self, value: u8, carry: bool, insn: &Instruction) {
+        let accumulator = self.register(Register::A, insn);
+        let carry = carry as u16;
+
+        let result = accumulator as u16 + value as u16 + carry;
+        let half_result = (accumulator & 0x0f) as u16 + (value & 0x0f) as u16 + carry;
+        self.assign_flag(FlagMask::C, result > 0xff);
+        self.assign_flag(FlagMask::A, half_result > 0x0f);
+
+        let result = (result & 0x00ff) as u8;
+        self.update_flags_u8(result);
+        self.set_register(Register::A, result, insn);
+    }
+
+    /// Subtracts `value` and the optional borrow from A, updating all flags.
+    ///
+    /// The result is returned instead of being stored so that CMP/CPI can share this.
+    fn subtract_from_accumulator(&mut self, value: u8, borrow: bool, insn: &Instruction) -> u8 {
+        let accumulator = self.register(Register::A, insn);
+        let borrow = borrow as u16;
+
+        let result = (accumulator as u16)
+            .wrapping_sub(value as u16)
+            .wrapping_sub(borrow);
+        // The 8080 subtracts by adding the two's complement, the auxiliary carry comes from that
+        let half_result = (accumulator & 0x0f) as u16 + (!value & 0x0f) as u16 + (1 - borrow);
+        self.assign_flag(FlagMask::C, (accumulator as u16) < value as u16 + borrow);
+        self.assign_flag(FlagMask::A, half_result > 0x0f);
+
+        let result = (result & 0x00ff) as u8;
+        self.update_flags_u8(result);
+        result
+    }
+
+    fn logic_with_accumulator(
+        &mut self,
+        value: u8,
+        operation: fn(u8, u8) -> u8,
+        insn: &Instruction,
+    ) {
+        let accumulator = self.register(Register::A, insn);
+        let result = operation(accumulator, value);
+
+        self.unset_flag(FlagMask::C);
+        // Only AND touches the auxiliary carry, with the OR of bit 3 of the operands
+        let auxiliary_carry = matches!(insn, Instruction::ANA(_) | Instruction::ANI(_))
+            && ((accumulator | value) & 0x08) != 0;
+        self.assign_flag(FlagMask::A, auxiliary_carry);
+
+        self.update_flags_u8(result);
+        self.set_register(Register::A, result, insn);
+    }
+
+    fn decimal_adjust_accumulator(&mut self, insn: &Instruction) {
+        let accumulator = self.register(Register::A, insn);
+        let low_nibble = accumulator & 0x0f;
+        let high_nibble = accumulator >> 4;
+
+        let mut correction = 0;
+        let mut carry = self.get_flag(FlagMask::C);
+        if self.get_flag(FlagMask::A) || low_nibble > 9 {
+            correction |= 0x06;
+        }
+        if carry || high_nibble > 9 || (high_nibble >= 9 && low_nibble > 9) {
+            correction |= 0x60;
+            carry = true;
+        }
+
+        let result = accumulator.wrapping_add(correction);
+        self.assign_flag(FlagMask::A, low_nibble + (correction & 0x0f) > 0x0f);
+        self.assign_flag(FlagMask::C, carry);
+        self.update_flags_u8(result);
+        self.set_register(Register::A, result, insn);
+    }
+
+    /**
+    Requests a `RST vector` interrupt, as done by external hardware placing the
+    instruction on the data bus.
+
+    Returns whether the interrupt was accepted, which only happens when interrupts
+    are enabled.
+    */
+    pub fn interrupt(&mut self, vector: u8) -> bool {
+        if !self.interrupts_enabled {
+            return false;
+        }
+        assert!(vector < 8, "RST vector must be between 0 and 7");
+
+        self.interrupts_enabled = false;
+        self.halted = false;
+        self.stack_push(self.program_counter);
+        self.program_counter = (vector as u16) * 8;
+        true
+    }
+
+    /// Executes one instruction with no I/O devices attached
+    pub fn fetch_decode_execute(&mut self) -> usize {
+        self.step(&mut NullBus)
+    }
+
+    /// Executes one instruction, routing `IN`/`OUT` to `bus`, and returns the
+    /// number of clock cycles it took
+    pub fn step(&mut self, bus: &mut impl Bus) -> usize {
+        if self.halted {
+            return 4;
+        }
+
         // Fetch
-        let program_counter = self.program_counter as usize;
+        let program_counter = self.program_counter;
 
-        let current_instruction_byte = self.memory[program_counter];
+        let current_instruction_byte = self.memory[program_counter as usize];
         let bytes_to_read = InstructionParser::bytes_to_read(current_instruction_byte);
 
-        let slice = &self.memory[program_counter..=program_counter + bytes_to_read];
+        let mut bytes = [current_instruction_byte, 0, 0];
+        for (offset, byte) in bytes.iter_mut().enumerate().skip(1).take(bytes_to_read) {
+            *byte = self.memory[program_counter.wrapping_add(offset as u16) as usize];
+        }
 
         // Decode
-        let insn = InstructionParser::parse_bytes(slice);
+        let insn = InstructionParser::parse_bytes(&bytes[..=bytes_to_read]);
         if insn.is_none() {
-            return;
+            return 0;
         }
         let insn = insn.unwrap();
 
         // Execute
-        let instruction_size = bytes_to_read + 1;
+        let instruction_size = bytes_to_read as u16 + 1;
+        let next_instruction = program_counter.wrapping_add(instruction_size);
         trace!("${:04x}: {insn}", self.program_counter);
+
+        // Where the program counter goes after this instruction, and whether a conditional was taken
+        let mut jump_to = None;
+        let mut taken = false;
         match insn {
             Instruction::NOP => {}
             Instruction::JMP(addr) => {
-                self.program_counter = addr;
-                return;
+                jump_to = Some(addr);
             }
             Instruction::LXI(register_pair, immediate) => {
                 self.set_register_pair(register_pair, immediate, &insn)
@@ -235,14 +445,88 @@ impl CPU {
             Instruction::MVI(register, immediate) => {
                 self.set_register(register, immediate, &insn);
             }
+            Instruction::LDA(addr) => {
+                self.set_register(Register::A, self.memory[addr as usize], &insn);
+            }
+            Instruction::STA(addr) => {
+                self.write_byte(addr, self.register(Register::A, &insn));
+            }
+            Instruction::LHLD(addr) => {
+                let value = self.read_u16(addr);
+                self.set_register_pair(RegisterPair::HL, value, &insn);
+            }
+            Instruction::SHLD(addr) => {
+                let value = self.register_pair(RegisterPair::HL, &insn);
+                self.write_u16(addr, value);
+            }
+            Instruction::ADD(register) => {
+                let value = self.register(register, &insn);
+                self.add_to_accumulator(value, false, &insn);
+            }
+            Instruction::ADI(immediate) => {
+                self.add_to_accumulator(immediate, false, &insn);
+            }
+            Instruction::ADC(register) => {
+                let value = self.register(register, &insn);
+                self.add_to_accumulator(value, self.get_flag(FlagMask::C), &insn);
+            }
+            Instruction::ACI(immediate) => {
+                self.add_to_accumulator(immediate, self.get_flag(FlagMask::C), &insn);
+            }
+            Instruction::SUB(register) => {
+                let value = self.register(register, &insn);
+                let result = self.subtract_from_accumulator(value, false, &insn);
+                self.set_register(Register::A, result, &insn);
+            }
+            Instruction::SUI(immediate) => {
+                let result = self.subtract_from_accumulator(immediate, false, &insn);
+                self.set_register(Register::A, result, &insn);
+            }
+            Instruction::SBB(register) => {
+                let value = self.register(register, &insn);
+                let borrow = self.get_flag(FlagMask::C);
+                let result = self.subtract_from_accumulator(value, borrow, &insn);
+                self.set_register(Register::A, result, &insn);
+            }
+            Instruction::SBI(immediate) => {
+                let borrow = self.get_flag(FlagMask::C);
+                let result = self.subtract_from_accumulator(immediate, borrow, &insn);
+                self.set_register(Register::A, result, &insn);
+            }
+            Instruction::CMP(register) => {
+                let value = self.register(register, &insn);
+                self.subtract_from_accumulator(value, false, &insn);
+            }
             Instruction::CPI(immediate) => {
-                let result = self.register(Register::A, &insn).wrapping_sub(immediate);
-                self.update_flags_u8(result);
+                self.subtract_from_accumulator(immediate, false, &insn);
+            }
+            Instruction::ANA(register) => {
+                let value = self.register(register, &insn);
+                self.logic_with_accumulator(value, u8::bitand, &insn);
+            }
+            Instruction::ANI(immediate) => {
+                self.logic_with_accumulator(immediate, u8::bitand, &insn);
+            }
+            Instruction::ORA(register) => {
+                let value = self.register(register, &insn);
+                self.logic_with_accumulator(value, u8::bitor, &insn);
+            }
+            Instruction::ORI(immediate) => {
+                self.logic_with_accumulator(immediate, u8::bitor, &insn);
+            }
+            Instruction::XRA(register) => {
+                let value = self.register(register, &insn);
+                self.logic_with_accumulator(value, u8::bitxor, &insn);
+            }
+            Instruction::XRI(immediate) => {
+                self.logic_with_accumulator(immediate, u8::bitxor, &insn);
+            }
+            Instruction::DAA => {
+                self.decimal_adjust_accumulator(&insn);
             }
             Instruction::J(condition, addr) => {
                 if self.verify_condition(condition) {
-                    self.program_counter = addr;
-                    return;
+                    jump_to = Some(addr);
                 }
             }
             Instruction::POP(pair) => {
@@ -250,37 +534,67 @@ impl CPU {
                 self.set_register_pair(pair, value, &insn);
             }
             Instruction::CALL(addr) => {
-                self.stack_push(self.program_counter);
-                self.program_counter = addr;
-                return;
+                self.stack_push(next_instruction);
+                jump_to = Some(addr);
+            }
+            Instruction::C(condition, addr) => {
+                if self.verify_condition(condition) {
+                    taken = true;
+                    self.stack_push(next_instruction);
+                    jump_to = Some(addr);
+                }
             }
             Instruction::RET => {
-                self.program_counter = self.stack_pop();
+                jump_to = Some(self.stack_pop());
+            }
+            Instruction::R(condition) => {
+                if self.verify_condition(condition) {
+                    taken = true;
+                    jump_to = Some(self.stack_pop());
+                }
+            }
+            Instruction::RST(n) => {
+                self.stack_push(next_instruction);
+                jump_to = Some((n as u16) * 8);
+            }
+            Instruction::PCHL => {
+                jump_to = Some(self.register_pair(RegisterPair::HL, &insn));
             }
             Instruction::LDAX(pair) => {
                 assert!(pair == RegisterPair::BC || pair == RegisterPair::DE);
                 let address = self.register_pair(pair, &insn);
                 self.set_register(Register::A, self.memory[address as usize], &insn);
             }
+            Instruction::STAX(pair) => {
+                assert!(pair == RegisterPair::BC || pair == RegisterPair::DE);
+                let address = self.register_pair(pair, &insn);
+                self.write_byte(address, self.register(Register::A, &insn));
+            }
             Instruction::MOV(dest, src) => {
                 let src_value = self.register(src, &insn);
                 self.set_register(dest, src_value, &insn);
             }
-            Instruction::INX(pair) => {
-                let value = self.register_pair(pair, &insn);
-                self.set_register_pair(pair, value.wrapping_add(1), &insn);
+            Instruction::INR(register) => {
+                let value = self.register(register, &insn);
+                let result = value.wrapping_add(1);
+                self.assign_flag(FlagMask::A, (value & 0x0f) == 0x0f);
+                self.update_flags_u8(result);
+                self.set_register(register, result, &insn);
             }
             Instruction::DCR(register) => {
                 let value = self.register(register, &insn);
                 let result = value.wrapping_sub(1);
+                self.assign_flag(FlagMask::A, (result & 0x0f) != 0x0f);
                 self.update_flags_u8(result);
                 self.set_register(register, result, &insn);
             }
-            Instruction::ANI(immediate) => {
-                let value = self.register(Register::A, &insn);
-                let result = value.bitand(immediate);
-                self.update_flags_u8(result);
-                self.set_register(Register::A, result, &insn);
+            Instruction::INX(pair) => {
+                let value = self.register_pair(pair, &insn);
+                self.set_register_pair(pair, value.wrapping_add(1), &insn);
+            }
+            Instruction::DCX(pair) => {
+                let value = self.register_pair(pair, &insn);
+                self.set_register_pair(pair, value.wrapping_sub(1), &insn);
             }
             Instruction::PUSH(pair) => {
                 let value = self.register_pair(pair, &insn);
@@ -307,17 +621,71 @@ impl CPU {
                 self.set_register_pair(RegisterPair::DE, hl, &insn);
                 self.set_register_pair(RegisterPair::HL, de, &insn);
             }
+            Instruction::XTHL => {
+                let top = self.read_u16(self.stack_pointer);
+                let hl = self.register_pair(RegisterPair::HL, &insn);
+                self.write_u16(self.stack_pointer, hl);
+                self.set_register_pair(RegisterPair::HL, top, &insn);
+            }
+            Instruction::SPHL => {
+                self.stack_pointer = self.register_pair(RegisterPair::HL, &insn);
+            }
+
+            Instruction::RLC => {
+                let accumulator = self.register(Register::A, &insn);
+                self.assign_flag(FlagMask::C, accumulator & 0x80 != 0);
+                self.set_register(Register::A, accumulator.rotate_left(1), &insn);
+            }
+            Instruction::RRC => {
+                let accumulator = self.register(Register::A, &insn);
+                self.assign_flag(FlagMask::C, accumulator & 0x01 != 0);
+                self.set_register(Register::A, accumulator.rotate_right(1), &insn);
+            }
+            Instruction::RAL => {
+                let accumulator = self.register(Register::A, &insn);
+                let carry = self.get_flag(FlagMask::C) as u8;
+                self.assign_flag(FlagMask::C, accumulator & 0x80 != 0);
+                self.set_register(Register::A, (accumulator << 1) | carry, &insn);
+            }
+            Instruction::RAR => {
+                let accumulator = self.register(Register::A, &insn);
+                let carry = self.get_flag(FlagMask::C) as u8;
+                self.assign_flag(FlagMask::C, accumulator & 0x01 != 0);
+                self.set_register(Register::A, (accumulator >> 1) | (carry << 7), &insn);
+            }
+            Instruction::CMA => {
+                let accumulator = self.register(Register::A, &insn);
+                self.set_register(Register::A, !accumulator, &insn);
+            }
+            Instruction::CMC => {
+                self.assign_flag(FlagMask::C, !self.get_flag(FlagMask::C));
+            }
+            Instruction::STC => {
+                self.set_flag(FlagMask::C);
+            }
 
-            Instruction::OUT(_) => {
-                warn!("TODO: Implement port I/O");
+            Instruction::OUT(port) => {
+                bus.output(port, self.register(Register::A, &insn));
+            }
+            Instruction::IN(port) => {
+                self.set_register(Register::A, bus.input(port), &insn);
+            }
+            Instruction::EI => {
+                self.interrupts_enabled = true;
+            }
+            Instruction::DI => {
+                self.interrupts_enabled = false;
             }
-            Instruction::IN(_) => {
-                warn!("TODO: Implement port I/O");
+            Instruction::HLT => {
+                self.halted = true;
             }
-            _ => {
-                todo!("Implement instruction {}", insn);
+            Instruction::Unknown => {
+                panic!(
+                    "Unknown instruction #${current_instruction_byte:02x} at ${program_counter:04x}"
+                );
             }
         };
-        self.program_counter += instruction_size as u16;
+        self.program_counter = jump_to.unwrap_or(next_instruction);
+        opcode_cycles(current_instruction_byte).get(taken)
     }
 }
@@ -1,2 +1,5 @@
+mod bus;
+#[allow(clippy::module_inception)]
 mod cpu;
+pub use bus::{Bus, NullBus};
 pub use cpu::CPU;
@@ -0,0 +1,101 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    instruction::Instruction,
+    parser::{InstructionParser, Parsed},
+};
+
+use super::Item;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ByteKind {
+    /// Not reached by the control flow
+    Unreached,
+
+    /// First byte of an instruction
+    Opcode,
+
+    /// Immediate or address byte of an instruction
+    Operand,
+}
+
+/// Addresses where execution may continue after `instruction`, `next` being the following one
+pub(super) fn successors(instruction: &Instruction, next: u16) -> Vec<u16> {
+    let mut addresses = vec![];
+    // The target of PCHL is only known at runtime
+    let transfers = instruction.is_branch() || instruction.is_return();
+    if !transfers || instruction.is_conditional() {
+        addresses.push(next);
+    }
+    addresses.extend(instruction.target());
+    addresses
+}
+
+/**
+Disassembles `program`, loaded at `origin`, following the control flow from `entry_points`.
+
+Jumps, calls and conditional branches are followed so that only reachable bytes
+are decoded as code, every other byte becomes data. Paths leading outside of the
+program or into the middle of an already decoded instruction are abandoned.
+*/
+pub fn follow_control_flow(program: &[u8], origin: u16, entry_points: &[u16]) -> Vec<Item> {
+    let parser = InstructionParser::new(program, origin);
+    let mut kinds = vec![ByteKind::Unreached; program.len()];
+    let mut instructions: BTreeMap<usize, (Instruction, usize)> = BTreeMap::new();
+
+    for entry_point in entry_points {
+        let mut pending = vec![*entry_point];
+
+        while let Some(address) = pending.pop() {
+            let (size, instruction) = match parser.decode_at(address) {
+                Some((_, Parsed::Instruction(Instruction::Unknown))) => continue,
+                Some((bytes, Parsed::Instruction(instruction))) => (bytes.len(), instruction),
+                Some((_, Parsed::Truncated(_))) | None => continue,
+            };
+            let offset = address.wrapping_sub(origin) as usize;
+            let end = offset + size;
+            if kinds[offset..end]
+                .iter()
+                .any(|kind| *kind != ByteKind::Unreached)
+            {
+                continue;
+            }
+
+            kinds[offset] = ByteKind::Opcode;
+            kinds[offset + 1..end].fill(ByteKind::Operand);
+            instructions.insert(offset, (instruction, size));
+
+            // Reversed so that the fall-through path is explored first
+            let next = address.wrapping_add(size as u16);
+            pending.extend(successors(&instruction, next).into_iter().rev());
+        }
+    }
+
+    let mut items = vec![];
+    let mut offset = 0;
+    while offset < program.len() {
+        let address = origin.wrapping_add(offset as u16);
+
+        if let Some((instruction, size)) = instructions.get(&offset) {
+            let size = *size;
+            items.push(Item::Code {
+                address,
+                bytes: program[offset..offset + size].to_vec(),
+                instruction: *instruction,
+            });
+            offset += size;
+            continue;
+        }
+
+        let end = kinds[offset..]
+            .iter()
+            .position(|kind| *kind != ByteKind::Unreached)
+            .map_or(program.len(), |length| offset + length);
+        items.push(Item::Data {
+            address,
+            bytes: program[offset..end].to_vec(),
+        });
+        offset = end;
+    }
+    items
+}
@@ -0,0 +1,59 @@
+use serde_json::{json, Value};
+
+use super::{mnemonic, operands, Item, Operand, SymbolTable};
+
+fn operand_json(operand: &Operand) -> Value {
+    match operand {
+        Operand::Register(register) => json!({"type": "register", "value": register.to_string()}),
+        Operand::RegisterPair(pair) => json!({"type": "register_pair", "value": pair.to_string()}),
+        Operand::ProgramStatusWord => json!({"type": "register_pair", "value": "PSW"}),
+        Operand::Condition(condition) => {
+            json!({"type": "condition", "value": condition.to_string()})
+        }
+        Operand::Immediate(value) => json!({"type": "immediate", "value": value}),
+        Operand::Address(address) => json!({"type": "address", "value": address}),
+    }
+}
+
+/**
+Lines of a JSON array with one object per item, such as
+
+```json
+{"address":3,"bytes":[195,212,24],"kind":"code","label":null,"length":3,"mnemonic":"JMP","operands":[{"type":"address","value":6356}]}
+```
+
+Data runs have the `DB` mnemonic and no operands.
+*/
+pub fn json_listing(items: &[Item], symbols: &SymbolTable) -> Vec<String> {
+    let objects: Vec<String> = items
+        .iter()
+        .map(|item| {
+            let (kind, mnemonic, operands) = match item {
+                Item::Code { instruction, .. } => (
+                    "code",
+                    mnemonic(instruction),
+                    operands(instruction).iter().map(operand_json).collect(),
+                ),
+                Item::Data { .. } => ("data", "DB".to_owned(), vec![]),
+            };
+            json!({
+                "address": item.address(),
+                "bytes": item.bytes(),
+                "kind": kind,
+                "label": symbols.get(item.address()).map(|symbol| &symbol.name),
+                "length": item.bytes().len(),
+                "mnemonic": mnemonic,
+                "operands": operands,
+            })
+            .to_string()
+        })
+        .collect();
+
+    let mut lines = vec!["[".to_owned()];
+    let last = objects.len().saturating_sub(1);
+    for (index, object) in objects.into_iter().enumerate() {
+        lines.push(if index == last { object } else { object + "," });
+    }
+    lines.push("]".to_owned());
+    lines
+}
@@ -0,0 +1,65 @@
+mod flow;
+mod json;
+mod operands;
+mod source;
+mod symbols;
+mod xref;
+pub use flow::follow_control_flow;
+pub use json::json_listing;
+pub use operands::{mnemonic, operands, Operand};
+pub use source::{
+    format_intel_instruction, intel_hex, reassemblable_source, with_comment, DATA_BYTES_PER_LINE,
+};
+pub use symbols::{format_instruction, referenced_comment, Symbol, SymbolTable};
+pub use xref::{call_graph_dot, CrossReferences, Reference, ReferenceKind};
+
+use crate::{
+    instruction::Instruction,
+    parser::{InstructionParser, Parsed},
+};
+
+/// One entry of a disassembly listing
+#[derive(Debug, Clone)]
+pub enum Item {
+    /// Bytes decoded as an instruction
+    Code {
+        address: u16,
+        bytes: Vec<u8>,
+        instruction: Instruction,
+    },
+
+    /// Bytes that are not decoded as code
+    Data { address: u16, bytes: Vec<u8> },
+}
+
+impl Item {
+    pub fn address(&self) -> u16 {
+        match self {
+            Item::Code { address, .. } | Item::Data { address, .. } => *address,
+        }
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        match self {
+            Item::Code { bytes, .. } | Item::Data { bytes, .. } => bytes,
+        }
+    }
+}
+
+/**
+Decodes every byte of `program`, loaded at `origin`, as code, one instruction after the other.
+
+An instruction cut off by the end of `program` becomes data.
+*/
+pub fn linear_sweep(program: &[u8], origin: u16) -> Vec<Item> {
+    InstructionParser::new(program, origin)
+        .map(|(address, bytes, parsed)| match parsed {
+            Parsed::Instruction(instruction) => Item::Code {
+                address,
+                bytes: bytes.to_vec(),
+                instruction,
+            },
+            Parsed::Truncated(bytes) => Item::Data { address, bytes },
+        })
+        .collect()
+}
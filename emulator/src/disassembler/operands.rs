@@ -0,0 +1,106 @@
+use crate::{
+    condition::Condition,
+    instruction::Instruction,
+    register::{Register, RegisterPair},
+};
+
+/// An operand of an instruction, as structured data
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Operand {
+    Register(Register),
+
+    RegisterPair(RegisterPair),
+
+    /// The A:FLAGS pair of `PUSH PSW` and `POP PSW`
+    ProgramStatusWord,
+
+    Condition(Condition),
+
+    /// Immediate data, port number or restart vector number
+    Immediate(u16),
+
+    /// Memory address
+    Address(u16),
+}
+
+/// The mnemonic of `instruction`, conditions included as in `JNZ`
+pub fn mnemonic(instruction: &Instruction) -> String {
+    let text = instruction.to_string();
+    match text.split_once(' ') {
+        Some((mnemonic, _)) => mnemonic.to_owned(),
+        None => text,
+    }
+}
+
+/// The operands of `instruction` in source order
+pub fn operands(instruction: &Instruction) -> Vec<Operand> {
+    let stack_pair = |pair: &RegisterPair| match pair {
+        RegisterPair::SP => Operand::ProgramStatusWord,
+        pair => Operand::RegisterPair(*pair),
+    };
+
+    match instruction {
+        Instruction::MOV(dest, src) => vec![Operand::Register(*dest), Operand::Register(*src)],
+        Instruction::MVI(dest, imm) => {
+            vec![Operand::Register(*dest), Operand::Immediate(*imm as u16)]
+        }
+        Instruction::LXI(pair, imm) => {
+            vec![Operand::RegisterPair(*pair), Operand::Immediate(*imm)]
+        }
+        Instruction::LDA(addr)
+        | Instruction::STA(addr)
+        | Instruction::LHLD(addr)
+        | Instruction::SHLD(addr)
+        | Instruction::JMP(addr)
+        | Instruction::CALL(addr) => vec![Operand::Address(*addr)],
+        Instruction::LDAX(pair)
+        | Instruction::STAX(pair)
+        | Instruction::INX(pair)
+        | Instruction::DCX(pair)
+        | Instruction::DAD(pair) => vec![Operand::RegisterPair(*pair)],
+        Instruction::ADD(src)
+        | Instruction::ADC(src)
+        | Instruction::SUB(src)
+        | Instruction::SBB(src)
+        | Instruction::INR(src)
+        | Instruction::DCR(src)
+        | Instruction::ANA(src)
+        | Instruction::ORA(src)
+        | Instruction::XRA(src)
+        | Instruction::CMP(src) => vec![Operand::Register(*src)],
+        Instruction::ADI(imm)
+        | Instruction::ACI(imm)
+        | Instruction::SUI(imm)
+        | Instruction::SBI(imm)
+        | Instruction::ANI(imm)
+        | Instruction::ORI(imm)
+        | Instruction::XRI(imm)
+        | Instruction::CPI(imm)
+        | Instruction::RST(imm)
+        | Instruction::IN(imm)
+        | Instruction::OUT(imm) => vec![Operand::Immediate(*imm as u16)],
+        Instruction::J(condition, addr) | Instruction::C(condition, addr) => {
+            vec![Operand::Condition(*condition), Operand::Address(*addr)]
+        }
+        Instruction::R(condition) => vec![Operand::Condition(*condition)],
+        Instruction::PUSH(pair) | Instruction::POP(pair) => vec![stack_pair(pair)],
+        Instruction::XCHG
+        | Instruction::DAA
+        | Instruction::RLC
+        | Instruction::RRC
+        | Instruction::RAL
+        | Instruction::RAR
+        | Instruction::CMA
+        | Instruction::CMC
+        | Instruction::STC
+        | Instruction::RET
+        | Instruction::PCHL
+        | Instruction::XTHL
+        | Instruction::SPHL
+        | Instruction::EI
+        | Instruction::DI
+        | Instruction::HLT
+        | Instruction::NOP
+        | Instruction::Unknown => vec![],
+    }
+}
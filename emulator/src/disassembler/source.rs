@@ -0,0 +1,177 @@
+use std::collections::BTreeSet;
+
+use crate::{instruction::Instruction, register::RegisterPair};
+
+use super::{referenced_comment, Item, SymbolTable};
+
+/// Data bytes emitted on each `DB` line
+pub const DATA_BYTES_PER_LINE: usize = 8;
+
+/// Indentation of the statements, labels start at the first column
+const INDENT: &str = "        ";
+
+/**
+Formats `value` as an Intel hexadecimal number of at least `digits` digits.
+
+Numbers starting with a letter get a leading zero, so that the assembler does
+not read them as a name: `0C3H`, `18D4H`.
+*/
+pub fn intel_hex(value: u16, digits: usize) -> String {
+    let hex = format!("{value:0digits$X}");
+    if hex.starts_with(|digit: char| digit.is_ascii_alphabetic()) {
+        format!("0{hex}H")
+    } else {
+        format!("{hex}H")
+    }
+}
+
+/// Formats `instruction` in Intel syntax, with known addresses replaced by their name
+pub fn format_intel_instruction(instruction: &Instruction, symbols: &SymbolTable) -> String {
+    let address = |addr: &u16| match symbols.get(*addr) {
+        Some(symbol) => symbol.name.clone(),
+        None => intel_hex(*addr, 4),
+    };
+    let byte = |imm: &u8| intel_hex(*imm as u16, 2);
+    let stack_pair = |pair: &RegisterPair| match pair {
+        RegisterPair::SP => "PSW".to_owned(),
+        pair => pair.to_string(),
+    };
+
+    match instruction {
+        Instruction::MVI(dest, imm) => format!("MVI {dest},{}", byte(imm)),
+        Instruction::LXI(pair, imm) => format!("LXI {pair},{}", address(imm)),
+        Instruction::LDA(addr) => format!("LDA {}", address(addr)),
+        Instruction::STA(addr) => format!("STA {}", address(addr)),
+        Instruction::LHLD(addr) => format!("LHLD {}", address(addr)),
+        Instruction::SHLD(addr) => format!("SHLD {}", address(addr)),
+        Instruction::ADI(imm) => format!("ADI {}", byte(imm)),
+        Instruction::ACI(imm) => format!("ACI {}", byte(imm)),
+        Instruction::SUI(imm) => format!("SUI {}", byte(imm)),
+        Instruction::SBI(imm) => format!("SBI {}", byte(imm)),
+        Instruction::ANI(imm) => format!("ANI {}", byte(imm)),
+        Instruction::ORI(imm) => format!("ORI {}", byte(imm)),
+        Instruction::XRI(imm) => format!("XRI {}", byte(imm)),
+        Instruction::CPI(imm) => format!("CPI {}", byte(imm)),
+        Instruction::IN(port) => format!("IN {}", byte(port)),
+        Instruction::OUT(port) => format!("OUT {}", byte(port)),
+        Instruction::JMP(addr) => format!("JMP {}", address(addr)),
+        Instruction::J(condition, addr) => format!("J{condition} {}", address(addr)),
+        Instruction::CALL(addr) => format!("CALL {}", address(addr)),
+        Instruction::C(condition, addr) => format!("C{condition} {}", address(addr)),
+        Instruction::PUSH(pair) => format!("PUSH {}", stack_pair(pair)),
+        Instruction::POP(pair) => format!("POP {}", stack_pair(pair)),
+        // Every other instruction has no numeric operand and reads the same
+        _ => instruction.to_string(),
+    }
+}
+
+/// Whether assembling `instruction` gives back `bytes`, which is not the case of undocumented opcodes
+fn reassembles(instruction: &Instruction, bytes: &[u8]) -> bool {
+    instruction.encode().as_slice() == bytes
+}
+
+fn data_line(bytes: &[u8]) -> String {
+    let bytes: Vec<String> = bytes
+        .iter()
+        .map(|byte| intel_hex(*byte as u16, 2))
+        .collect();
+    format!("{INDENT}DB {}", bytes.join(","))
+}
+
+/**
+Source text of `items`, loaded at `origin`, that an Intel 8080 assembler turns
+back into the same bytes.
+
+Symbols are defined as labels where a statement starts and with `EQU` anywhere
+else. Bytes an assembler would not encode the same way, such as undocumented
+opcodes, are emitted as `DB`, and the targets of `LHLD`/`SHLD` as `DW`.
+*/
+pub fn reassemblable_source(items: &[Item], origin: u16, symbols: &SymbolTable) -> Vec<String> {
+    let words: BTreeSet<u16> = items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Code {
+                instruction: Instruction::LHLD(addr) | Instruction::SHLD(addr),
+                ..
+            } => Some(*addr),
+            _ => None,
+        })
+        .collect();
+
+    let mut body = vec![];
+    let mut labelled = BTreeSet::new();
+    let mut push_label = |body: &mut Vec<String>, address: u16| {
+        if let Some(symbol) = symbols.get(address) {
+            let label = format!("{}:", symbol.name);
+            body.push(with_comment(label, symbol.comment.as_deref()));
+            labelled.insert(address);
+        }
+    };
+
+    for item in items {
+        let address = item.address();
+        match item {
+            Item::Code {
+                bytes, instruction, ..
+            } if reassembles(instruction, bytes) => {
+                push_label(&mut body, address);
+                let line = format!("{INDENT}{}", format_intel_instruction(instruction, symbols));
+                body.push(with_comment(line, referenced_comment(instruction, symbols)));
+            }
+            Item::Code { bytes, .. } => {
+                push_label(&mut body, address);
+                body.push(data_line(bytes));
+            }
+            Item::Data { bytes, .. } => {
+                let mut data: Vec<u8> = vec![];
+                let mut offset = 0;
+                while offset < bytes.len() {
+                    let byte_address = address.wrapping_add(offset as u16);
+                    let label = symbols.get(byte_address).is_some();
+                    let word = words.contains(&byte_address)
+                        && offset + 1 < bytes.len()
+                        && symbols.get(byte_address.wrapping_add(1)).is_none();
+
+                    if data.len() == DATA_BYTES_PER_LINE || ((label || word) && !data.is_empty()) {
+                        body.push(data_line(&data));
+                        data.clear();
+                    }
+                    if data.is_empty() {
+                        push_label(&mut body, byte_address);
+                    }
+                    if word {
+                        let value = u16::from_le_bytes([bytes[offset], bytes[offset + 1]]);
+                        body.push(format!("{INDENT}DW {}", intel_hex(value, 4)));
+                        offset += 2;
+                        continue;
+                    }
+                    data.push(bytes[offset]);
+                    offset += 1;
+                }
+                if !data.is_empty() {
+                    body.push(data_line(&data));
+                }
+            }
+        }
+    }
+
+    let mut lines = vec![];
+    for (address, symbol) in symbols.iter() {
+        if !labelled.contains(&address) {
+            let line = format!("{} EQU {}", symbol.name, intel_hex(address, 4));
+            lines.push(with_comment(line, symbol.comment.as_deref()));
+        }
+    }
+    lines.push(format!("{INDENT}ORG {}", intel_hex(origin, 4)));
+    lines.extend(body);
+    lines.push(format!("{INDENT}END"));
+    lines
+}
+
+/// Appends `; comment` to `line` when there is a comment
+pub fn with_comment(line: String, comment: Option<&str>) -> String {
+    match comment {
+        Some(comment) => format!("{line} ; {comment}"),
+        None => line,
+    }
+}
@@ -0,0 +1,190 @@
+use std::{collections::BTreeMap, str::FromStr};
+
+use crate::{address::parse_address, assembler::is_reserved_word, instruction::Instruction};
+
+use super::Item;
+
+/// A name given to an address, with an optional comment
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Symbol {
+    pub name: String,
+    pub comment: Option<String>,
+}
+
+/// Names of addresses, substituted into the operands of a listing
+#[derive(Default, Debug, Clone)]
+pub struct SymbolTable {
+    symbols: BTreeMap<u16, Symbol>,
+}
+
+fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) if first.is_ascii_alphabetic() || first == '_' => {}
+        _ => return false,
+    }
+    chars.all(|char| char.is_ascii_alphanumeric() || char == '_')
+}
+
+impl SymbolTable {
+    pub fn get(&self, address: u16) -> Option<&Symbol> {
+        self.symbols.get(&address)
+    }
+
+    pub fn insert(&mut self, address: u16, symbol: Symbol) {
+        self.symbols.insert(address, symbol);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (u16, &Symbol)> {
+        self.symbols
+            .iter()
+            .map(|(address, symbol)| (*address, symbol))
+    }
+
+    /**
+    Names every jump and call target starting an item of `items`, as `L_XXXX` and
+    `SUB_XXXX` respectively.
+
+    Addresses that already have a symbol keep it.
+    */
+    pub fn add_branch_labels(&mut self, items: &[Item]) {
+        let starts: Vec<u16> = items.iter().map(Item::address).collect();
+
+        let mut labels: BTreeMap<u16, String> = BTreeMap::new();
+        for item in items {
+            let (target, prefix) = match item {
+                Item::Code { instruction, .. } => match instruction.target() {
+                    Some(target) if instruction.is_call() => (target, "SUB"),
+                    Some(target) => (target, "L"),
+                    None => continue,
+                },
+                Item::Data { .. } => continue,
+            };
+            if starts.binary_search(&target).is_err() {
+                continue;
+            }
+
+            // A routine that is also jumped to is still a routine
+            let label = labels.entry(target).or_default();
+            if label.is_empty() || prefix == "SUB" {
+                *label = format!("{prefix}_{target:04X}");
+            }
+        }
+
+        for (address, name) in labels {
+            self.symbols.entry(address).or_insert(Symbol {
+                name,
+                comment: None,
+            });
+        }
+    }
+}
+
+/**
+Parses a symbol file of `address=name` lines, where the address is in hex and
+the name may be followed by a `;` comment. Lines starting with `;` or `#` are
+ignored.
+
+Names must be identifiers other than registers, mnemonics, directives and
+operators, so that the reassemblable source assembles back, and each address
+and name may only be defined once.
+
+```text
+# Space Invaders
+18d4=init ; Entry point after reset
+20e9=suspend_play ; 1 while the game is suspended
+```
+*/
+impl FromStr for SymbolTable {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut table = SymbolTable::default();
+
+        for (index, line) in s.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+                continue;
+            }
+
+            let (definition, comment) = match line.split_once(';') {
+                Some((definition, comment)) => (definition, Some(comment.trim().to_owned())),
+                None => (line, None),
+            };
+            let (address, name) = definition
+                .split_once('=')
+                .ok_or(format!("Line {line_number}: expected `address=name`"))?;
+
+            let address = parse_address(address.trim())
+                .map_err(|err| format!("Line {line_number}: {err}"))?;
+            let name = name.trim();
+            if !is_valid_name(name) {
+                return Err(format!("Line {line_number}: invalid symbol name `{name}`"));
+            }
+            if is_reserved_word(name) {
+                return Err(format!(
+                    "Line {line_number}: `{name}` is reserved by the assembler"
+                ));
+            }
+            if table.get(address).is_some() {
+                return Err(format!(
+                    "Line {line_number}: address {address:04x} already has a symbol"
+                ));
+            }
+            if table.iter().any(|(_, symbol)| symbol.name == name) {
+                return Err(format!("Line {line_number}: `{name}` is already defined"));
+            }
+
+            table.insert(
+                address,
+                Symbol {
+                    name: name.to_owned(),
+                    comment,
+                },
+            );
+        }
+
+        Ok(table)
+    }
+}
+
+/// Formats `instruction` like its `Display` implementation, with known addresses replaced by their name
+pub fn format_instruction(instruction: &Instruction, symbols: &SymbolTable) -> String {
+    let address = |addr: &u16| match symbols.get(*addr) {
+        Some(symbol) => symbol.name.clone(),
+        None => format!("${addr:04x}"),
+    };
+
+    match instruction {
+        Instruction::JMP(addr) => format!("JMP {}", address(addr)),
+        Instruction::J(condition, addr) => format!("J{condition} {}", address(addr)),
+        Instruction::CALL(addr) => format!("CALL {}", address(addr)),
+        Instruction::C(condition, addr) => format!("C{condition} {}", address(addr)),
+        Instruction::LDA(addr) => format!("LDA {}", address(addr)),
+        Instruction::STA(addr) => format!("STA {}", address(addr)),
+        Instruction::LHLD(addr) => format!("LHLD {}", address(addr)),
+        Instruction::SHLD(addr) => format!("SHLD {}", address(addr)),
+        Instruction::LXI(pair, immediate) => match symbols.get(*immediate) {
+            Some(symbol) => format!("LXI {pair},{}", symbol.name),
+            None => instruction.to_string(),
+        },
+        _ => instruction.to_string(),
+    }
+}
+
+/// The comment of the variable accessed by `instruction`, to annotate the listing with
+pub fn referenced_comment<'a>(
+    instruction: &Instruction,
+    symbols: &'a SymbolTable,
+) -> Option<&'a str> {
+    let address = match instruction {
+        Instruction::LDA(addr)
+        | Instruction::STA(addr)
+        | Instruction::LHLD(addr)
+        | Instruction::SHLD(addr)
+        | Instruction::LXI(_, addr) => *addr,
+        _ => return None,
+    };
+    symbols.get(address)?.comment.as_deref()
+}
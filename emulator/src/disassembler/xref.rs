@@ -0,0 +1,167 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write,
+};
+
+use crate::instruction::Instruction;
+
+use super::{flow::successors, Item, SymbolTable};
+
+/// How an instruction refers to an address
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy)]
+pub enum ReferenceKind {
+    /// `JMP` or conditional jump
+    Jump,
+
+    /// `CALL`, conditional call or `RST`
+    Call,
+
+    /// `LDA` or `LHLD`
+    Read,
+
+    /// `STA` or `SHLD`
+    Write,
+
+    /// `LXI` of the address as a constant
+    Constant,
+}
+
+/// An instruction at `from` referring to an address
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy)]
+pub struct Reference {
+    pub from: u16,
+    pub kind: ReferenceKind,
+}
+
+/// Every absolute address referred to by the code of a listing, with the instructions referring to it
+#[derive(Default, Debug, Clone)]
+pub struct CrossReferences {
+    references: BTreeMap<u16, Vec<Reference>>,
+}
+
+/// The address `instruction` refers to and how
+fn reference(instruction: &Instruction) -> Option<(u16, ReferenceKind)> {
+    if let Some(target) = instruction.target() {
+        let kind = if instruction.is_call() {
+            ReferenceKind::Call
+        } else {
+            ReferenceKind::Jump
+        };
+        return Some((target, kind));
+    }
+    match instruction {
+        Instruction::LDA(addr) | Instruction::LHLD(addr) => Some((*addr, ReferenceKind::Read)),
+        Instruction::STA(addr) | Instruction::SHLD(addr) => Some((*addr, ReferenceKind::Write)),
+        Instruction::LXI(_, addr) => Some((*addr, ReferenceKind::Constant)),
+        _ => None,
+    }
+}
+
+impl CrossReferences {
+    pub fn new(items: &[Item]) -> Self {
+        let mut references: BTreeMap<u16, Vec<Reference>> = BTreeMap::new();
+        for item in items {
+            if let Item::Code {
+                address,
+                instruction,
+                ..
+            } = item
+            {
+                if let Some((target, kind)) = reference(instruction) {
+                    references.entry(target).or_default().push(Reference {
+                        from: *address,
+                        kind,
+                    });
+                }
+            }
+        }
+        CrossReferences { references }
+    }
+
+    /// References to `address`, in the order of the listing
+    pub fn get(&self, address: u16) -> &[Reference] {
+        self.references.get(&address).map_or(&[], Vec::as_slice)
+    }
+
+    /// Referenced addresses in ascending order, with their references
+    pub fn iter(&self) -> impl Iterator<Item = (u16, &[Reference])> {
+        self.references
+            .iter()
+            .map(|(address, references)| (*address, references.as_slice()))
+    }
+}
+
+/**
+Call graph of the code in `items` as a Graphviz DOT digraph.
+
+Routines start at the `entry_points` and at every call target. A
+routine is made of the code reachable from its start without following calls,
+and has an edge to every routine it calls. Nodes are named after `symbols`.
+*/
+pub fn call_graph_dot(items: &[Item], entry_points: &[u16], symbols: &SymbolTable) -> String {
+    let code: BTreeMap<u16, (&Instruction, u16)> = items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Code {
+                address,
+                bytes,
+                instruction,
+            } => Some((
+                *address,
+                (instruction, address.wrapping_add(bytes.len() as u16)),
+            )),
+            Item::Data { .. } => None,
+        })
+        .collect();
+
+    let mut routines: BTreeSet<u16> = entry_points
+        .iter()
+        .copied()
+        .filter(|address| code.contains_key(address))
+        .collect();
+    for (instruction, _) in code.values() {
+        if let Some((target, ReferenceKind::Call)) = reference(instruction) {
+            if code.contains_key(&target) {
+                routines.insert(target);
+            }
+        }
+    }
+
+    let mut edges: BTreeSet<(u16, u16)> = BTreeSet::new();
+    for routine in &routines {
+        let mut visited = BTreeSet::new();
+        let mut pending = vec![*routine];
+        while let Some(address) = pending.pop() {
+            let Some((instruction, next)) = code.get(&address) else {
+                continue;
+            };
+            if !visited.insert(address) {
+                continue;
+            }
+            match reference(instruction) {
+                Some((target, ReferenceKind::Call)) => {
+                    if routines.contains(&target) {
+                        edges.insert((*routine, target));
+                    }
+                    // The callee returns to the next instruction
+                    pending.push(*next);
+                }
+                _ => pending.extend(successors(instruction, *next)),
+            }
+        }
+    }
+
+    let name = |address: u16| match symbols.get(address) {
+        Some(symbol) => symbol.name.clone(),
+        None => format!("{address:04x}"),
+    };
+    let mut dot = String::from("digraph calls {\n    node [shape=box];\n");
+    for routine in &routines {
+        let _ = writeln!(dot, "    n{routine:04x} [label=\"{}\"];", name(*routine));
+    }
+    for (caller, callee) in &edges {
+        let _ = writeln!(dot, "    n{caller:04x} -> n{callee:04x};");
+    }
+    dot.push_str("}\n");
+    dot
+}
@@ -1,11 +1,22 @@
-use std::fmt::{Debug, Display};
+use std::{
+    fmt::{Debug, Display},
+    str::FromStr,
+};
+
+use arrayvec::ArrayVec;
 
 use crate::{
     condition::Condition,
+    parser::InstructionParser,
     register::{Register, RegisterPair},
 };
 
-#[derive(Clone, Copy)]
+mod metadata;
+pub(crate) use metadata::{opcode_cycles, opcode_size};
+pub use metadata::{Cycles, Flags};
+use metadata::{OpcodeInfo, BRANCH, CALL, CONDITIONAL, OPCODES, READS, RETURN, WRITES};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum Instruction {
     /// Move register to register
     MOV(Register, Register),
@@ -180,12 +191,175 @@ pub enum Instruction {
     Unknown,
 }
 
+impl Instruction {
+    /// Row of the opcode table, `None` for `Unknown`
+    fn info(&self) -> Option<&'static OpcodeInfo> {
+        self.encode()
+            .first()
+            .map(|opcode| &OPCODES[*opcode as usize])
+    }
+
+    fn is(&self, kind: u8) -> bool {
+        self.info().is_some_and(|info| info.kind & kind != 0)
+    }
+
+    /// Size in bytes of the encoded instruction
+    pub fn size(&self) -> usize {
+        self.info().map_or(1, |info| info.size)
+    }
+
+    /// Clock cycles the instruction takes to execute
+    pub fn cycles(&self) -> Cycles {
+        self.info().map_or(
+            Cycles {
+                taken: 4,
+                not_taken: 4,
+            },
+            |info| info.cycles,
+        )
+    }
+
+    /// Flags the instruction may change
+    pub fn flags_written(&self) -> Flags {
+        self.info().map_or(Flags::default(), |info| info.flags)
+    }
+
+    /// `JMP`, conditional jumps and `PCHL`
+    pub fn is_branch(&self) -> bool {
+        self.is(BRANCH)
+    }
+
+    /// `CALL`, conditional calls and `RST`
+    pub fn is_call(&self) -> bool {
+        self.is(CALL)
+    }
+
+    /// `RET` and conditional returns
+    pub fn is_return(&self) -> bool {
+        self.is(RETURN)
+    }
+
+    /// Conditional jumps, calls and returns
+    pub fn is_conditional(&self) -> bool {
+        self.is(CONDITIONAL)
+    }
+
+    /// Whether the instruction reads memory, the stack included, besides being fetched
+    pub fn reads_memory(&self) -> bool {
+        self.is(READS)
+    }
+
+    /// Whether the instruction writes memory, the stack included
+    pub fn writes_memory(&self) -> bool {
+        self.is(WRITES)
+    }
+
+    /// Address a jump or call transfers control to, when known before running it
+    pub fn target(&self) -> Option<u16> {
+        match self {
+            Instruction::JMP(addr)
+            | Instruction::J(_, addr)
+            | Instruction::CALL(addr)
+            | Instruction::C(_, addr) => Some(*addr),
+            Instruction::RST(n) => Some((*n as u16) * 8),
+            _ => None,
+        }
+    }
+
+    /**
+    Machine code of the instruction, the inverse of [`InstructionParser::parse_bytes`].
+
+    Undocumented opcodes decode to the instruction they alias, which encodes to
+    its documented opcode. `Unknown` encodes to no bytes.
+
+    [`InstructionParser::parse_bytes`]: crate::parser::InstructionParser::parse_bytes
+    */
+    pub fn encode(&self) -> ArrayVec<u8, 3> {
+        let mut bytes = ArrayVec::new();
+        let reg = |register: &Register| u8::from(*register);
+        let pair = |pair: &RegisterPair| u8::from(*pair) << 4;
+        let cond = |condition: &Condition| u8::from(*condition) << 3;
+
+        let (opcode, operand) = match self {
+            Instruction::MOV(dest, src) => (0x40 | reg(dest) << 3 | reg(src), None),
+            Instruction::MVI(dest, imm) => (0x06 | reg(dest) << 3, Some(*imm as u16)),
+            Instruction::LXI(rp, imm) => (0x01 | pair(rp), Some(*imm)),
+            Instruction::LDA(addr) => (0x3a, Some(*addr)),
+            Instruction::STA(addr) => (0x32, Some(*addr)),
+            Instruction::LHLD(addr) => (0x2a, Some(*addr)),
+            Instruction::SHLD(addr) => (0x22, Some(*addr)),
+            Instruction::LDAX(rp) => (0x0a | pair(rp), None),
+            Instruction::STAX(rp) => (0x02 | pair(rp), None),
+            Instruction::XCHG => (0xeb, None),
+            Instruction::ADD(src) => (0x80 | reg(src), None),
+            Instruction::ADI(imm) => (0xc6, Some(*imm as u16)),
+            Instruction::ADC(src) => (0x88 | reg(src), None),
+            Instruction::ACI(imm) => (0xce, Some(*imm as u16)),
+            Instruction::SUB(src) => (0x90 | reg(src), None),
+            Instruction::SUI(imm) => (0xd6, Some(*imm as u16)),
+            Instruction::SBB(src) => (0x98 | reg(src), None),
+            Instruction::SBI(imm) => (0xde, Some(*imm as u16)),
+            Instruction::INR(dest) => (0x04 | reg(dest) << 3, None),
+            Instruction::DCR(dest) => (0x05 | reg(dest) << 3, None),
+            Instruction::INX(rp) => (0x03 | pair(rp), None),
+            Instruction::DCX(rp) => (0x0b | pair(rp), None),
+            Instruction::DAD(rp) => (0x09 | pair(rp), None),
+            Instruction::ANA(src) => (0xa0 | reg(src), None),
+            Instruction::ANI(imm) => (0xe6, Some(*imm as u16)),
+            Instruction::ORA(src) => (0xb0 | reg(src), None),
+            Instruction::ORI(imm) => (0xf6, Some(*imm as u16)),
+            Instruction::XRA(src) => (0xa8 | reg(src), None),
+            Instruction::XRI(imm) => (0xee, Some(*imm as u16)),
+            Instruction::CMP(src) => (0xb8 | reg(src), None),
+            Instruction::CPI(imm) => (0xfe, Some(*imm as u16)),
+            Instruction::DAA => (0x27, None),
+            Instruction::RLC => (0x07, None),
+            Instruction::RRC => (0x0f, None),
+            Instruction::RAL => (0x17, None),
+            Instruction::RAR => (0x1f, None),
+            Instruction::CMA => (0x2f, None),
+            Instruction::CMC => (0x3f, None),
+            Instruction::STC => (0x37, None),
+            Instruction::JMP(addr) => (0xc3, Some(*addr)),
+            Instruction::J(condition, addr) => (0xc2 | cond(condition), Some(*addr)),
+            Instruction::CALL(addr) => (0xcd, Some(*addr)),
+            Instruction::C(condition, addr) => (0xc4 | cond(condition), Some(*addr)),
+            Instruction::RET => (0xc9, None),
+            Instruction::R(condition) => (0xc0 | cond(condition), None),
+            Instruction::RST(n) => (0xc7 | (n & 0x07) << 3, None),
+            Instruction::PUSH(rp) => (0xc5 | pair(rp), None),
+            Instruction::POP(rp) => (0xc1 | pair(rp), None),
+            Instruction::PCHL => (0xe9, None),
+            Instruction::XTHL => (0xe3, None),
+            Instruction::SPHL => (0xf9, None),
+            Instruction::IN(port) => (0xdb, Some(*port as u16)),
+            Instruction::OUT(port) => (0xd3, Some(*port as u16)),
+            Instruction::EI => (0xfb, None),
+            Instruction::DI => (0xf3, None),
+            Instruction::HLT => (0x76, None),
+            Instruction::NOP => (0x00, None),
+            Instruction::Unknown => return bytes,
+        };
+
+        bytes.push(opcode);
+        if let Some(operand) = operand {
+            let [low, high] = operand.to_le_bytes();
+            bytes.push(low);
+            // 16 bit operands are stored little endian after the opcode
+            if InstructionParser::bytes_to_read(opcode) == 2 {
+                bytes.push(high);
+            }
+        }
+        bytes
+    }
+}
+
 impl Debug for Instruction {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Instruction::NOP => write!(f, "NOP"),
             Instruction::HLT => write!(f, "HLT"),
-            Instruction::JMP(arg0) => write!(f, "JMP ${:02x}", arg0),
+            Instruction::JMP(addr) => write!(f, "JMP ${addr:04x}"),
             Instruction::Unknown => write!(f, "Unknown"),
             Instruction::EI => write!(f, "EI"),
             Instruction::DI => write!(f, "DI"),
@@ -260,3 +434,15 @@ impl Display for Instruction {
         Debug::fmt(&self, f)
     }
 }
+
+/**
+Parses the syntax printed by `Display`, as `MVI A,#$3f` or `JMP $0018`, and the
+Intel one, as `MVI A,3FH` or `PUSH PSW`. Operands may be constant expressions.
+*/
+impl FromStr for Instruction {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        crate::assembler::parse_instruction(text)
+    }
+}
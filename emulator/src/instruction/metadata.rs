@@ -0,0 +1,376 @@
+use std::fmt::Display;
+
+/// Flags of the PSW, at their bit position in the flags register
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub struct Flags(u8);
+
+impl Flags {
+    pub const SIGN: Flags = Flags(0x80);
+    pub const ZERO: Flags = Flags(0x40);
+    pub const AUX_CARRY: Flags = Flags(0x10);
+    pub const PARITY: Flags = Flags(0x04);
+    pub const CARRY: Flags = Flags(0x01);
+
+    pub fn bits(&self) -> u8 {
+        self.0
+    }
+
+    pub fn contains(&self, flags: Flags) -> bool {
+        self.0 & flags.0 == flags.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+}
+
+/// Prints the flags as in `SZAPC`, with `-` for the missing ones
+impl Display for Flags {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names = [
+            (Flags::SIGN, 'S'),
+            (Flags::ZERO, 'Z'),
+            (Flags::AUX_CARRY, 'A'),
+            (Flags::PARITY, 'P'),
+            (Flags::CARRY, 'C'),
+        ];
+        for (flag, name) in names {
+            write!(f, "{}", if self.contains(flag) { name } else { '-' })?;
+        }
+        Ok(())
+    }
+}
+
+/// Clock cycles of an instruction, which differ for conditional calls and returns
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct Cycles {
+    /// When the condition holds, or for unconditional instructions
+    pub taken: usize,
+
+    /// When the condition does not hold
+    pub not_taken: usize,
+}
+
+impl Cycles {
+    pub fn get(&self, taken: bool) -> usize {
+        if taken {
+            self.taken
+        } else {
+            self.not_taken
+        }
+    }
+}
+
+const NONE: u8 = 0;
+const CY: u8 = Flags::CARRY.0;
+const SZAP: u8 = Flags::SIGN.0 | Flags::ZERO.0 | Flags::AUX_CARRY.0 | Flags::PARITY.0;
+const ALL: u8 = SZAP | CY;
+
+/// Jumps to another address
+pub(super) const BRANCH: u8 = 0x01;
+
+/// Pushes the return address and jumps
+pub(super) const CALL: u8 = 0x02;
+
+/// Pops the return address
+pub(super) const RETURN: u8 = 0x04;
+
+/// Depends on a condition
+pub(super) const CONDITIONAL: u8 = 0x08;
+
+/// Reads memory, besides fetching the instruction
+pub(super) const READS: u8 = 0x10;
+
+/// Writes memory
+pub(super) const WRITES: u8 = 0x20;
+
+/// What is known about an opcode
+pub(super) struct OpcodeInfo {
+    pub size: usize,
+    pub cycles: Cycles,
+    pub flags: Flags,
+    pub kind: u8,
+}
+
+const fn row(size: usize, taken: usize, not_taken: usize, flags: u8, kind: u8) -> OpcodeInfo {
+    OpcodeInfo {
+        size,
+        cycles: Cycles { taken, not_taken },
+        flags: Flags(flags),
+        kind,
+    }
+}
+
+/// Size in bytes of the instruction starting with `opcode`
+pub(crate) fn opcode_size(opcode: u8) -> usize {
+    OPCODES[opcode as usize].size
+}
+
+/// Clock cycles of the instruction starting with `opcode`
+pub(crate) fn opcode_cycles(opcode: u8) -> Cycles {
+    OPCODES[opcode as usize].cycles
+}
+
+/**
+Size in bytes, clock cycles taken and not taken, flags written and kind of
+every opcode, undocumented ones included.
+*/
+#[rustfmt::skip]
+pub(super) static OPCODES: [OpcodeInfo; 256] = [
+    /* 00 NOP       */ row(1, 4, 4, NONE, 0),
+    /* 01 LXI B,d16 */ row(3, 10, 10, NONE, 0),
+    /* 02 STAX B    */ row(1, 7, 7, NONE, WRITES),
+    /* 03 INX B     */ row(1, 5, 5, NONE, 0),
+    /* 04 INR B     */ row(1, 5, 5, SZAP, 0),
+    /* 05 DCR B     */ row(1, 5, 5, SZAP, 0),
+    /* 06 MVI B,d8  */ row(2, 7, 7, NONE, 0),
+    /* 07 RLC       */ row(1, 4, 4, CY, 0),
+    /* 08 NOP       */ row(1, 4, 4, NONE, 0),
+    /* 09 DAD B     */ row(1, 10, 10, CY, 0),
+    /* 0a LDAX B    */ row(1, 7, 7, NONE, READS),
+    /* 0b DCX B     */ row(1, 5, 5, NONE, 0),
+    /* 0c INR C     */ row(1, 5, 5, SZAP, 0),
+    /* 0d DCR C     */ row(1, 5, 5, SZAP, 0),
+    /* 0e MVI C,d8  */ row(2, 7, 7, NONE, 0),
+    /* 0f RRC       */ row(1, 4, 4, CY, 0),
+    /* 10 NOP       */ row(1, 4, 4, NONE, 0),
+    /* 11 LXI D,d16 */ row(3, 10, 10, NONE, 0),
+    /* 12 STAX D    */ row(1, 7, 7, NONE, WRITES),
+    /* 13 INX D     */ row(1, 5, 5, NONE, 0),
+    /* 14 INR D     */ row(1, 5, 5, SZAP, 0),
+    /* 15 DCR D     */ row(1, 5, 5, SZAP, 0),
+    /* 16 MVI D,d8  */ row(2, 7, 7, NONE, 0),
+    /* 17 RAL       */ row(1, 4, 4, CY, 0),
+    /* 18 NOP       */ row(1, 4, 4, NONE, 0),
+    /* 19 DAD D     */ row(1, 10, 10, CY, 0),
+    /* 1a LDAX D    */ row(1, 7, 7, NONE, READS),
+    /* 1b DCX D     */ row(1, 5, 5, NONE, 0),
+    /* 1c INR E     */ row(1, 5, 5, SZAP, 0),
+    /* 1d DCR E     */ row(1, 5, 5, SZAP, 0),
+    /* 1e MVI E,d8  */ row(2, 7, 7, NONE, 0),
+    /* 1f RAR       */ row(1, 4, 4, CY, 0),
+    /* 20 NOP       */ row(1, 4, 4, NONE, 0),
+    /* 21 LXI H,d16 */ row(3, 10, 10, NONE, 0),
+    /* 22 SHLD a16  */ row(3, 16, 16, NONE, WRITES),
+    /* 23 INX H     */ row(1, 5, 5, NONE, 0),
+    /* 24 INR H     */ row(1, 5, 5, SZAP, 0),
+    /* 25 DCR H     */ row(1, 5, 5, SZAP, 0),
+    /* 26 MVI H,d8  */ row(2, 7, 7, NONE, 0),
+    /* 27 DAA       */ row(1, 4, 4, ALL, 0),
+    /* 28 NOP       */ row(1, 4, 4, NONE, 0),
+    /* 29 DAD H     */ row(1, 10, 10, CY, 0),
+    /* 2a LHLD a16  */ row(3, 16, 16, NONE, READS),
+    /* 2b DCX H     */ row(1, 5, 5, NONE, 0),
+    /* 2c INR L     */ row(1, 5, 5, SZAP, 0),
+    /* 2d DCR L     */ row(1, 5, 5, SZAP, 0),
+    /* 2e MVI L,d8  */ row(2, 7, 7, NONE, 0),
+    /* 2f CMA       */ row(1, 4, 4, NONE, 0),
+    /* 30 NOP       */ row(1, 4, 4, NONE, 0),
+    /* 31 LXI SP,d16 */ row(3, 10, 10, NONE, 0),
+    /* 32 STA a16   */ row(3, 13, 13, NONE, WRITES),
+    /* 33 INX SP    */ row(1, 5, 5, NONE, 0),
+    /* 34 INR M     */ row(1, 10, 10, SZAP, READS | WRITES),
+    /* 35 DCR M     */ row(1, 10, 10, SZAP, READS | WRITES),
+    /* 36 MVI M,d8  */ row(2, 10, 10, NONE, WRITES),
+    /* 37 STC       */ row(1, 4, 4, CY, 0),
+    /* 38 NOP       */ row(1, 4, 4, NONE, 0),
+    /* 39 DAD SP    */ row(1, 10, 10, CY, 0),
+    /* 3a LDA a16   */ row(3, 13, 13, NONE, READS),
+    /* 3b DCX SP    */ row(1, 5, 5, NONE, 0),
+    /* 3c INR A     */ row(1, 5, 5, SZAP, 0),
+    /* 3d DCR A     */ row(1, 5, 5, SZAP, 0),
+    /* 3e MVI A,d8  */ row(2, 7, 7, NONE, 0),
+    /* 3f CMC       */ row(1, 4, 4, CY, 0),
+    /* 40 MOV B,B   */ row(1, 5, 5, NONE, 0),
+    /* 41 MOV B,C   */ row(1, 5, 5, NONE, 0),
+    /* 42 MOV B,D   */ row(1, 5, 5, NONE, 0),
+    /* 43 MOV B,E   */ row(1, 5, 5, NONE, 0),
+    /* 44 MOV B,H   */ row(1, 5, 5, NONE, 0),
+    /* 45 MOV B,L   */ row(1, 5, 5, NONE, 0),
+    /* 46 MOV B,M   */ row(1, 7, 7, NONE, READS),
+    /* 47 MOV B,A   */ row(1, 5, 5, NONE, 0),
+    /* 48 MOV C,B   */ row(1, 5, 5, NONE, 0),
+    /* 49 MOV C,C   */ row(1, 5, 5, NONE, 0),
+    /* 4a MOV C,D   */ row(1, 5, 5, NONE, 0),
+    /* 4b MOV C,E   */ row(1, 5, 5, NONE, 0),
+    /* 4c MOV C,H   */ row(1, 5, 5, NONE, 0),
+    /* 4d MOV C,L   */ row(1, 5, 5, NONE, 0),
+    /* 4e MOV C,M   */ row(1, 7, 7, NONE, READS),
+    /* 4f MOV C,A   */ row(1, 5, 5, NONE, 0),
+    /* 50 MOV D,B   */ row(1, 5, 5, NONE, 0),
+    /* 51 MOV D,C   */ row(1, 5, 5, NONE, 0),
+    /* 52 MOV D,D   */ row(1, 5, 5, NONE, 0),
+    /* 53 MOV D,E   */ row(1, 5, 5, NONE, 0),
+    /* 54 MOV D,H   */ row(1, 5, 5, NONE, 0),
+    /* 55 MOV D,L   */ row(1, 5, 5, NONE, 0),
+    /* 56 MOV D,M   */ row(1, 7, 7, NONE, READS),
+    /* 57 MOV D,A   */ row(1, 5, 5, NONE, 0),
+    /* 58 MOV E,B   */ row(1, 5, 5, NONE, 0),
+    /* 59 MOV E,C   */ row(1, 5, 5, NONE, 0),
+    /* 5a MOV E,D   */ row(1, 5, 5, NONE, 0),
+    /* 5b MOV E,E   */ row(1, 5, 5, NONE, 0),
+    /* 5c MOV E,H   */ row(1, 5, 5, NONE, 0),
+    /* 5d MOV E,L   */ row(1, 5, 5, NONE, 0),
+    /* 5e MOV E,M   */ row(1, 7, 7, NONE, READS),
+    /* 5f MOV E,A   */ row(1, 5, 5, NONE, 0),
+    /* 60 MOV H,B   */ row(1, 5, 5, NONE, 0),
+    /* 61 MOV H,C   */ row(1, 5, 5, NONE, 0),
+    /* 62 MOV H,D   */ row(1, 5, 5, NONE, 0),
+    /* 63 MOV H,E   */ row(1, 5, 5, NONE, 0),
+    /* 64 MOV H,H   */ row(1, 5, 5, NONE, 0),
+    /* 65 MOV H,L   */ row(1, 5, 5, NONE, 0),
+    /* 66 MOV H,M   */ row(1, 7, 7, NONE, READS),
+    /* 67 MOV H,A   */ row(1, 5, 5, NONE, 0),
+    /* 68 MOV L,B   */ row(1, 5, 5, NONE, 0),
+    /* 69 MOV L,C   */ row(1, 5, 5, NONE, 0),
+    /* 6a MOV L,D   */ row(1, 5, 5, NONE, 0),
+    /* 6b MOV L,E   */ row(1, 5, 5, NONE, 0),
+    /* 6c MOV L,H   */ row(1, 5, 5, NONE, 0),
+    /* 6d MOV L,L   */ row(1, 5, 5, NONE, 0),
+    /* 6e MOV L,M   */ row(1, 7, 7, NONE, READS),
+    /* 6f MOV L,A   */ row(1, 5, 5, NONE, 0),
+    /* 70 MOV M,B   */ row(1, 7, 7, NONE, WRITES),
+    /* 71 MOV M,C   */ row(1, 7, 7, NONE, WRITES),
+    /* 72 MOV M,D   */ row(1, 7, 7, NONE, WRITES),
+    /* 73 MOV M,E   */ row(1, 7, 7, NONE, WRITES),
+    /* 74 MOV M,H   */ row(1, 7, 7, NONE, WRITES),
+    /* 75 MOV M,L   */ row(1, 7, 7, NONE, WRITES),
+    /* 76 HLT       */ row(1, 7, 7, NONE, 0),
+    /* 77 MOV M,A   */ row(1, 7, 7, NONE, WRITES),
+    /* 78 MOV A,B   */ row(1, 5, 5, NONE, 0),
+    /* 79 MOV A,C   */ row(1, 5, 5, NONE, 0),
+    /* 7a MOV A,D   */ row(1, 5, 5, NONE, 0),
+    /* 7b MOV A,E   */ row(1, 5, 5, NONE, 0),
+    /* 7c MOV A,H   */ row(1, 5, 5, NONE, 0),
+    /* 7d MOV A,L   */ row(1, 5, 5, NONE, 0),
+    /* 7e MOV A,M   */ row(1, 7, 7, NONE, READS),
+    /* 7f MOV A,A   */ row(1, 5, 5, NONE, 0),
+    /* 80 ADD B     */ row(1, 4, 4, ALL, 0),
+    /* 81 ADD C     */ row(1, 4, 4, ALL, 0),
+    /* 82 ADD D     */ row(1, 4, 4, ALL, 0),
+    /* 83 ADD E     */ row(1, 4, 4, ALL, 0),
+    /* 84 ADD H     */ row(1, 4, 4, ALL, 0),
+    /* 85 ADD L     */ row(1, 4, 4, ALL, 0),
+    /* 86 ADD M     */ row(1, 7, 7, ALL, READS),
+    /* 87 ADD A     */ row(1, 4, 4, ALL, 0),
+    /* 88 ADC B     */ row(1, 4, 4, ALL, 0),
+    /* 89 ADC C     */ row(1, 4, 4, ALL, 0),
+    /* 8a ADC D     */ row(1, 4, 4, ALL, 0),
+    /* 8b ADC E     */ row(1, 4, 4, ALL, 0),
+    /* 8c ADC H     */ row(1, 4, 4, ALL, 0),
+    /* 8d ADC L     */ row(1, 4, 4, ALL, 0),
+    /* 8e ADC M     */ row(1, 7, 7, ALL, READS),
+    /* 8f ADC A     */ row(1, 4, 4, ALL, 0),
+    /* 90 SUB B     */ row(1, 4, 4, ALL, 0),
+    /* 91 SUB C     */ row(1, 4, 4, ALL, 0),
+    /* 92 SUB D     */ row(1, 4, 4, ALL, 0),
+    /* 93 SUB E     */ row(1, 4, 4, ALL, 0),
+    /* 94 SUB H     */ row(1, 4, 4, ALL, 0),
+    /* 95 SUB L     */ row(1, 4, 4, ALL, 0),
+    /* 96 SUB M     */ row(1, 7, 7, ALL, READS),
+    /* 97 SUB A     */ row(1, 4, 4, ALL, 0),
+    /* 98 SBB B     */ row(1, 4, 4, ALL, 0),
+    /* 99 SBB C     */ row(1, 4, 4, ALL, 0),
+    /* 9a SBB D     */ row(1, 4, 4, ALL, 0),
+    /* 9b SBB E     */ row(1, 4, 4, ALL, 0),
+    /* 9c SBB H     */ row(1, 4, 4, ALL, 0),
+    /* 9d SBB L     */ row(1, 4, 4, ALL, 0),
+    /* 9e SBB M     */ row(1, 7, 7, ALL, READS),
+    /* 9f SBB A     */ row(1, 4, 4, ALL, 0),
+    /* a0 ANA B     */ row(1, 4, 4, ALL, 0),
+    /* a1 ANA C     */ row(1, 4, 4, ALL, 0),
+    /* a2 ANA D     */ row(1, 4, 4, ALL, 0),
+    /* a3 ANA E     */ row(1, 4, 4, ALL, 0),
+    /* a4 ANA H     */ row(1, 4, 4, ALL, 0),
+    /* a5 ANA L     */ row(1, 4, 4, ALL, 0),
+    /* a6 ANA M     */ row(1, 7, 7, ALL, READS),
+    /* a7 ANA A     */ row(1, 4, 4, ALL, 0),
+    /* a8 XRA B     */ row(1, 4, 4, ALL, 0),
+    /* a9 XRA C     */ row(1, 4, 4, ALL, 0),
+    /* aa XRA D     */ row(1, 4, 4, ALL, 0),
+    /* ab XRA E     */ row(1, 4, 4, ALL, 0),
+    /* ac XRA H     */ row(1, 4, 4, ALL, 0),
+    /* ad XRA L     */ row(1, 4, 4, ALL, 0),
+    /* ae XRA M     */ row(1, 7, 7, ALL, READS),
+    /* af XRA A     */ row(1, 4, 4, ALL, 0),
+    /* b0 ORA B     */ row(1, 4, 4, ALL, 0),
+    /* b1 ORA C     */ row(1, 4, 4, ALL, 0),
+    /* b2 ORA D     */ row(1, 4, 4, ALL, 0),
+    /* b3 ORA E     */ row(1, 4, 4, ALL, 0),
+    /* b4 ORA H     */ row(1, 4, 4, ALL, 0),
+    /* b5 ORA L     */ row(1, 4, 4, ALL, 0),
+    /* b6 ORA M     */ row(1, 7, 7, ALL, READS),
+    /* b7 ORA A     */ row(1, 4, 4, ALL, 0),
+    /* b8 CMP B     */ row(1, 4, 4, ALL, 0),
+    /* b9 CMP C     */ row(1, 4, 4, ALL, 0),
+    /* ba CMP D     */ row(1, 4, 4, ALL, 0),
+    /* bb CMP E     */ row(1, 4, 4, ALL, 0),
+    /* bc CMP H     */ row(1, 4, 4, ALL, 0),
+    /* bd CMP L     */ row(1, 4, 4, ALL, 0),
+    /* be CMP M     */ row(1, 7, 7, ALL, READS),
+    /* bf CMP A     */ row(1, 4, 4, ALL, 0),
+    /* c0 RNZ       */ row(1, 11, 5, NONE, RETURN | CONDITIONAL | READS),
+    /* c1 POP B     */ row(1, 10, 10, NONE, READS),
+    /* c2 JNZ a16   */ row(3, 10, 10, NONE, BRANCH | CONDITIONAL),
+    /* c3 JMP a16   */ row(3, 10, 10, NONE, BRANCH),
+    /* c4 CNZ a16   */ row(3, 17, 11, NONE, CALL | CONDITIONAL | WRITES),
+    /* c5 PUSH B    */ row(1, 11, 11, NONE, WRITES),
+    /* c6 ADI d8    */ row(2, 7, 7, ALL, 0),
+    /* c7 RST 0     */ row(1, 11, 11, NONE, CALL | WRITES),
+    /* c8 RZ        */ row(1, 11, 5, NONE, RETURN | CONDITIONAL | READS),
+    /* c9 RET       */ row(1, 10, 10, NONE, RETURN | READS),
+    /* ca JZ a16    */ row(3, 10, 10, NONE, BRANCH | CONDITIONAL),
+    /* cb JMP a16   */ row(3, 10, 10, NONE, BRANCH),
+    /* cc CZ a16    */ row(3, 17, 11, NONE, CALL | CONDITIONAL | WRITES),
+    /* cd CALL a16  */ row(3, 17, 17, NONE, CALL | WRITES),
+    /* ce ACI d8    */ row(2, 7, 7, ALL, 0),
+    /* cf RST 1     */ row(1, 11, 11, NONE, CALL | WRITES),
+    /* d0 RNC       */ row(1, 11, 5, NONE, RETURN | CONDITIONAL | READS),
+    /* d1 POP D     */ row(1, 10, 10, NONE, READS),
+    /* d2 JNC a16   */ row(3, 10, 10, NONE, BRANCH | CONDITIONAL),
+    /* d3 OUT d8    */ row(2, 10, 10, NONE, 0),
+    /* d4 CNC a16   */ row(3, 17, 11, NONE, CALL | CONDITIONAL | WRITES),
+    /* d5 PUSH D    */ row(1, 11, 11, NONE, WRITES),
+    /* d6 SUI d8    */ row(2, 7, 7, ALL, 0),
+    /* d7 RST 2     */ row(1, 11, 11, NONE, CALL | WRITES),
+    /* d8 RC        */ row(1, 11, 5, NONE, RETURN | CONDITIONAL | READS),
+    /* d9 RET       */ row(1, 10, 10, NONE, RETURN | READS),
+    /* da JC a16    */ row(3, 10, 10, NONE, BRANCH | CONDITIONAL),
+    /* db IN d8     */ row(2, 10, 10, NONE, 0),
+    /* dc CC a16    */ row(3, 17, 11, NONE, CALL | CONDITIONAL | WRITES),
+    /* dd CALL a16  */ row(3, 17, 17, NONE, CALL | WRITES),
+    /* de SBI d8    */ row(2, 7, 7, ALL, 0),
+    /* df RST 3     */ row(1, 11, 11, NONE, CALL | WRITES),
+    /* e0 RPO       */ row(1, 11, 5, NONE, RETURN | CONDITIONAL | READS),
+    /* e1 POP H     */ row(1, 10, 10, NONE, READS),
+    /* e2 JPO a16   */ row(3, 10, 10, NONE, BRANCH | CONDITIONAL),
+    /* e3 XTHL      */ row(1, 18, 18, NONE, READS | WRITES),
+    /* e4 CPO a16   */ row(3, 17, 11, NONE, CALL | CONDITIONAL | WRITES),
+    /* e5 PUSH H    */ row(1, 11, 11, NONE, WRITES),
+    /* e6 ANI d8    */ row(2, 7, 7, ALL, 0),
+    /* e7 RST 4     */ row(1, 11, 11, NONE, CALL | WRITES),
+    /* e8 RPE       */ row(1, 11, 5, NONE, RETURN | CONDITIONAL | READS),
+    /* e9 PCHL      */ row(1, 5, 5, NONE, BRANCH),
+    /* ea JPE a16   */ row(3, 10, 10, NONE, BRANCH | CONDITIONAL),
+    /* eb XCHG      */ row(1, 4, 4, NONE, 0),
+    /* ec CPE a16   */ row(3, 17, 11, NONE, CALL | CONDITIONAL | WRITES),
+    /* ed CALL a16  */ row(3, 17, 17, NONE, CALL | WRITES),
+    /* ee XRI d8    */ row(2, 7, 7, ALL, 0),
+    /* ef RST 5     */ row(1, 11, 11, NONE, CALL | WRITES),
+    /* f0 RP        */ row(1, 11, 5, NONE, RETURN | CONDITIONAL | READS),
+    /* f1 POP PSW   */ row(1, 10, 10, ALL, READS),
+    /* f2 JP a16    */ row(3, 10, 10, NONE, BRANCH | CONDITIONAL),
+    /* f3 DI        */ row(1, 4, 4, NONE, 0),
+    /* f4 CP a16    */ row(3, 17, 11, NONE, CALL | CONDITIONAL | WRITES),
+    /* f5 PUSH PSW  */ row(1, 11, 11, NONE, WRITES),
+    /* f6 ORI d8    */ row(2, 7, 7, ALL, 0),
+    /* f7 RST 6     */ row(1, 11, 11, NONE, CALL | WRITES),
+    /* f8 RM        */ row(1, 11, 5, NONE, RETURN | CONDITIONAL | READS),
+    /* f9 SPHL      */ row(1, 5, 5, NONE, 0),
+    /* fa JM a16    */ row(3, 10, 10, NONE, BRANCH | CONDITIONAL),
+    /* fb EI        */ row(1, 4, 4, NONE, 0),
+    /* fc CM a16    */ row(3, 17, 11, NONE, CALL | CONDITIONAL | WRITES),
+    /* fd CALL a16  */ row(3, 17, 17, NONE, CALL | WRITES),
+    /* fe CPI d8    */ row(2, 7, 7, ALL, 0),
+    /* ff RST 7     */ row(1, 11, 11, NONE, CALL | WRITES),
+];
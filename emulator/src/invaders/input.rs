@@ -0,0 +1,220 @@
+use std::str::FromStr;
+
+/// Cabinet controls wired to input ports 0 to 2
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy)]
+pub enum Button {
+    Coin,
+    P1Start,
+    P2Start,
+    P1Fire,
+    P1Left,
+    P1Right,
+    P2Fire,
+    P2Left,
+    P2Right,
+    Tilt,
+}
+
+/// Fire and movement buttons of a single player
+#[derive(Default, PartialEq, Eq, Debug, Clone, Copy)]
+pub struct PlayerControls {
+    pub fire: bool,
+    pub left: bool,
+    pub right: bool,
+}
+
+impl PlayerControls {
+    /// Bits 4 to 6 of an input port
+    fn bits(&self) -> u8 {
+        (self.fire as u8) << 4 | (self.left as u8) << 5 | (self.right as u8) << 6
+    }
+
+    fn from_bits(port: u8) -> PlayerControls {
+        PlayerControls {
+            fire: port & 0x10 != 0,
+            left: port & 0x20 != 0,
+            right: port & 0x40 != 0,
+        }
+    }
+}
+
+/// State of every button on the cabinet, `true` meaning pressed
+#[derive(Default, PartialEq, Eq, Debug, Clone, Copy)]
+pub struct InvadersInputs {
+    pub coin: bool,
+    pub p1_start: bool,
+    pub p2_start: bool,
+    pub tilt: bool,
+    pub player1: PlayerControls,
+    pub player2: PlayerControls,
+}
+
+impl InvadersInputs {
+    pub fn set(&mut self, button: Button, pressed: bool) {
+        let state = match button {
+            Button::Coin => &mut self.coin,
+            Button::P1Start => &mut self.p1_start,
+            Button::P2Start => &mut self.p2_start,
+            Button::P1Fire => &mut self.player1.fire,
+            Button::P1Left => &mut self.player1.left,
+            Button::P1Right => &mut self.player1.right,
+            Button::P2Fire => &mut self.player2.fire,
+            Button::P2Left => &mut self.player2.left,
+            Button::P2Right => &mut self.player2.right,
+            Button::Tilt => &mut self.tilt,
+        };
+        *state = pressed;
+    }
+
+    /**
+    Values read by `IN 0`, `IN 1` and `IN 2`.
+
+    ```text
+    Port 0: bit 0 self test (DIP 4), bits 1-3 always 1, bits 4-6 P1 fire/left/right
+    Port 1: bit 0 coin, bit 1 P2 start, bit 2 P1 start, bit 3 always 1, bits 4-6 P1 fire/left/right
+    Port 2: bits 0-1 lives (DIP 3, 5), bit 2 tilt, bit 3 bonus life (DIP 6),
+            bits 4-6 P2 fire/left/right, bit 7 coin info (DIP 7)
+    ```
+    */
+    pub fn ports(&self, dip_switches: &DipSwitches) -> [u8; 3] {
+        let port0 = 0x0e | self.player1.bits();
+
+        let port1 = self.coin as u8
+            | (self.p2_start as u8) << 1
+            | (self.p1_start as u8) << 2
+            | 0x08
+            | self.player1.bits();
+
+        let lives = dip_switches
+            .lives
+            .clamp(DipSwitches::MIN_LIVES, DipSwitches::MAX_LIVES);
+        let port2 = (lives - DipSwitches::MIN_LIVES)
+            | (self.tilt as u8) << 2
+            | (dip_switches.bonus_life_at_1000 as u8) << 3
+            | self.player2.bits()
+            // The switch is active low: 0 shows the coin info
+            | (!dip_switches.coin_info as u8) << 7;
+
+        [port0, port1, port2]
+    }
+
+    /// Inverse of [`InvadersInputs::ports`], port 0 only mirrors player 1 and is ignored
+    pub fn from_ports(ports: &[u8; 3]) -> (InvadersInputs, DipSwitches) {
+        let [_, port1, port2] = *ports;
+        let inputs = InvadersInputs {
+            coin: port1 & 0x01 != 0,
+            p2_start: port1 & 0x02 != 0,
+            p1_start: port1 & 0x04 != 0,
+            tilt: port2 & 0x04 != 0,
+            player1: PlayerControls::from_bits(port1),
+            player2: PlayerControls::from_bits(port2),
+        };
+        let dip_switches = DipSwitches {
+            lives: (port2 & 0x03) + DipSwitches::MIN_LIVES,
+            bonus_life_at_1000: port2 & 0x08 != 0,
+            coin_info: port2 & 0x80 == 0,
+        };
+        (inputs, dip_switches)
+    }
+}
+
+/// Operator settings of the DIP switch bank read through port 2
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct DipSwitches {
+    /// Ships at the start of a game, from 3 to 6
+    pub lives: u8,
+
+    /// Award the extra ship at 1000 points instead of 1500
+    pub bonus_life_at_1000: bool,
+
+    /// Show the coin info on the attract screen
+    pub coin_info: bool,
+}
+
+impl Default for DipSwitches {
+    fn default() -> Self {
+        DipSwitches {
+            lives: 3,
+            bonus_life_at_1000: false,
+            coin_info: true,
+        }
+    }
+}
+
+impl DipSwitches {
+    pub const MIN_LIVES: u8 = 3;
+    pub const MAX_LIVES: u8 = 6;
+
+    pub fn set_lives(&mut self, lives: u8) -> Result<(), String> {
+        if !(Self::MIN_LIVES..=Self::MAX_LIVES).contains(&lives) {
+            return Err(format!(
+                "Lives must be between {} and {}, got {lives}",
+                Self::MIN_LIVES,
+                Self::MAX_LIVES
+            ));
+        }
+        self.lives = lives;
+        Ok(())
+    }
+
+    pub fn set_bonus_life(&mut self, points: u16) -> Result<(), String> {
+        self.bonus_life_at_1000 = match points {
+            1000 => true,
+            1500 => false,
+            _ => return Err(format!("Bonus life must be at 1000 or 1500, got {points}")),
+        };
+        Ok(())
+    }
+}
+
+/**
+Parses a configuration file of `key = value` lines, `#` starting a comment.
+
+```text
+# Settings of the arcade operator
+lives = 5
+bonus_life = 1000
+coin_info = false
+```
+
+Keys that are not present keep their default value.
+*/
+impl FromStr for DipSwitches {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut dip_switches = DipSwitches::default();
+
+        for (index, line) in s.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => return Err(format!("Line {line_number}: expected `key = value`")),
+            };
+
+            let result = match key {
+                "lives" => value
+                    .parse()
+                    .map_err(|_| format!("Invalid number of lives `{value}`"))
+                    .and_then(|lives| dip_switches.set_lives(lives)),
+                "bonus_life" => value
+                    .parse()
+                    .map_err(|_| format!("Invalid bonus life score `{value}`"))
+                    .and_then(|points| dip_switches.set_bonus_life(points)),
+                "coin_info" => value
+                    .parse()
+                    .map(|coin_info| dip_switches.coin_info = coin_info)
+                    .map_err(|_| format!("Expected true or false, got `{value}`")),
+                _ => Err(format!("Unknown key `{key}`")),
+            };
+            result.map_err(|err| format!("Line {line_number}: {err}"))?;
+        }
+
+        Ok(dip_switches)
+    }
+}
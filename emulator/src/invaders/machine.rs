@@ -0,0 +1,154 @@
+use crate::cpu::{Bus, CPU};
+
+use super::{
+    input::{Button, DipSwitches, InvadersInputs},
+    sound::{SoundEvent, SoundPorts},
+};
+
+/// Width of the upright (rotated) screen in pixels
+pub const SCREEN_WIDTH: usize = 224;
+
+/// Height of the upright (rotated) screen in pixels
+pub const SCREEN_HEIGHT: usize = 256;
+
+const VIDEO_RAM_START: usize = 0x2400;
+const VIDEO_RAM_END: usize = 0x4000;
+
+/// The CPU runs at 2 MHz and the screen refreshes at 60 Hz
+const CYCLES_PER_FRAME: usize = 2_000_000 / 60;
+
+/// Everything living on the I/O ports of the Space Invaders board
+struct Hardware {
+    inputs: InvadersInputs,
+    dip_switches: DipSwitches,
+
+    /// The external 16 bit shift register read through port 3
+    shift_register: u16,
+    shift_offset: u8,
+
+    sound_ports: SoundPorts,
+
+    /// Sounds triggered since the front-end last took them
+    sound_events: Vec<SoundEvent>,
+}
+
+impl Bus for Hardware {
+    fn input(&mut self, port: u8) -> u8 {
+        match port {
+            0..=2 => self.inputs.ports(&self.dip_switches)[port as usize],
+            3 => ((self.shift_register >> (8 - self.shift_offset)) & 0x00ff) as u8,
+            _ => 0,
+        }
+    }
+
+    fn output(&mut self, port: u8, value: u8) {
+        match port {
+            2 => self.shift_offset = value & 0x07,
+            3 | 5 => self.sound_ports.write(port, value, &mut self.sound_events),
+            4 => self.shift_register = ((value as u16) << 8) | (self.shift_register >> 8),
+            // The watchdog (6) is not emulated
+            _ => {}
+        }
+    }
+}
+
+/// The Space Invaders arcade board: an 8080, 8 KiB of ROM, RAM with a
+/// 256x224 bitmap and the shift register hardware
+pub struct Invaders {
+    cpu: CPU,
+    hardware: Hardware,
+}
+
+impl Invaders {
+    pub fn new(rom: &[u8], dip_switches: DipSwitches) -> Invaders {
+        let mut cpu = CPU::new();
+        cpu.load_program(rom);
+        Invaders {
+            cpu,
+            hardware: Hardware {
+                inputs: InvadersInputs::default(),
+                dip_switches,
+                shift_register: 0,
+                shift_offset: 0,
+                sound_ports: SoundPorts::default(),
+                sound_events: vec![],
+            },
+        }
+    }
+
+    pub fn inputs(&self) -> InvadersInputs {
+        self.hardware.inputs
+    }
+
+    pub fn set_inputs(&mut self, inputs: InvadersInputs) {
+        self.hardware.inputs = inputs;
+    }
+
+    pub fn set_button(&mut self, button: Button, pressed: bool) {
+        self.hardware.inputs.set(button, pressed);
+    }
+
+    /// Values of input ports 0 to 2, buttons and DIP switches included
+    pub fn input_ports(&self) -> [u8; 3] {
+        self.hardware.inputs.ports(&self.hardware.dip_switches)
+    }
+
+    /// Sets buttons and DIP switches from the values of input ports 0 to 2
+    pub fn set_input_ports(&mut self, ports: &[u8; 3]) {
+        let (inputs, dip_switches) = InvadersInputs::from_ports(ports);
+        self.hardware.inputs = inputs;
+        self.hardware.dip_switches = dip_switches;
+    }
+
+    fn run_cycles(&mut self, cycles: usize) {
+        let mut elapsed = 0;
+        while elapsed < cycles {
+            elapsed += self.cpu.step(&mut self.hardware);
+        }
+    }
+
+    /**
+    Runs the CPU for one 60 Hz frame.
+
+    The video hardware raises `RST 1` when the beam reaches the middle of the
+    screen and `RST 2` at the start of the vertical blank.
+    */
+    pub fn run_frame(&mut self) {
+        self.run_cycles(CYCLES_PER_FRAME / 2);
+        self.cpu.interrupt(1);
+        self.run_cycles(CYCLES_PER_FRAME / 2);
+        self.cpu.interrupt(2);
+    }
+
+    /// Sounds triggered since the last call, in order
+    pub fn take_sound_events(&mut self) -> Vec<SoundEvent> {
+        std::mem::take(&mut self.hardware.sound_events)
+    }
+
+    /// Raw video memory, one bit per pixel, as the CPU sees it
+    pub fn video_ram(&self) -> &[u8] {
+        &self.cpu.memory()[VIDEO_RAM_START..VIDEO_RAM_END]
+    }
+
+    /// FNV-1a hash of the video memory, identifying what is on screen
+    pub fn frame_hash(&self) -> u64 {
+        self.video_ram()
+            .iter()
+            .fold(0xcbf29ce484222325, |hash, byte| {
+                (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+            })
+    }
+
+    /**
+    Whether the pixel at `(x, y)` of the upright screen is lit.
+
+    The monitor is mounted rotated 90° counterclockwise, so each byte of video
+    memory holds 8 vertical pixels, bottom to top, of a screen column.
+    */
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        assert!(x < SCREEN_WIDTH && y < SCREEN_HEIGHT);
+        let row_in_memory = SCREEN_HEIGHT - 1 - y;
+        let byte = self.video_ram()[x * SCREEN_HEIGHT / 8 + row_in_memory / 8];
+        byte & (1 << (row_in_memory % 8)) != 0
+    }
+}
@@ -0,0 +1,10 @@
+mod input;
+mod machine;
+mod movie;
+mod rom;
+mod sound;
+pub use input::{Button, DipSwitches, InvadersInputs, PlayerControls};
+pub use machine::{Invaders, SCREEN_HEIGHT, SCREEN_WIDTH};
+pub use movie::Movie;
+pub use rom::{load_split_rom, Chip, CHIPS};
+pub use sound::{Mixer, SoundEvent};
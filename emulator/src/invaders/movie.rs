@@ -0,0 +1,154 @@
+use std::{fmt::Display, str::FromStr};
+
+use super::machine::Invaders;
+
+const HEADER: &str = "EIGHTYEIGHTY-MOVIE 1";
+
+/// Longest movie accepted when parsing, a day of frames at 60 Hz
+const MAX_FRAMES: usize = 24 * 60 * 60 * 60;
+
+/**
+A recording of the input ports of every frame of a Space Invaders session.
+
+Since the machine is deterministic, feeding the same inputs to a freshly booted
+machine reproduces the session exactly, which `final_hash` verifies.
+
+The text format starts with a header and the final hash, followed by
+run-length encoded frames as a repeat count and the three input ports in hex:
+
+```text
+EIGHTYEIGHTY-MOVIE 1
+hash 5b6e07d40d98ad42
+200 0e 08 00
+10 0e 09 00
+```
+*/
+#[derive(Default, PartialEq, Eq, Debug, Clone)]
+pub struct Movie {
+    /// Values of input ports 0 to 2 for each frame
+    pub frames: Vec<[u8; 3]>,
+
+    /// [`Invaders::frame_hash`] after the last frame
+    pub final_hash: u64,
+}
+
+impl Movie {
+    /// Stores the inputs of `machine` for the frame it is about to run
+    pub fn record_frame(&mut self, machine: &Invaders) {
+        self.frames.push(machine.input_ports());
+    }
+
+    /// Stores the state the replay must end in
+    pub fn finish(&mut self, machine: &Invaders) {
+        self.final_hash = machine.frame_hash();
+    }
+
+    /// Sets the inputs of `machine` for `frame`, returns `false` once the movie is over
+    pub fn apply_frame(&self, machine: &mut Invaders, frame: usize) -> bool {
+        match self.frames.get(frame) {
+            Some(ports) => {
+                machine.set_input_ports(ports);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Checks that `machine` ended the replay in the recorded state
+    pub fn verify(&self, machine: &Invaders) -> Result<(), String> {
+        let hash = machine.frame_hash();
+        if hash != self.final_hash {
+            return Err(format!(
+                "Replay desynchronized: final frame hash is {hash:016x}, the movie expects {:016x}",
+                self.final_hash
+            ));
+        }
+        Ok(())
+    }
+
+    /// Boots `rom`, replays the whole movie and verifies the final state
+    pub fn replay(&self, rom: &[u8]) -> Result<Invaders, String> {
+        let mut machine = Invaders::new(rom, Default::default());
+        let mut frame = 0;
+        while self.apply_frame(&mut machine, frame) {
+            machine.run_frame();
+            frame += 1;
+        }
+        self.verify(&machine)?;
+        Ok(machine)
+    }
+}
+
+impl Display for Movie {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{HEADER}")?;
+        writeln!(f, "hash {:016x}", self.final_hash)?;
+
+        let mut frames = self.frames.iter().peekable();
+        while let Some(ports) = frames.next() {
+            let mut repeat = 1;
+            while frames.next_if_eq(&ports).is_some() {
+                repeat += 1;
+            }
+            writeln!(
+                f,
+                "{repeat} {:02x} {:02x} {:02x}",
+                ports[0], ports[1], ports[2]
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Movie {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.trim()));
+
+        match lines.next() {
+            Some((_, HEADER)) => {}
+            _ => return Err(format!("Line 1: expected `{HEADER}`")),
+        }
+
+        let final_hash = match lines.next() {
+            Some((line_number, line)) => line
+                .strip_prefix("hash ")
+                .and_then(|hash| u64::from_str_radix(hash, 16).ok())
+                .ok_or(format!(
+                    "Line {line_number}: expected `hash <16 hex digits>`"
+                ))?,
+            None => return Err("Line 2: expected `hash <16 hex digits>`".to_owned()),
+        };
+
+        let mut frames = vec![];
+        for (line_number, line) in lines {
+            if line.is_empty() {
+                continue;
+            }
+            let invalid =
+                || format!("Line {line_number}: expected `<repeat> <port 0> <port 1> <port 2>`");
+
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() != 4 {
+                return Err(invalid());
+            }
+            let repeat: usize = fields[0].parse().map_err(|_| invalid())?;
+            if repeat > MAX_FRAMES - frames.len() {
+                return Err(format!(
+                    "Line {line_number}: the movie is longer than {MAX_FRAMES} frames"
+                ));
+            }
+            let mut ports = [0; 3];
+            for (port, field) in ports.iter_mut().zip(&fields[1..]) {
+                *port = u8::from_str_radix(field, 16).map_err(|_| invalid())?;
+            }
+            frames.extend(std::iter::repeat_n(ports, repeat));
+        }
+
+        Ok(Movie { frames, final_hash })
+    }
+}
@@ -0,0 +1,88 @@
+use std::path::Path;
+
+/// Size of each ROM chip, 2 KiB
+const CHIP_SIZE: usize = 0x800;
+
+/// One of the ROM chips of the Space Invaders board, with the checksums of a good dump
+pub struct Chip {
+    pub name: &'static str,
+    pub address: u16,
+    pub crc32: u32,
+    pub sha1: &'static str,
+}
+
+/// The chips of the original board, in address order
+pub const CHIPS: [Chip; 4] = [
+    Chip {
+        name: "invaders.h",
+        address: 0x0000,
+        crc32: 0x734f5ad8,
+        sha1: "ff6200af4c9110d8181249cbcef1a8a40fa40b7f",
+    },
+    Chip {
+        name: "invaders.g",
+        address: 0x0800,
+        crc32: 0x6bfaca4a,
+        sha1: "16f48649b531bdef8c2d1446c429b5f414524350",
+    },
+    Chip {
+        name: "invaders.f",
+        address: 0x1000,
+        crc32: 0x0ccead96,
+        sha1: "537aef03468f63c5b9e11dd61e253f7ae17d9743",
+    },
+    Chip {
+        name: "invaders.e",
+        address: 0x1800,
+        crc32: 0x14e538b0,
+        sha1: "1d6ca0c99f9df71e2990b610deb9d7da0125e2d8",
+    },
+];
+
+impl Chip {
+    /// Checks that `bytes` are a good dump of the chip
+    pub fn verify(&self, bytes: &[u8]) -> Result<(), String> {
+        if bytes.len() != CHIP_SIZE {
+            return Err(format!(
+                "{} is {} bytes long instead of {CHIP_SIZE}",
+                self.name,
+                bytes.len()
+            ));
+        }
+        let crc32 = crc32fast::hash(bytes);
+        if crc32 != self.crc32 {
+            return Err(format!(
+                "{} is a bad dump: CRC32 {crc32:08x}, expected {:08x}",
+                self.name, self.crc32
+            ));
+        }
+        let sha1 = sha1_smol::Sha1::from(bytes).digest().to_string();
+        if sha1 != self.sha1 {
+            return Err(format!(
+                "{} is a bad dump: SHA1 {sha1}, expected {}",
+                self.name, self.sha1
+            ));
+        }
+        Ok(())
+    }
+}
+
+/**
+Reads the four ROM chips from `directory` and places them at their addresses,
+giving the 8 KiB ROM. Names are also looked up in upper case, as on some dumps.
+*/
+pub fn load_split_rom(directory: &Path) -> Result<Vec<u8>, String> {
+    let mut rom = vec![0; CHIPS.len() * CHIP_SIZE];
+    for chip in &CHIPS {
+        let path = directory.join(chip.name);
+        let upper_path = directory.join(chip.name.to_ascii_uppercase());
+        let bytes = std::fs::read(&path)
+            .or_else(|_| std::fs::read(&upper_path))
+            .map_err(|err| format!("Could not read {}: {err}", path.display()))?;
+        chip.verify(&bytes)?;
+
+        let start = chip.address as usize;
+        rom[start..start + CHIP_SIZE].copy_from_slice(&bytes);
+    }
+    Ok(rom)
+}
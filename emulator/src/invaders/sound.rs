@@ -0,0 +1,246 @@
+use std::{collections::BTreeMap, path::Path};
+
+/// Sounds triggered by the game through bits of output ports 3 and 5
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy)]
+pub enum SoundEvent {
+    /// Flying saucer, port 3 bit 0
+    Ufo,
+
+    /// Player shot, port 3 bit 1
+    Shot,
+
+    /// Player ship explosion, port 3 bit 2
+    PlayerDeath,
+
+    /// Invader explosion, port 3 bit 3
+    InvaderHit,
+
+    /// Extra ship awarded, port 3 bit 4
+    ExtendedPlay,
+
+    /// First of the four fleet movement notes, port 5 bit 0
+    FleetMovement1,
+
+    /// Port 5 bit 1
+    FleetMovement2,
+
+    /// Port 5 bit 2
+    FleetMovement3,
+
+    /// Port 5 bit 3
+    FleetMovement4,
+
+    /// Flying saucer explosion, port 5 bit 4
+    UfoHit,
+}
+
+impl SoundEvent {
+    pub const ALL: [SoundEvent; 10] = [
+        SoundEvent::Ufo,
+        SoundEvent::Shot,
+        SoundEvent::PlayerDeath,
+        SoundEvent::InvaderHit,
+        SoundEvent::ExtendedPlay,
+        SoundEvent::FleetMovement1,
+        SoundEvent::FleetMovement2,
+        SoundEvent::FleetMovement3,
+        SoundEvent::FleetMovement4,
+        SoundEvent::UfoHit,
+    ];
+
+    /// Output port and bit mask triggering the sound
+    pub fn port_and_mask(&self) -> (u8, u8) {
+        match self {
+            SoundEvent::Ufo => (3, 0x01),
+            SoundEvent::Shot => (3, 0x02),
+            SoundEvent::PlayerDeath => (3, 0x04),
+            SoundEvent::InvaderHit => (3, 0x08),
+            SoundEvent::ExtendedPlay => (3, 0x10),
+            SoundEvent::FleetMovement1 => (5, 0x01),
+            SoundEvent::FleetMovement2 => (5, 0x02),
+            SoundEvent::FleetMovement3 => (5, 0x04),
+            SoundEvent::FleetMovement4 => (5, 0x08),
+            SoundEvent::UfoHit => (5, 0x10),
+        }
+    }
+
+    /// Name of the sample file, without the `.wav` extension
+    pub fn sample_name(&self) -> &'static str {
+        match self {
+            SoundEvent::Ufo => "ufo",
+            SoundEvent::Shot => "shot",
+            SoundEvent::PlayerDeath => "player_death",
+            SoundEvent::InvaderHit => "invader_hit",
+            SoundEvent::ExtendedPlay => "extended_play",
+            SoundEvent::FleetMovement1 => "fleet1",
+            SoundEvent::FleetMovement2 => "fleet2",
+            SoundEvent::FleetMovement3 => "fleet3",
+            SoundEvent::FleetMovement4 => "fleet4",
+            SoundEvent::UfoHit => "ufo_hit",
+        }
+    }
+}
+
+/// Port 3 bit 5 powers the amplifier, the game turns it off during the attract mode
+const AMPLIFIER_ENABLE: u8 = 0x20;
+
+/// Last values written to the sound ports
+#[derive(Default)]
+pub(super) struct SoundPorts {
+    port3: u8,
+    port5: u8,
+}
+
+impl SoundPorts {
+    /// Latches `value` on `port`, pushing an event for every bit going from 0 to 1
+    pub(super) fn write(&mut self, port: u8, value: u8, events: &mut Vec<SoundEvent>) {
+        let previous = match port {
+            3 => std::mem::replace(&mut self.port3, value),
+            5 => std::mem::replace(&mut self.port5, value),
+            _ => return,
+        };
+
+        if self.port3 & AMPLIFIER_ENABLE == 0 {
+            return;
+        }
+
+        let rising_edges = value & !previous;
+        events.extend(SoundEvent::ALL.iter().filter(|event| {
+            let (event_port, mask) = event.port_and_mask();
+            event_port == port && rising_edges & mask != 0
+        }));
+    }
+}
+
+/**
+Mixes sound samples into a mono audio buffer, one 60 Hz frame at a time.
+
+Each [`SoundEvent`] plays its sample once from the start of the frame it was
+raised in, events without a sample are silent.
+*/
+pub struct Mixer {
+    samples: BTreeMap<SoundEvent, Vec<f32>>,
+    buffer: Vec<f32>,
+    frames: usize,
+}
+
+impl Default for Mixer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Mixer {
+    pub const SAMPLE_RATE: u32 = 44100;
+    const SAMPLES_PER_FRAME: usize = Self::SAMPLE_RATE as usize / 60;
+
+    pub fn new() -> Mixer {
+        Mixer {
+            samples: BTreeMap::new(),
+            buffer: vec![],
+            frames: 0,
+        }
+    }
+
+    /// Sets the sample played for `event`, as values between -1 and 1 at [`Mixer::SAMPLE_RATE`]
+    pub fn set_sample(&mut self, event: SoundEvent, sample: Vec<f32>) {
+        self.samples.insert(event, sample);
+    }
+
+    /**
+    Loads `<sample_name>.wav` for every event from `directory`.
+
+    Returns the events whose file does not exist.
+    */
+    pub fn load_samples(&mut self, directory: &Path) -> Result<Vec<SoundEvent>, String> {
+        let mut missing = vec![];
+        for event in SoundEvent::ALL {
+            let path = directory.join(format!("{}.wav", event.sample_name()));
+            if !path.exists() {
+                missing.push(event);
+                continue;
+            }
+            let sample = read_wav(&path).map_err(|err| format!("{}: {err}", path.display()))?;
+            self.set_sample(event, sample);
+        }
+        Ok(missing)
+    }
+
+    /// Mixes the samples of `events` at the start of the next frame
+    pub fn mix_frame(&mut self, events: &[SoundEvent]) {
+        let frame_start = self.frames * Self::SAMPLES_PER_FRAME;
+        self.frames += 1;
+
+        let frame_end = self.frames * Self::SAMPLES_PER_FRAME;
+        if self.buffer.len() < frame_end {
+            self.buffer.resize(frame_end, 0.0);
+        }
+
+        for event in events {
+            let sample = match self.samples.get(event) {
+                Some(sample) => sample,
+                None => continue,
+            };
+            if self.buffer.len() < frame_start + sample.len() {
+                self.buffer.resize(frame_start + sample.len(), 0.0);
+            }
+            for (mixed, value) in self.buffer[frame_start..].iter_mut().zip(sample) {
+                *mixed += value;
+            }
+        }
+    }
+
+    /// The mixed audio, including the tail of samples still playing after the last frame
+    pub fn buffer(&self) -> &[f32] {
+        &self.buffer
+    }
+
+    /// Writes the mixed audio as a 16 bit mono WAV file
+    pub fn write_wav(&self, path: &Path) -> Result<(), String> {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: Self::SAMPLE_RATE,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(path, spec).map_err(|err| err.to_string())?;
+        for value in &self.buffer {
+            let value = (value.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+            writer.write_sample(value).map_err(|err| err.to_string())?;
+        }
+        writer.finalize().map_err(|err| err.to_string())
+    }
+}
+
+/// Reads a WAV file as mono values between -1 and 1, resampled to [`Mixer::SAMPLE_RATE`]
+fn read_wav(path: &Path) -> Result<Vec<f32>, String> {
+    let mut reader = hound::WavReader::open(path).map_err(|err| err.to_string())?;
+    let spec = reader.spec();
+
+    let interleaved: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => reader
+            .samples::<f32>()
+            .collect::<Result<_, _>>()
+            .map_err(|err| err.to_string())?,
+        hound::SampleFormat::Int => {
+            let scale = (1i64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|value| value.map(|value| value as f32 / scale))
+                .collect::<Result<_, _>>()
+                .map_err(|err| err.to_string())?
+        }
+    };
+
+    let channels = spec.channels as usize;
+    let mono: Vec<f32> = interleaved
+        .chunks(channels)
+        .map(|channels| channels.iter().sum::<f32>() / channels.len() as f32)
+        .collect();
+
+    // Nearest neighbour resampling is plenty for these sound effects
+    let length = mono.len() as u64 * Mixer::SAMPLE_RATE as u64 / spec.sample_rate as u64;
+    Ok((0..length)
+        .map(|index| mono[(index * spec.sample_rate as u64 / Mixer::SAMPLE_RATE as u64) as usize])
+        .collect())
+}
@@ -1,5 +1,13 @@
+pub mod address;
+pub mod altair;
+pub mod assembler;
 pub mod condition;
+pub mod console;
+pub mod cpm;
 pub mod cpu;
+pub mod disassembler;
 pub mod instruction;
+pub mod invaders;
+pub mod loader;
 pub mod parser;
 pub mod register;
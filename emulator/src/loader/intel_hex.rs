@@ -0,0 +1,159 @@
+use std::fmt::Write;
+
+use super::Image;
+
+/// Data bytes written on each record
+const BYTES_PER_RECORD: usize = 16;
+
+const DATA: u8 = 0x00;
+const END_OF_FILE: u8 = 0x01;
+const EXTENDED_SEGMENT_ADDRESS: u8 = 0x02;
+const START_SEGMENT_ADDRESS: u8 = 0x03;
+const EXTENDED_LINEAR_ADDRESS: u8 = 0x04;
+const START_LINEAR_ADDRESS: u8 = 0x05;
+
+/// Decodes the bytes of a `:LLAAAATT...CC` record, checking its length and checksum
+fn record_bytes(line: &str) -> Result<Vec<u8>, String> {
+    let digits = line
+        .strip_prefix(':')
+        .ok_or("expected a record starting with `:`")?;
+    if !digits.len().is_multiple_of(2) || !digits.chars().all(|char| char.is_ascii_hexdigit()) {
+        return Err("expected pairs of hex digits after `:`".to_owned());
+    }
+    let bytes: Vec<u8> = (0..digits.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(&digits[index..index + 2], 16).unwrap())
+        .collect();
+
+    if bytes.len() < 5 || bytes.len() != bytes[0] as usize + 5 {
+        return Err("the record length does not match its byte count".to_owned());
+    }
+    let (checksum, contents) = bytes.split_last().unwrap();
+    let expected = contents
+        .iter()
+        .fold(0u8, |sum, byte| sum.wrapping_add(*byte))
+        .wrapping_neg();
+    if *checksum != expected {
+        return Err(format!(
+            "checksum is {checksum:02X}, expected {expected:02X}"
+        ));
+    }
+    Ok(contents.to_vec())
+}
+
+/**
+Parses Intel HEX records into the image they describe.
+
+Only the 64 KiB of the 8080 can be addressed: extended address records must be
+zero. The start address comes from a start segment or start linear address record.
+*/
+pub fn parse_intel_hex(text: &str) -> Result<Image, String> {
+    let mut memory: Vec<Option<u8>> = vec![None; 0x10000];
+    let mut start = None;
+    let mut ended = false;
+
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let error = |message: String| format!("Line {}: {message}", index + 1);
+
+        let record = record_bytes(line).map_err(error)?;
+        let address = u16::from_be_bytes([record[1], record[2]]) as usize;
+        let record_type = record[3];
+        let data = &record[4..];
+        let value = || {
+            data.iter()
+                .fold(0usize, |value, byte| value << 8 | *byte as usize)
+        };
+
+        match record_type {
+            DATA => {
+                if address + data.len() > memory.len() {
+                    return Err(error("data goes past address FFFF".to_owned()));
+                }
+                for (offset, byte) in data.iter().enumerate() {
+                    let slot = &mut memory[address + offset];
+                    if slot.is_some() {
+                        return Err(error(format!(
+                            "overwrites the data already loaded at {:04X}",
+                            address + offset
+                        )));
+                    }
+                    *slot = Some(*byte);
+                }
+            }
+            END_OF_FILE => {
+                ended = true;
+                break;
+            }
+            EXTENDED_SEGMENT_ADDRESS | EXTENDED_LINEAR_ADDRESS if data.len() != 2 => {
+                return Err(error("expected a 2 byte address".to_owned()));
+            }
+            EXTENDED_SEGMENT_ADDRESS | EXTENDED_LINEAR_ADDRESS if value() != 0 => {
+                return Err(error("addresses past FFFF are not supported".to_owned()));
+            }
+            EXTENDED_SEGMENT_ADDRESS | EXTENDED_LINEAR_ADDRESS => {}
+            START_SEGMENT_ADDRESS | START_LINEAR_ADDRESS if data.len() != 4 => {
+                return Err(error("expected a 4 byte start address".to_owned()));
+            }
+            START_SEGMENT_ADDRESS | START_LINEAR_ADDRESS => {
+                let address = if record_type == START_SEGMENT_ADDRESS {
+                    // Segment and offset, as in CS:IP
+                    ((value() >> 16) << 4) + (value() & 0xffff)
+                } else {
+                    value()
+                };
+                if address > u16::MAX as usize {
+                    return Err(error(format!("start address {address:X} is past FFFF")));
+                }
+                start = Some(address as u16);
+            }
+            _ => return Err(error(format!("unknown record type {record_type:02X}"))),
+        }
+    }
+    if !ended {
+        return Err("Missing the end of file record".to_owned());
+    }
+
+    Ok(Image::from_memory(&memory, start))
+}
+
+/// Appends one record with its checksum to `text`
+fn write_record(text: &mut String, address: u16, record_type: u8, data: &[u8]) {
+    let mut record = vec![data.len() as u8];
+    record.extend(address.to_be_bytes());
+    record.push(record_type);
+    record.extend(data);
+    let checksum = record
+        .iter()
+        .fold(0u8, |sum, byte| sum.wrapping_add(*byte))
+        .wrapping_neg();
+    record.push(checksum);
+
+    text.push(':');
+    for byte in record {
+        let _ = write!(text, "{byte:02X}");
+    }
+    text.push('\n');
+}
+
+/**
+Writes `image` as Intel HEX data records of up to 16 bytes, a start segment
+address record when it has a start address, and the end of file record.
+*/
+pub fn write_intel_hex(image: &Image) -> String {
+    let mut text = String::new();
+    for (index, chunk) in image.bytes.chunks(BYTES_PER_RECORD).enumerate() {
+        let address = image.origin as usize + index * BYTES_PER_RECORD;
+        write_record(&mut text, address as u16, DATA, chunk);
+    }
+    if let Some(start) = image.start {
+        let mut data = vec![0, 0];
+        data.extend(start.to_be_bytes());
+        write_record(&mut text, 0, START_SEGMENT_ADDRESS, &data);
+    }
+    write_record(&mut text, 0, END_OF_FILE, &[]);
+    text
+}
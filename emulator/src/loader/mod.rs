@@ -0,0 +1,133 @@
+mod intel_hex;
+mod srec;
+
+use std::path::Path;
+
+pub use intel_hex::{parse_intel_hex, write_intel_hex};
+pub use srec::parse_srec;
+
+/// Encodings of a program file
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Format {
+    /// The bytes of the program, as loaded in memory
+    Raw,
+    IntelHex,
+
+    /// Motorola S-records
+    Srec,
+}
+
+impl Format {
+    /**
+    Guesses the format of the file at `path` from its extension, then from its
+    first line: `:` and hex digits for Intel HEX, `S`, a digit and hex digits
+    for S-records. Anything else is raw.
+    */
+    pub fn detect(path: &Path, bytes: &[u8]) -> Format {
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_ascii_lowercase());
+        match extension.as_ref().and_then(|extension| extension.to_str()) {
+            Some("hex" | "ihx") => return Format::IntelHex,
+            Some("srec" | "s19" | "s28" | "s37" | "mot") => return Format::Srec,
+            _ => {}
+        }
+
+        let Ok(text) = std::str::from_utf8(bytes) else {
+            return Format::Raw;
+        };
+        let Some(line) = text.lines().map(str::trim).find(|line| !line.is_empty()) else {
+            return Format::Raw;
+        };
+        let is_hex = |digits: &str| {
+            !digits.is_empty() && digits.chars().all(|char| char.is_ascii_hexdigit())
+        };
+        match line.as_bytes() {
+            [b':', ..] if is_hex(&line[1..]) => Format::IntelHex,
+            [b'S', record_type, ..] if record_type.is_ascii_digit() && is_hex(&line[2..]) => {
+                Format::Srec
+            }
+            _ => Format::Raw,
+        }
+    }
+}
+
+/// A program and the address it is loaded at
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Image {
+    /// Address of the first byte of `bytes`
+    pub origin: u16,
+
+    /// Bytes from the lowest to the highest loaded address, gaps filled with zeros
+    pub bytes: Vec<u8>,
+
+    /// Address execution starts at, when the file gives one
+    pub start: Option<u16>,
+}
+
+impl Image {
+    /**
+    Reads the program in the file at `path`, in the format [`Format::detect`]
+    finds. Raw files are loaded at `raw_origin`.
+    */
+    pub fn from_file(path: &Path, raw_origin: u16) -> Result<Image, String> {
+        let bytes = std::fs::read(path)
+            .map_err(|err| format!("Could not read {}: {err}", path.display()))?;
+        let error = |err: String| format!("{}: {err}", path.display());
+
+        let format = Format::detect(path, &bytes);
+        if format == Format::Raw {
+            return Image::raw(raw_origin, bytes).map_err(error);
+        }
+        let text =
+            String::from_utf8(bytes).map_err(|_| error(format!("{format:?} files are text")))?;
+        match format {
+            Format::IntelHex => parse_intel_hex(&text),
+            _ => parse_srec(&text),
+        }
+        .map_err(error)
+    }
+
+    /// Raw `bytes` loaded at `origin`, which must fit below 64 KiB
+    pub fn raw(origin: u16, bytes: Vec<u8>) -> Result<Image, String> {
+        if origin as usize + bytes.len() > 0x10000 {
+            return Err(format!(
+                "{} bytes loaded at {origin:04X} do not fit in the 64 KiB address space",
+                bytes.len()
+            ));
+        }
+        Ok(Image {
+            origin,
+            bytes,
+            start: None,
+        })
+    }
+
+    /// The image of the bytes set in `memory`, a 64 KiB address space
+    fn from_memory(memory: &[Option<u8>], start: Option<u16>) -> Image {
+        let first = memory.iter().position(Option::is_some);
+        let last = memory.iter().rposition(Option::is_some);
+        let (origin, bytes) = match (first, last) {
+            (Some(first), Some(last)) => (
+                first as u16,
+                memory[first..=last]
+                    .iter()
+                    .map(|byte| byte.unwrap_or(0))
+                    .collect(),
+            ),
+            _ => (0, vec![]),
+        };
+        Image {
+            origin,
+            bytes,
+            start,
+        }
+    }
+
+    /// The bytes as loaded in memory from address 0, the addresses before the origin being zeros
+    pub fn memory(&self) -> Vec<u8> {
+        let mut memory = vec![0; self.origin as usize];
+        memory.extend(&self.bytes);
+        memory
+    }
+}
@@ -0,0 +1,97 @@
+use super::Image;
+
+/// Decodes the bytes after the type of an `STLLAAAA...CC` record, checking its length and checksum
+fn record_bytes(line: &str) -> Result<Vec<u8>, String> {
+    let digits = &line[2..];
+    if !digits.len().is_multiple_of(2) || !digits.chars().all(|char| char.is_ascii_hexdigit()) {
+        return Err("expected pairs of hex digits after the record type".to_owned());
+    }
+    let bytes: Vec<u8> = (0..digits.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(&digits[index..index + 2], 16).unwrap())
+        .collect();
+
+    if bytes.len() < 2 || bytes.len() != bytes[0] as usize + 1 {
+        return Err("the record length does not match its byte count".to_owned());
+    }
+    let (checksum, contents) = bytes.split_last().unwrap();
+    let expected = !contents
+        .iter()
+        .fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+    if *checksum != expected {
+        return Err(format!(
+            "checksum is {checksum:02X}, expected {expected:02X}"
+        ));
+    }
+    Ok(contents[1..].to_vec())
+}
+
+/**
+Parses Motorola S-records into the image they describe.
+
+`S1`, `S2` and `S3` data records are loaded within the 64 KiB of the 8080 and
+`S7`, `S8` or `S9` gives the start address. Header and count records are skipped.
+*/
+pub fn parse_srec(text: &str) -> Result<Image, String> {
+    let mut memory: Vec<Option<u8>> = vec![None; 0x10000];
+    let mut start = None;
+
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let error = |message: String| format!("Line {}: {message}", index + 1);
+
+        let record_type = match line.as_bytes() {
+            [b'S', record_type, ..] if record_type.is_ascii_digit() => record_type - b'0',
+            _ => {
+                return Err(error(
+                    "expected a record starting with `S` and its type".to_owned(),
+                ))
+            }
+        };
+        let record = record_bytes(line).map_err(error)?;
+        let address_length = match record_type {
+            0 | 1 | 5 | 9 => 2,
+            2 | 6 | 8 => 3,
+            3 | 7 => 4,
+            _ => return Err(error(format!("unknown record type S{record_type}"))),
+        };
+        if record.len() < address_length {
+            return Err(error("the record is too short for its address".to_owned()));
+        }
+        let (address, data) = record.split_at(address_length);
+        let address = address
+            .iter()
+            .fold(0usize, |address, byte| address << 8 | *byte as usize);
+
+        match record_type {
+            1..=3 => {
+                if address + data.len() > memory.len() {
+                    return Err(error("data goes past address FFFF".to_owned()));
+                }
+                for (offset, byte) in data.iter().enumerate() {
+                    let slot = &mut memory[address + offset];
+                    if slot.is_some() {
+                        return Err(error(format!(
+                            "overwrites the data already loaded at {:04X}",
+                            address + offset
+                        )));
+                    }
+                    *slot = Some(*byte);
+                }
+            }
+            7..=9 => {
+                if address > u16::MAX as usize {
+                    return Err(error(format!("start address {address:X} is past FFFF")));
+                }
+                start = Some(address as u16);
+                break;
+            }
+            _ => {}
+        }
+    }
+
+    Ok(Image::from_memory(&memory, start))
+}
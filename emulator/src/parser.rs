@@ -1,45 +1,92 @@
-use crate::{condition::Condition, instruction::Instruction, register::RegisterPair};
+use crate::{
+    condition::Condition,
+    instruction::{opcode_size, Instruction},
+    register::RegisterPair,
+};
+
+/// Result of parsing the next instruction of an [`InstructionParser`]
+#[derive(Debug, Clone)]
+pub enum Parsed {
+    /// A complete instruction
+    Instruction(Instruction),
+
+    /// An instruction cut off by the end of the buffer, with the bytes that are left
+    Truncated(Vec<u8>),
+}
+
+/**
+Decodes the instructions of a borrowed buffer loaded at a base address.
+
+Iterating yields the address, bytes and parse result of each instruction in turn.
 
-pub struct InstructionParser {
-    buffer: Vec<u8>,
+```
+use emulator::parser::{InstructionParser, Parsed};
+
+let mut parser = InstructionParser::new(&[0x00, 0xc3, 0x00, 0x01], 0x0100);
+let (address, bytes, parsed) = parser.nth(1).unwrap();
+assert_eq!((address, bytes), (0x0101, &[0xc3, 0x00, 0x01][..]));
+assert!(matches!(parsed, Parsed::Instruction(_)));
+```
+*/
+pub struct InstructionParser<'a> {
+    buffer: &'a [u8],
+    origin: u16,
     cursor: usize,
 }
 
-impl InstructionParser {
-    pub fn new(buffer: Vec<u8>) -> InstructionParser {
-        InstructionParser { buffer, cursor: 0 }
+impl<'a> InstructionParser<'a> {
+    pub fn new(buffer: &'a [u8], origin: u16) -> InstructionParser<'a> {
+        InstructionParser {
+            buffer,
+            origin,
+            cursor: 0,
+        }
     }
 
+    /// Offset in the buffer of the next instruction
     pub fn cursor(&self) -> usize {
         self.cursor
     }
 
-    fn consume_next(&mut self) -> Option<Vec<u8>> {
-        if self.cursor == self.buffer.len() {
-            return None;
-        }
-        let mut bytes = vec![];
-
-        let mut current_byte = *self.buffer.get(self.cursor)?;
-        self.cursor += 1;
+    /// Address of the next instruction
+    pub fn address(&self) -> u16 {
+        self.origin.wrapping_add(self.cursor as u16)
+    }
 
-        bytes.push(current_byte);
-        let number_of_bytes_to_read = Self::bytes_to_read(current_byte);
+    /// The instruction at buffer `offset`, or what is left of it at the end of the buffer
+    fn bytes_at(&self, offset: usize) -> Option<&'a [u8]> {
+        let opcode = *self.buffer.get(offset)?;
+        let end = (offset + 1 + Self::bytes_to_read(opcode)).min(self.buffer.len());
+        Some(&self.buffer[offset..end])
+    }
 
-        if self.cursor > self.buffer.len() - number_of_bytes_to_read {
-            return None;
+    fn parse_slice(bytes: &[u8]) -> Option<Parsed> {
+        if bytes.len() < Self::bytes_to_read(*bytes.first()?) + 1 {
+            return Some(Parsed::Truncated(bytes.to_vec()));
         }
+        Self::parse_bytes(bytes).map(Parsed::Instruction)
+    }
 
-        for _ in 0..number_of_bytes_to_read {
-            current_byte = *self.buffer.get(self.cursor)?;
-            bytes.push(current_byte);
-            self.cursor += 1;
-        }
-        Some(bytes)
+    /// Parses the next instruction, `None` once the whole buffer has been parsed
+    pub fn parse(&mut self) -> Option<Parsed> {
+        self.next().map(|(_, _, parsed)| parsed)
     }
 
+    /// Decodes the instruction at `address` without moving the cursor, `None` outside of the buffer
+    pub fn decode_at(&self, address: u16) -> Option<(&'a [u8], Parsed)> {
+        let offset = address.wrapping_sub(self.origin) as usize;
+        let bytes = self.bytes_at(offset)?;
+        Some((bytes, Self::parse_slice(bytes)?))
+    }
+
+    /**
+    Decodes the instruction at the start of `bytes`, ignoring the bytes after it.
+
+    Returns `None` when `bytes` is shorter than the instruction.
+    */
     pub fn parse_bytes(bytes: &[u8]) -> Option<Instruction> {
-        let opcode = bytes.get(0)?;
+        let opcode = bytes.first()?;
+        let bytes = bytes.get(..opcode_size(*opcode))?;
 
         // Trivial opcodes
         let parsed = match opcode {
@@ -58,7 +105,7 @@ impl InstructionParser {
 
             0x76 => Some(Instruction::HLT),
 
-            0xc9 => Some(Instruction::RET),
+            0xc9 | 0xd9 => Some(Instruction::RET),
 
             0xeb => Some(Instruction::XCHG),
             0xe3 => Some(Instruction::XTHL),
@@ -71,7 +118,6 @@ impl InstructionParser {
         };
 
         let parse_low_high_byte = |bytes: &[u8]| {
-            assert_eq!(bytes.len(), 3);
             let low_byte = bytes[1] as u16;
             let high_byte = bytes[2] as u16;
             (high_byte << 8) + low_byte
@@ -98,15 +144,13 @@ impl InstructionParser {
 
         // Parse MVI instruction -> 00DDD110
         if (opcode & 0xc0) == 0x00 && opcode & src_mask == 0x06 {
-            assert_eq!(bytes.len(), 2);
             let dest = dest.try_into().unwrap();
             return Some(Instruction::MVI(dest, bytes[1]));
         }
 
         // Parse LXI instruction -> 00RP0001
         if (opcode & 0xc0) == 0x00 && opcode & 0x0f == 0x01 {
-            assert_eq!(bytes.len(), 3);
-            let immediate: u16 = parse_low_high_byte(&bytes);
+            let immediate: u16 = parse_low_high_byte(bytes);
 
             let register_pair = RegisterPair::try_from(register_pair);
             if register_pair.is_err() {
@@ -117,29 +161,25 @@ impl InstructionParser {
 
         // Parse LDA instruction -> 00111010
         if *opcode == 0x3a {
-            assert_eq!(bytes.len(), 3);
-            let address: u16 = parse_low_high_byte(&bytes);
+            let address: u16 = parse_low_high_byte(bytes);
             return Some(Instruction::LDA(address));
         }
 
         // Parse STA instruction -> 00110010
         if *opcode == 0x32 {
-            assert_eq!(bytes.len(), 3);
-            let address: u16 = parse_low_high_byte(&bytes);
+            let address: u16 = parse_low_high_byte(bytes);
             return Some(Instruction::STA(address));
         }
 
         // Parse LHLD instruction -> 00101010
         if *opcode == 0x2a {
-            assert_eq!(bytes.len(), 3);
-            let address = parse_low_high_byte(&bytes);
+            let address = parse_low_high_byte(bytes);
             return Some(Instruction::LHLD(address));
         }
 
         // Parse SHLD instruction -> 00101010
         if *opcode == 0x22 {
-            assert_eq!(bytes.len(), 3);
-            let address = parse_low_high_byte(&bytes);
+            let address = parse_low_high_byte(bytes);
             return Some(Instruction::SHLD(address));
         }
 
@@ -173,7 +213,6 @@ impl InstructionParser {
 
         // Parse ADI instruction -> 11000110
         if *opcode == 0xc6 {
-            assert_eq!(bytes.len(), 2);
             let immediate = bytes[1];
             return Some(Instruction::ADI(immediate));
         }
@@ -186,7 +225,6 @@ impl InstructionParser {
 
         // Parse ACI instruction -> 11001110
         if *opcode == 0xce {
-            assert_eq!(bytes.len(), 2);
             let immediate = bytes[1];
             return Some(Instruction::ACI(immediate));
         }
@@ -199,7 +237,6 @@ impl InstructionParser {
 
         // Parse SUI instruction -> 11010110
         if *opcode == 0xd6 {
-            assert_eq!(bytes.len(), 2);
             let immediate = bytes[1];
             return Some(Instruction::SUI(immediate));
         }
@@ -212,7 +249,6 @@ impl InstructionParser {
 
         // Parse SBI instruction -> 11011110
         if *opcode == 0xde {
-            assert_eq!(bytes.len(), 2);
             let immediate = bytes[1];
             return Some(Instruction::SBI(immediate));
         }
@@ -263,7 +299,6 @@ impl InstructionParser {
 
         // Parse ANI instruction -> 11100110
         if *opcode == 0xe6 {
-            assert_eq!(bytes.len(), 2);
             let immediate = bytes[1];
             return Some(Instruction::ANI(immediate));
         }
@@ -276,7 +311,6 @@ impl InstructionParser {
 
         // Parse ORI instruction -> 11110110
         if *opcode == 0xf6 {
-            assert_eq!(bytes.len(), 2);
             let immediate = bytes[1];
             return Some(Instruction::ORI(immediate));
         }
@@ -289,7 +323,6 @@ impl InstructionParser {
 
         // Parse XRI instruction -> 11101110
         if *opcode == 0xee {
-            assert_eq!(bytes.len(), 2);
             let immediate = bytes[1];
             return Some(Instruction::XRI(immediate));
         }
@@ -302,22 +335,19 @@ impl InstructionParser {
 
         // Parse CPI instruction -> 11111110
         if *opcode == 0xfe {
-            assert_eq!(bytes.len(), 2);
             let immediate = bytes[1];
             return Some(Instruction::CPI(immediate));
         }
 
-        // Parse JMP instruction -> 11000011
-        if *opcode == 0xc3 {
-            assert_eq!(bytes.len(), 3);
-            let immediate = parse_low_high_byte(&bytes);
+        // Parse JMP instruction -> 11000011, 0xcb is undocumented
+        if *opcode == 0xc3 || *opcode == 0xcb {
+            let immediate = parse_low_high_byte(bytes);
             return Some(Instruction::JMP(immediate));
         }
 
         // Parse Jccc instruction -> 11CCC010
         if (*opcode & 0xc7) == 0xc2 {
-            assert_eq!(bytes.len(), 3);
-            let address = parse_low_high_byte(&bytes);
+            let address = parse_low_high_byte(bytes);
 
             let condition = Condition::try_from(dest);
             if condition.is_err() {
@@ -326,17 +356,15 @@ impl InstructionParser {
             return Some(Instruction::J(condition.unwrap(), address));
         }
 
-        // Parse CALL instruction -> 11001101
-        if *opcode == 0xcd {
-            assert_eq!(bytes.len(), 3);
-            let address = parse_low_high_byte(&bytes);
+        // Parse CALL instruction -> 11001101, 0xdd, 0xed and 0xfd are undocumented
+        if matches!(opcode, 0xcd | 0xdd | 0xed | 0xfd) {
+            let address = parse_low_high_byte(bytes);
             return Some(Instruction::CALL(address));
         }
 
         // Parse Cccc instruction -> 11CCC100
         if (opcode & 0xc7) == 0xc4 {
-            assert_eq!(bytes.len(), 3);
-            let address = parse_low_high_byte(&bytes);
+            let address = parse_low_high_byte(bytes);
 
             let condition = Condition::try_from(dest);
             if condition.is_err() {
@@ -347,7 +375,6 @@ impl InstructionParser {
 
         // Parse Rccc instruction -> 11CCC000
         if (opcode & 0xc7) == 0xc0 {
-            assert_eq!(bytes.len(), 1);
             let condition = Condition::try_from(dest);
             if condition.is_err() {
                 return None;
@@ -357,13 +384,11 @@ impl InstructionParser {
 
         // Parse RST instruction -> 11NNN111
         if (opcode & 0xc7) == 0xc7 {
-            assert_eq!(bytes.len(), 1);
             return Some(Instruction::RST(dest));
         }
 
         // Parse PUSH instruction -> 11RP0101
         if (opcode & 0xcf) == 0xc5 {
-            assert_eq!(bytes.len(), 1);
             let register_pair = RegisterPair::try_from(register_pair);
             if register_pair.is_err() {
                 return None;
@@ -373,7 +398,6 @@ impl InstructionParser {
 
         // Parse POP instruction -> 11RP0001
         if (opcode & 0xcf) == 0xc1 {
-            assert_eq!(bytes.len(), 1);
             let register_pair = RegisterPair::try_from(register_pair);
             if register_pair.is_err() {
                 return None;
@@ -383,14 +407,12 @@ impl InstructionParser {
 
         // Parse IN instruction -> 11011011
         if *opcode == 0xdb {
-            assert_eq!(bytes.len(), 2);
             let port = bytes[1];
             return Some(Instruction::IN(port));
         }
 
         // Parse OUT instruction -> 11010011
         if *opcode == 0xd3 {
-            assert_eq!(bytes.len(), 2);
             let port = bytes[1];
             return Some(Instruction::OUT(port));
         }
@@ -398,162 +420,19 @@ impl InstructionParser {
         Some(Instruction::Unknown)
     }
 
-    pub fn parse(&mut self) -> Option<Instruction> {
-        let bytes = self.consume_next()?;
-        Self::parse_bytes(&bytes)
+    /// Returns the number of bytes to read **after** the current byte
+    pub fn bytes_to_read(current_byte: u8) -> usize {
+        opcode_size(current_byte) - 1
     }
+}
 
-    /**
-    Returns the number of bytes to read **after** the current byte.
+impl<'a> Iterator for InstructionParser<'a> {
+    type Item = (u16, &'a [u8], Parsed);
 
-    The number is taken from the `size` column in the **opcodes.md** doc file minus 1.
-    */
-    pub fn bytes_to_read(current_byte: u8) -> usize {
-        if current_byte >= 0x40 && current_byte <= 0xbf {
-            // From 0x40 to 0xbf all instructions are 1 byte long
-            return 0;
-        }
-        match current_byte {
-            0x00 => 0, // NOP
-            0x01 => 2, // LXI B,d16
-            0x02 => 0, // STAX B
-            0x03 => 0, // INX B
-            0x04 => 0, // INR B
-            0x05 => 0, // DCR B
-            0x06 => 1, // MVI B, d8
-            0x07 => 0, // RLC
-            0x08 => 0, // NOP
-            0x09 => 0, // DAD B
-            0x0a => 0, // LDAX B
-            0x0b => 0, // DCX B
-            0x0c => 0, // INR C
-            0x0d => 0, // DCR C
-            0x0e => 1, // MVI C,d8
-            0x0f => 0, // RRC
-
-            0x10 => 0, // NOP
-            0x11 => 2, // LXI D,d16
-            0x12 => 0, // STAX D
-            0x13 => 0, // INX D
-            0x14 => 0, // INR D
-            0x15 => 0, // DCR D
-            0x16 => 1, // MVI D,d8
-            0x17 => 0, // RAL
-            0x18 => 0, // NOP
-            0x19 => 0, // DAD D
-            0x1a => 0, // LDAX D
-            0x1b => 0, // DCX D
-            0x1c => 0, // INR E
-            0x1d => 0, // DCR E
-            0x1e => 1, // MVI E, d8
-            0x1f => 0, // RAR
-
-            0x20 => 0, // NOP
-            0x21 => 2, // LXI H,d16
-            0x22 => 2, // SHLD a16
-            0x23 => 0, // INX H
-            0x24 => 0, // INR H
-            0x25 => 0, // DCR h
-            0x26 => 1, // MVI H,d8
-            0x27 => 0, // DAA
-            0x28 => 0, // NOP
-            0x29 => 0, // DAD H
-            0x2a => 2, // LHLD a16
-            0x2b => 0, // DCX H
-            0x2c => 0, // INR L
-            0x2d => 0, // DCR L
-            0x2e => 1, // MVI A,d8
-            0x2f => 0, // CMA
-
-            0x30 => 0, // NOP
-            0x31 => 2, // LXI SP,d16
-            0x32 => 2, // STA a16
-            0x33 => 0, // INX SP
-            0x34 => 0, // INR M
-            0x35 => 0, // DCR M
-            0x36 => 1, // MVI M,d8
-            0x37 => 0, // STC
-            0x38 => 0, // NOP
-            0x39 => 0, // DAD SP
-            0x3a => 2, // LDA a16
-            0x3b => 0, // DCX SP
-            0x3c => 0, // INR A
-            0x3d => 0, // DCR A
-            0x3e => 1, // MVI A,d8
-            0x3f => 0, // CMC
-
-            /*
-            0x40 .. 0xbf => 0
-            */
-            0xc0 => 0, // RNZ
-            0xc1 => 0, // POP B
-            0xc2 => 2, // JNZ a16
-            0xc3 => 2, // JMP a16
-            0xc4 => 2, // CNZ a16
-            0xc5 => 0, // PUSH B
-            0xc6 => 1, // ADI d8
-            0xc7 => 0, // RST 0
-            0xc8 => 0, // RZ
-            0xc9 => 0, // RET
-            0xca => 2, // JZ a16
-            0xcb => 2, // JMP a16
-            0xcc => 2, // CZ a16
-            0xcd => 2, // CALL a16
-            0xce => 1, // ACI d8
-            0xcf => 1, // RST 1
-
-            0xd0 => 0, // RNC
-            0xd1 => 0, // POP D
-            0xd2 => 2, // JNC a16
-            0xd3 => 1, // OUT d8
-            0xd4 => 2, // CNC a16
-            0xd5 => 0, // PUSH D
-            0xd6 => 1, // SUI d8
-            0xd7 => 0, // RST 2
-            0xd8 => 0, // RC
-            0xd9 => 0, // RET
-            0xda => 2, // JC a16
-            0xdb => 1, // IN d8
-            0xdc => 2, // CC a16
-            0xdd => 2, // CALL a16
-            0xde => 1, // SBI d8
-            0xdf => 0, // RST 3
-
-            0xe0 => 0, // RPO
-            0xe1 => 0, // POP H
-            0xe2 => 2, // JPO a16
-            0xe3 => 0, // XTHL
-            0xe4 => 2, // CPO a16
-            0xe5 => 0, // PUSH H
-            0xe6 => 1, // ANI d8
-            0xe7 => 0, // RST 4
-            0xe8 => 0, // RPE
-            0xe9 => 0, // PCHL
-            0xea => 2, // JPE a16
-            0xeb => 0, // XCHG
-            0xec => 2, // CPE a16
-            0xed => 2, // CALL a16
-            0xee => 1, // XRI d8
-            0xef => 0, // RST 5
-
-            0xf0 => 0, // RP
-            0xf1 => 0, // POP PSW
-            0xf2 => 2, // JP a16
-            0xf3 => 0, // DI
-            0xf4 => 2, // CP a16
-            0xf5 => 0, // PUSH PSW
-            0xf6 => 1, // ORI d8
-            0xf7 => 0, // RST 6
-            0xf8 => 0, // RM
-            0xf9 => 0, // SPHL
-            0xfa => 2, // JM a16
-            0xfb => 0, // EI
-            0xfc => 2, // CM a16
-            0xfd => 2, // CALL a16
-            0xfe => 1, // CPI d8
-            0xff => 0, // RST 7
-
-            _ => todo!("Determine 0x{:02x} size", current_byte),
-        }
+    fn next(&mut self) -> Option<Self::Item> {
+        let address = self.address();
+        let bytes = self.bytes_at(self.cursor)?;
+        self.cursor += bytes.len();
+        Some((address, bytes, Self::parse_slice(bytes)?))
     }
 }
@@ -1,6 +1,6 @@
-use std::fmt::Display;
+use std::{fmt::Display, str::FromStr};
 
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
 pub enum Register {
     A,
     B,
@@ -12,7 +12,7 @@ pub enum Register {
     M,
 }
 
-#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
 pub enum RegisterPair {
     /// B:C as 16 bit register
     BC,
@@ -39,6 +39,21 @@ impl Display for RegisterPair {
     }
 }
 
+/// Accepts the names printed by `Display`, the full pair names and `PSW` for [`RegisterPair::SP`]
+impl FromStr for RegisterPair {
+    type Err = &'static str;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text.to_ascii_uppercase().as_str() {
+            "B" | "BC" => Ok(RegisterPair::BC),
+            "D" | "DE" => Ok(RegisterPair::DE),
+            "H" | "HL" => Ok(RegisterPair::HL),
+            "SP" | "PSW" => Ok(RegisterPair::SP),
+            _ => Err("Invalid register pair name"),
+        }
+    }
+}
+
 impl TryFrom<u8> for RegisterPair {
     type Error = &'static str;
 
@@ -68,6 +83,24 @@ impl Display for Register {
     }
 }
 
+impl FromStr for Register {
+    type Err = &'static str;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text.to_ascii_uppercase().as_str() {
+            "A" => Ok(Register::A),
+            "B" => Ok(Register::B),
+            "C" => Ok(Register::C),
+            "D" => Ok(Register::D),
+            "E" => Ok(Register::E),
+            "H" => Ok(Register::H),
+            "L" => Ok(Register::L),
+            "M" => Ok(Register::M),
+            _ => Err("Invalid register name"),
+        }
+    }
+}
+
 impl TryFrom<u8> for Register {
     fn try_from(value: u8) -> Result<Register, &'static str> {
         match value {
@@ -85,3 +118,29 @@ impl TryFrom<u8> for Register {
 
     type Error = &'static str;
 }
+
+impl From<Register> for u8 {
+    fn from(register: Register) -> u8 {
+        match register {
+            Register::B => 0,
+            Register::C => 1,
+            Register::D => 2,
+            Register::E => 3,
+            Register::H => 4,
+            Register::L => 5,
+            Register::M => 6,
+            Register::A => 7,
+        }
+    }
+}
+
+impl From<RegisterPair> for u8 {
+    fn from(pair: RegisterPair) -> u8 {
+        match pair {
+            RegisterPair::BC => 0,
+            RegisterPair::DE => 1,
+            RegisterPair::HL => 2,
+            RegisterPair::SP => 3,
+        }
+    }
+}
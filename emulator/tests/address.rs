@@ -0,0 +1,55 @@
+use emulator::address::{address_range, parse_address};
+
+#[test]
+fn addresses_are_hexadecimal() {
+    assert_eq!(parse_address("1a2b"), Ok(0x1a2b));
+    assert_eq!(parse_address("0x100"), Ok(0x100));
+    assert_eq!(parse_address("$ffff"), Ok(0xffff));
+    assert!(parse_address("10000").is_err());
+    assert!(parse_address("0x").is_err());
+    assert!(parse_address("12g").is_err());
+}
+
+#[test]
+fn address_ranges_stay_within_the_input() {
+    let program = [0, 1, 2, 3];
+    assert_eq!(
+        address_range(&program, 0x100, None, None),
+        Ok((&program[..], 0x100))
+    );
+    assert_eq!(
+        address_range(&program, 0x100, Some(0x101), Some(0x102)),
+        Ok((&program[1..3], 0x101))
+    );
+    assert_eq!(
+        address_range(&program, 0x100, Some(0x103), None),
+        Ok((&program[3..], 0x103))
+    );
+
+    let error = |start, end| address_range(&program, 0x100, start, end).unwrap_err();
+    // Start before the origin
+    assert_eq!(
+        error(Some(0xff), None),
+        "Range 00ff-0103 is not within the input, loaded at 0100-0103"
+    );
+    // End past the last byte
+    assert_eq!(
+        error(None, Some(0x104)),
+        "Range 0100-0104 is not within the input, loaded at 0100-0103"
+    );
+    // Start after the end
+    assert_eq!(
+        error(Some(0x102), Some(0x101)),
+        "Range 0102-0101 is not within the input, loaded at 0100-0103"
+    );
+
+    assert_eq!(
+        address_range(&[], 0x100, None, None),
+        Err("The input is empty".to_owned())
+    );
+    assert_eq!(
+        address_range(&program, 0xfffd, None, None),
+        Err("4 bytes loaded at fffd do not fit in the address space".to_owned())
+    );
+    assert!(address_range(&program, 0xfffc, None, None).is_ok());
+}
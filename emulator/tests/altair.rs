@@ -0,0 +1,30 @@
+use emulator::{altair::Altair, asm8080, console::BufferConsole};
+
+#[test]
+fn programs_talk_through_the_serial_boards() {
+    let program = asm8080! {
+        LXI SP, 0x1000;
+        IN 0xff; STA 0x100;
+        MVI A, 0x55; STA 0x2000; STA 0x3000;
+        LDA 0x2000; STA 0x101; LDA 0x3000; STA 0x102;
+        // The 88-SIO status bit is low once a character is received
+        sio: IN 0x00; RRC; JC sio;
+        IN 0x01; OUT 0x01; CPI 0x0d; JNZ sio;
+        echo: IN 0x10; RRC; JNC echo;
+        IN 0x11; ORI 0x80; OUT 0x11;
+        JMP echo
+    };
+
+    let mut altair = Altair::new(4, BufferConsole::new(b"hi\rok\r")).unwrap();
+    altair.load(0, &program).unwrap();
+    altair.load(0x3000, &[0xc9]).unwrap();
+    altair.set_sense_switches(0xa5);
+    altair.run();
+
+    // The machine stops once it waits for input that never comes
+    assert_eq!(altair.console().output, b"hi\rok\r");
+    // Past the 4 KiB of RAM there is nothing to write to but the ROM, which keeps its byte
+    assert_eq!(&altair.cpu().memory()[0x100..0x103], &[0xa5, 0xff, 0xc9]);
+
+    assert!(Altair::new(0, BufferConsole::default()).is_err());
+}
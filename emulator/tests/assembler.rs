@@ -0,0 +1,136 @@
+use emulator::{
+    asm8080,
+    assembler::{assemble, assemble_file},
+    cpu::CPU,
+    disassembler::{follow_control_flow, reassemblable_source, SymbolTable},
+};
+
+const ROM: &[u8] = include_bytes!("../../roms/invaders.concatenated");
+
+#[test]
+fn reassembles_the_disassembled_rom() {
+    let entry_points: Vec<u16> = (0..8).map(|vector| vector * 8).collect();
+    let items = follow_control_flow(ROM, 0, &entry_points);
+    let mut symbols = SymbolTable::default();
+    symbols.add_branch_labels(&items);
+    let source = reassemblable_source(&items, 0, &symbols).join("\n");
+
+    let assembly = assemble(&source).unwrap();
+    assert_eq!(assembly.origin, 0);
+    assert!(assembly.bytes == ROM, "the assembled ROM differs");
+}
+
+#[test]
+fn assembled_program_runs() {
+    let source = "
+COUNT   EQU 10
+        ORG 0
+        LXI H,table
+        MVI B,COUNT
+        XRA A
+loop:   ADD M           ; sum the table
+        INX H
+        DCR B
+        JNZ loop
+        STA result
+        HLT
+table:  DB 1, 2, 3, 4, 5, 6, 7, 8, 9, 10
+result: DS 1
+        END
+";
+    let assembly = assemble(source).unwrap();
+    let mut cpu = CPU::new();
+    cpu.load_program(&assembly.bytes);
+    while !cpu.is_halted() {
+        cpu.fetch_decode_execute();
+    }
+    let result = assembly.bytes.len();
+    assert_eq!(cpu.memory()[result], 55);
+}
+
+#[test]
+fn errors_have_a_line_and_column() {
+    let error = assemble("start:  NOP\n        MVI A,missing\n").unwrap_err();
+    assert_eq!((error.line, error.column), (2, 15));
+    assert_eq!(error.message, "`missing` is not defined");
+
+    let error = assemble("        MOV A,Q\n").unwrap_err();
+    assert_eq!((error.line, error.column), (1, 15));
+
+    let error = assemble("        MOV M,M\n").unwrap_err();
+    assert_eq!((error.line, error.column), (1, 15));
+    assert_eq!(error.message, "MOV M,M is not an instruction");
+}
+
+#[test]
+fn macros_and_conditional_blocks_expand() {
+    let source = "
+DELAY   MACRO reg, count
+        LOCAL again
+        MVI reg, count
+again:  DCR reg
+        JNZ again
+        ENDM
+FAST    EQU 1
+        DELAY B, 3
+        DELAY C, 2
+        REPT 2
+        NOP
+        ENDM
+        IF FAST
+        HLT
+        ELSE
+        DI
+        ENDIF
+";
+    let expected = assemble(
+        "
+        MVI B, 3
+first:  DCR B
+        JNZ first
+        MVI C, 2
+second: DCR C
+        JNZ second
+        NOP
+        NOP
+        HLT
+",
+    )
+    .unwrap();
+    assert_eq!(assemble(source).unwrap().bytes, expected.bytes);
+}
+
+#[test]
+fn included_files_are_relative_to_the_including_file() {
+    let directory =
+        std::env::temp_dir().join(format!("eightyeighty-{}-include", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    std::fs::write(directory.join("constants.inc"), "VALUE   EQU 42\n").unwrap();
+    std::fs::write(
+        directory.join("main.asm"),
+        "        INCLUDE 'constants.inc'\n        MVI A,VALUE\n        MVI A,missing\n",
+    )
+    .unwrap();
+
+    let error = assemble_file(&directory.join("main.asm")).unwrap_err();
+    assert_eq!(error.file, Some(directory.join("main.asm")));
+    assert_eq!((error.line, error.column), (3, 15));
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn inline_programs_assemble() {
+    let program = asm8080! {
+        LXI H, message; MVI C, 0;
+        count: MOV A, M; CPI '$'; JZ done;
+        INR C; INX H; JMP count;
+        done: MOV A, C; STA 0x200; HLT;
+        message: DB "Hello; world", '$'
+    };
+    let mut cpu = CPU::new();
+    cpu.load_program(&program);
+    while !cpu.is_halted() {
+        cpu.fetch_decode_execute();
+    }
+    assert_eq!(cpu.memory()[0x200], 12);
+}
@@ -0,0 +1,148 @@
+use emulator::{
+    asm8080,
+    console::BufferConsole,
+    cpm::{Cpm, CpmSystem, Disk, DISK_SIZE},
+    register::RegisterPair,
+};
+
+#[test]
+fn programs_use_the_console_and_host_files() {
+    let program = asm8080! {
+        ORG 0x100;
+        MVI C, 9; LXI D, prompt; CALL 5;
+        MVI A, 10; STA 0x300;
+        MVI C, 10; LXI D, 0x300; CALL 5;
+
+        MVI C, 22; LXI D, 0x5c; CALL 5;
+        LXI H, text; LXI D, 0x80; MVI B, 5;
+        copy: MOV A, M; STAX D; INX H; INX D; DCR B; JNZ copy;
+        MVI C, 21; LXI D, 0x5c; CALL 5;
+        MVI C, 16; LXI D, 0x5c; CALL 5;
+
+        MVI C, 15; LXI D, 0x5c; CALL 5;
+        XRA A; STA 0x7c;
+        MVI C, 26; LXI D, 0x200; CALL 5;
+        MVI C, 20; LXI D, 0x5c; CALL 5;
+        MVI A, '$'; STA 0x205;
+        MVI C, 9; LXI D, 0x200; CALL 5;
+        RET;
+
+        prompt: DB "Name? $";
+        text: DB "Saved"
+    };
+
+    let directory = std::env::temp_dir().join(format!("eightyeighty-{}-cpm", std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+    std::fs::create_dir_all(&directory).unwrap();
+
+    let console = BufferConsole::new(b"ab\x08c\r");
+    let mut cpm = Cpm::new(&program, &["out.txt".to_owned()], &directory, console).unwrap();
+    cpm.run();
+
+    // Returning from the program warm boots through the BIOS jump table
+    assert_eq!(cpm.cpu().program_counter(), 0xff03);
+    assert_eq!(cpm.console().output, b"Name? ab\x08 \x08c\rSaved");
+    assert_eq!(&cpm.cpu().memory()[0x301..0x304], b"\x02ac");
+
+    let file = std::fs::read(directory.join("OUT.TXT")).unwrap();
+    assert_eq!(file.len(), 128);
+    assert!(file.starts_with(b"Saved"));
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn programs_end_below_the_stack() {
+    let directory = std::env::temp_dir();
+    let mut program = vec![0; 0xfdfe - 0x100];
+    *program.last_mut().unwrap() = 0x76;
+
+    let mut cpm = Cpm::new(&program, &[], &directory, BufferConsole::default()).unwrap();
+    assert_eq!(cpm.cpu().read_register_pair(RegisterPair::SP), 0xfdfe);
+    assert_eq!(&cpm.cpu().memory()[0xfdfd..0xfe00], &[0x76, 0, 0]);
+    cpm.run();
+    assert_eq!(cpm.cpu().program_counter(), 0xfdfe);
+
+    program.push(0);
+    let error = Cpm::new(&program, &[], &directory, BufferConsole::default()).err();
+    assert_eq!(
+        error.as_deref(),
+        Some("The program is 64767 bytes, the transient program area holds 64766")
+    );
+}
+
+#[test]
+fn made_files_must_not_exist() {
+    let program = asm8080! {
+        ORG 0x100;
+        MVI C, 22; LXI D, 0x5c; CALL 5; STA 0x200;
+        MVI C, 15; LXI D, 0x5c; CALL 5;
+        MVI C, 20; LXI D, 0x5c; CALL 5;
+        MVI C, 19; LXI D, 0x5c; CALL 5;
+        MVI C, 22; LXI D, 0x5c; CALL 5; STA 0x201;
+        MVI C, 21; LXI D, 0x5c; CALL 5; STA 0x202;
+        // The renamed file is no longer reachable through its old name
+        MVI C, 23; LXI D, 0x5c; CALL 5; STA 0x203;
+        MVI C, 21; LXI D, 0x5c; CALL 5; STA 0x204;
+        RET
+    };
+
+    let directory = std::env::temp_dir().join(format!("eightyeighty-{}-make", std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+    std::fs::create_dir_all(&directory).unwrap();
+    std::fs::write(directory.join("OUT.TXT"), b"old").unwrap();
+
+    let arguments = ["out.txt".to_owned(), "new.txt".to_owned()];
+    let mut cpm = Cpm::new(&program, &arguments, &directory, BufferConsole::default()).unwrap();
+    cpm.run();
+
+    let memory = cpm.cpu().memory();
+    assert_eq!(&memory[0x200..0x205], &[0xff, 0, 0, 0, 0xff]);
+    // Read before the file was deleted, since making it failed
+    assert_eq!(&memory[0x80..0x84], b"old\x1a");
+    assert!(!directory.join("OUT.TXT").exists());
+    assert_eq!(std::fs::read(directory.join("NEW.TXT")).unwrap().len(), 128);
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn systems_boot_from_disk_images_through_the_bios() {
+    // Stands in for a CCP generated for 64 KiB, calling the BIOS at FA00
+    let ccp = asm8080! {
+        ORG 0xe400; JMP start;
+        ORG 0xe75c;
+        start: MVI C, 'O'; CALL 0xfa0c; MVI C, 'K'; CALL 0xfa0c;
+        MVI C, 1; CALL 0xfa1b; SHLD 0x200;
+        LXI B, 2; CALL 0xfa1e; LXI B, 1; CALL 0xfa21; LXI B, 0x80; CALL 0xfa24;
+        MVI A, 0x42; STA 0x80;
+        CALL 0xfa2a; STA 0x202;
+        HLT
+    };
+    let mut image = vec![0xe5; DISK_SIZE];
+    image[128..128 + ccp.len()].copy_from_slice(&ccp);
+
+    let path =
+        std::env::temp_dir().join(format!("eightyeighty-{}-drive-b.img", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let disks = vec![Disk::from_bytes(image).unwrap(), Disk::open(&path).unwrap()];
+    let mut system = CpmSystem::new(disks, BufferConsole::default()).unwrap();
+    system.run();
+
+    assert_eq!(system.console().output, b"OK");
+    let memory = system.cpu().memory();
+    assert_eq!(&memory[0..6], &[0xc3, 0x03, 0xfa, 0x00, 0x00, 0xc3]);
+    assert_eq!(&memory[6..8], &[0x06, 0xec]);
+    // The disk parameter header of drive B, and the result of the write
+    assert_eq!(&memory[0x200..0x203], &[0x90, 0xfa, 0x00]);
+
+    system.save().unwrap();
+    let saved = std::fs::read(&path).unwrap();
+    assert_eq!(saved.len(), DISK_SIZE);
+    assert_eq!(saved[2 * 26 * 128], 0x42);
+    std::fs::remove_file(&path).unwrap();
+
+    let error = CpmSystem::new(vec![Disk::blank()], BufferConsole::default()).err();
+    assert_eq!(
+        error.as_deref(),
+        Some("The system tracks of drive A do not hold a CP/M 2.2 CCP")
+    );
+}
@@ -0,0 +1,138 @@
+use emulator::cpu::{NullBus, CPU};
+
+// Flag bits as pushed by PUSH PSW
+const S: u8 = 0x80;
+const Z: u8 = 0x40;
+const AC: u8 = 0x10;
+const P: u8 = 0x04;
+const CY: u8 = 0x01;
+const ALWAYS_SET: u8 = 0x02;
+
+/// Stack pointer set by `with_psw`, PUSH PSW storing A at 0x1fff and the flags at 0x1ffe
+const STACK: [u8; 3] = [0x31, 0x00, 0x20];
+const PUSH_PSW_HLT: [u8; 2] = [0xf5, 0x76];
+
+fn run(program: &[u8]) -> CPU {
+    let mut cpu = CPU::new();
+    cpu.load_program(program);
+    for _ in 0..1000 {
+        if cpu.is_halted() {
+            return cpu;
+        }
+        cpu.fetch_decode_execute();
+    }
+    panic!("The program did not halt");
+}
+
+/// Runs `code` after setting up the stack, returning A and the flags it leaves
+fn with_psw(code: &[u8]) -> (u8, u8) {
+    let program = [&STACK[..], code, &PUSH_PSW_HLT].concat();
+    let cpu = run(&program);
+    (cpu.memory()[0x1fff], cpu.memory()[0x1ffe])
+}
+
+#[test]
+fn arithmetic_and_logic_set_the_flags() {
+    // MVI A, 7FH; MVI B, 1; ADD B
+    assert_eq!(
+        with_psw(&[0x3e, 0x7f, 0x06, 0x01, 0x80]),
+        (0x80, S | AC | ALWAYS_SET)
+    );
+    // MVI A, 0FFH; ADI 1
+    assert_eq!(
+        with_psw(&[0x3e, 0xff, 0xc6, 0x01]),
+        (0x00, Z | AC | P | CY | ALWAYS_SET)
+    );
+    // MVI A, 5; CPI 5
+    assert_eq!(
+        with_psw(&[0x3e, 0x05, 0xfe, 0x05]),
+        (0x05, Z | AC | P | ALWAYS_SET)
+    );
+    // MVI A, 0; SUI 1, borrowing
+    assert_eq!(
+        with_psw(&[0x3e, 0x00, 0xd6, 0x01]),
+        (0xff, S | P | CY | ALWAYS_SET)
+    );
+    // STC; MVI A, 0F0H; ORI 0FH clears the carry
+    assert_eq!(
+        with_psw(&[0x37, 0x3e, 0xf0, 0xf6, 0x0f]),
+        (0xff, S | P | ALWAYS_SET)
+    );
+    // MVI A, 80H; RLC
+    assert_eq!(with_psw(&[0x3e, 0x80, 0x07]), (0x01, CY | ALWAYS_SET));
+}
+
+#[test]
+fn daa_adjusts_bcd_sums() {
+    // MVI A, 9; ADI 1; DAA
+    let (a, flags) = with_psw(&[0x3e, 0x09, 0xc6, 0x01, 0x27]);
+    assert_eq!(a, 0x10);
+    assert_eq!(flags & CY, 0);
+
+    // MVI A, 99H; ADI 1; DAA
+    let (a, flags) = with_psw(&[0x3e, 0x99, 0xc6, 0x01, 0x27]);
+    assert_eq!(a, 0x00);
+    assert_eq!(flags & (Z | CY), Z | CY);
+
+    // MVI A, 38H; ADI 45H; DAA
+    assert_eq!(with_psw(&[0x3e, 0x38, 0xc6, 0x45, 0x27]).0, 0x83);
+}
+
+#[test]
+fn pop_psw_keeps_only_the_real_flag_bits() {
+    // LXI SP, 2000H; LXI H, 0FFFFH; PUSH H; POP PSW; PUSH PSW; HLT
+    let cpu = run(&[0x31, 0x00, 0x20, 0x21, 0xff, 0xff, 0xe5, 0xf1, 0xf5, 0x76]);
+    assert_eq!(cpu.memory()[0x1fff], 0xff);
+    assert_eq!(cpu.memory()[0x1ffe], S | Z | AC | P | CY | ALWAYS_SET);
+}
+
+#[test]
+fn interrupts_call_the_rst_vector_when_enabled() {
+    let mut program = vec![0; 0x10];
+    // 0000: LXI SP, 2000H; EI; HLT
+    program[..5].copy_from_slice(&[0x31, 0x00, 0x20, 0xfb, 0x76]);
+    // 0008: MVI A, 42H; STA 0100H; HLT
+    program[0x08..0x0e].copy_from_slice(&[0x3e, 0x42, 0x32, 0x00, 0x01, 0x76]);
+
+    let mut cpu = CPU::new();
+    cpu.load_program(&program);
+    assert!(!cpu.interrupt(1), "interrupts start disabled");
+
+    while !cpu.is_halted() {
+        cpu.fetch_decode_execute();
+    }
+    assert_eq!(cpu.program_counter(), 0x0005);
+    assert!(cpu.interrupt(1));
+    assert!(!cpu.is_halted());
+    assert_eq!(cpu.program_counter(), 0x0008);
+    // The return address after the HLT is on the stack
+    assert_eq!(&cpu.memory()[0x1ffe..0x2000], &[0x05, 0x00]);
+    assert!(!cpu.interrupt(2), "accepting an interrupt disables them");
+
+    while !cpu.is_halted() {
+        cpu.fetch_decode_execute();
+    }
+    assert_eq!(cpu.memory()[0x100], 0x42);
+}
+
+#[test]
+fn instructions_take_their_documented_cycles() {
+    let program = [
+        0x00, // NOP
+        0x3e, 0x01, // MVI A, 1
+        0x31, 0x00, 0x20, // LXI SP, 2000H
+        0xb7, // ORA A, clearing Z
+        0xcc, 0x00, 0x10, // CZ 1000H, not taken
+        0xc4, 0x0f, 0x00, // CNZ 000FH, taken
+        0x76, // HLT
+        0x00, // padding
+        0xc8, // 000F: RZ, not taken
+        0xc9, // RET
+    ];
+    let mut cpu = CPU::new();
+    cpu.load_program(&program);
+
+    let cycles: Vec<usize> =
+        std::iter::from_fn(|| (!cpu.is_halted()).then(|| cpu.step(&mut NullBus))).collect();
+    assert_eq!(cycles, [4, 7, 10, 4, 11, 17, 5, 10, 7]);
+}
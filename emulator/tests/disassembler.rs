@@ -0,0 +1,252 @@
+use emulator::{
+    asm8080,
+    disassembler::{
+        call_graph_dot, follow_control_flow, json_listing, linear_sweep, CrossReferences, Item,
+        Reference, ReferenceKind, Symbol, SymbolTable,
+    },
+};
+use serde_json::{json, Value};
+
+fn program() -> Vec<u8> {
+    asm8080! {
+        ORG 0x100;
+        start: LXI H, 0x200; CALL first; STA 0x201; JMP start;
+        first: LDA 0x200; CALL second; RET;
+        second: RET;
+        // Only reached through an entry point
+        third: CALL second; RET
+    }
+}
+
+#[test]
+fn cross_references_have_a_kind() {
+    let items = linear_sweep(&program(), 0x100);
+    let references = CrossReferences::new(&items);
+
+    let reference = |from, kind| Reference { from, kind };
+    assert_eq!(
+        references.get(0x200),
+        [
+            reference(0x100, ReferenceKind::Constant),
+            reference(0x10c, ReferenceKind::Read)
+        ]
+    );
+    assert_eq!(
+        references.get(0x201),
+        [reference(0x106, ReferenceKind::Write)]
+    );
+    assert_eq!(
+        references.get(0x100),
+        [reference(0x109, ReferenceKind::Jump)]
+    );
+    assert_eq!(
+        references.get(0x113),
+        [
+            reference(0x10f, ReferenceKind::Call),
+            reference(0x114, ReferenceKind::Call)
+        ]
+    );
+    assert!(references.get(0x112).is_empty());
+    assert_eq!(
+        references
+            .iter()
+            .map(|(address, _)| address)
+            .collect::<Vec<_>>(),
+        [0x100, 0x10c, 0x113, 0x200, 0x201]
+    );
+}
+
+#[test]
+fn call_graph_starts_at_the_entry_points() {
+    let items = linear_sweep(&program(), 0x100);
+    let symbols = SymbolTable::default();
+
+    assert_eq!(
+        call_graph_dot(&items, &[0x100], &symbols),
+        "digraph calls {
+    node [shape=box];
+    n0100 [label=\"0100\"];
+    n010c [label=\"010c\"];
+    n0113 [label=\"0113\"];
+    n0100 -> n010c;
+    n010c -> n0113;
+}
+"
+    );
+
+    let dot = call_graph_dot(&items, &[0x100, 0x114], &symbols);
+    assert!(dot.contains("n0114 [label=\"0114\"];"), "{dot}");
+    assert!(dot.contains("n0114 -> n0113;"), "{dot}");
+}
+
+#[test]
+fn json_listing_describes_code_and_data() {
+    let program = asm8080! {
+        ORG 0x100;
+        start: MOV A, B; LXI H, 0x1234; PUSH PSW; JNZ start;
+        // Cut off by the end of the program
+        DB 0xc3
+    };
+    let items = linear_sweep(&program, 0x100);
+    let mut symbols = SymbolTable::default();
+    symbols.insert(
+        0x100,
+        Symbol {
+            name: "start".to_owned(),
+            comment: None,
+        },
+    );
+
+    let listing: Value = json_listing(&items, &symbols).join("\n").parse().unwrap();
+    let listing = listing.as_array().unwrap();
+    assert_eq!(listing.len(), 5);
+
+    let operands = |index: usize| listing[index]["operands"].clone();
+    assert_eq!(
+        listing[0],
+        json!({
+            "address": 0x100,
+            "bytes": [0x78],
+            "kind": "code",
+            "label": "start",
+            "length": 1,
+            "mnemonic": "MOV",
+            "operands": [
+                {"type": "register", "value": "A"},
+                {"type": "register", "value": "B"}
+            ]
+        })
+    );
+    assert_eq!(
+        operands(1),
+        json!([
+            {"type": "register_pair", "value": "H"},
+            {"type": "immediate", "value": 0x1234}
+        ])
+    );
+    assert_eq!(
+        operands(2),
+        json!([{"type": "register_pair", "value": "PSW"}])
+    );
+    assert_eq!(
+        operands(3),
+        json!([
+            {"type": "condition", "value": "NZ"},
+            {"type": "address", "value": 0x100}
+        ])
+    );
+    assert_eq!(
+        listing[4],
+        json!({
+            "address": 0x108,
+            "bytes": [0xc3],
+            "kind": "data",
+            "label": null,
+            "length": 1,
+            "mnemonic": "DB",
+            "operands": []
+        })
+    );
+}
+
+#[test]
+fn symbol_files_name_addresses() {
+    let symbols: SymbolTable = "
+        # Space Invaders
+        ; Routines
+        18d4=init ; Entry point after reset
+        0x20e9 = suspend_play
+        $0100=_Start2
+    "
+    .parse()
+    .unwrap();
+
+    let symbol = |name: &str, comment: Option<&str>| Symbol {
+        name: name.to_owned(),
+        comment: comment.map(str::to_owned),
+    };
+    assert_eq!(
+        symbols.iter().collect::<Vec<_>>(),
+        [
+            (0x0100, &symbol("_Start2", None)),
+            (0x18d4, &symbol("init", Some("Entry point after reset"))),
+            (0x20e9, &symbol("suspend_play", None)),
+        ]
+    );
+}
+
+#[test]
+fn bad_symbol_lines_are_rejected() {
+    let error = |symbols: &str| symbols.parse::<SymbolTable>().unwrap_err();
+
+    assert_eq!(error("0100 init"), "Line 1: expected `address=name`");
+    let bad_address = error("18d4=init\n12g4=loop");
+    assert!(
+        bad_address.starts_with("Line 2: Invalid address 12g4"),
+        "{bad_address}"
+    );
+    assert_eq!(error("0100=2nd"), "Line 1: invalid symbol name `2nd`");
+    assert_eq!(
+        error("0100=with space"),
+        "Line 1: invalid symbol name `with space`"
+    );
+    for reserved in [
+        "B", "m", "SP", "PSW", "MOV", "jnz", "ORG", "ENDIF", "MOD", "LOW",
+    ] {
+        assert_eq!(
+            error(&format!("0100={reserved}")),
+            format!("Line 1: `{reserved}` is reserved by the assembler")
+        );
+    }
+    assert_eq!(
+        error("0100=start\n0100=main"),
+        "Line 2: address 0100 already has a symbol"
+    );
+    assert_eq!(
+        error("0100=start\n0200=start"),
+        "Line 2: `start` is already defined"
+    );
+}
+
+#[test]
+fn following_the_control_flow_leaves_unreached_bytes_as_data() {
+    let program = asm8080! {
+        ORG 0x100;
+        MVI A, 1; JZ skip; CALL routine; JMP done;
+        DB 0xff, 0xff;
+        skip: NOP; RET;
+        DB 0x12;
+        routine: RET;
+        done: HLT;
+        // MVI, were it reached
+        DB 0x3e
+    };
+    let items = follow_control_flow(&program, 0x100, &[0x100]);
+
+    let summary: Vec<(u16, bool, &[u8])> = items
+        .iter()
+        .map(|item| {
+            (
+                item.address(),
+                matches!(item, Item::Code { .. }),
+                item.bytes(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            (0x100, true, &[0x3e, 0x01][..]),
+            (0x102, true, &[0xca, 0x0d, 0x01]),
+            (0x105, true, &[0xcd, 0x10, 0x01]),
+            (0x108, true, &[0xc3, 0x11, 0x01]),
+            (0x10b, false, &[0xff, 0xff]),
+            (0x10d, true, &[0x00]),
+            (0x10e, true, &[0xc9]),
+            (0x10f, false, &[0x12]),
+            (0x110, true, &[0xc9]),
+            (0x111, true, &[0x76]),
+            (0x112, false, &[0x3e]),
+        ]
+    );
+}
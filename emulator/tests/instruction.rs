@@ -0,0 +1,139 @@
+use emulator::{
+    cpu::CPU,
+    disassembler::{format_intel_instruction, SymbolTable},
+    instruction::Instruction,
+    parser::{InstructionParser, Parsed},
+    register::Register,
+};
+
+/// Opcodes the 8080 executes like another, documented, opcode
+const UNDOCUMENTED_OPCODES: [u8; 12] = [
+    0x08, 0x10, 0x18, 0x20, 0x28, 0x30, 0x38, 0xcb, 0xd9, 0xdd, 0xed, 0xfd,
+];
+
+#[test]
+fn encode_round_trips_every_opcode() {
+    for opcode in 0..=0xff_u8 {
+        let size = InstructionParser::bytes_to_read(opcode) + 1;
+        let bytes = &[opcode, 0x34, 0x12][..size];
+        let instruction = InstructionParser::parse_bytes(bytes).unwrap();
+        let encoded = instruction.encode();
+
+        if UNDOCUMENTED_OPCODES.contains(&opcode) {
+            assert_ne!(encoded.as_slice(), bytes, "{opcode:02x} encodes as itself");
+            let decoded = InstructionParser::parse_bytes(&encoded).unwrap();
+            assert!(decoded == instruction, "{opcode:02x}: {instruction}");
+        } else {
+            assert_eq!(encoded.as_slice(), bytes, "{opcode:02x}: {instruction}");
+        }
+    }
+}
+
+#[test]
+fn metadata_agrees_with_the_encoding() {
+    for opcode in 0..=0xff_u8 {
+        let size = InstructionParser::bytes_to_read(opcode) + 1;
+        let bytes = &[opcode, 0x34, 0x12][..size];
+        let instruction = InstructionParser::parse_bytes(bytes).unwrap();
+
+        assert_eq!(
+            instruction.size(),
+            instruction.encode().len(),
+            "{instruction}"
+        );
+        let cycles = instruction.cycles();
+        if !instruction.is_conditional() {
+            assert_eq!(cycles.taken, cycles.not_taken, "{instruction}");
+        }
+        if instruction.is_call() {
+            assert!(
+                instruction.writes_memory(),
+                "{instruction} pushes the return address"
+            );
+        }
+        if instruction.is_return() {
+            assert!(
+                instruction.reads_memory(),
+                "{instruction} pops the return address"
+            );
+        }
+    }
+}
+
+#[test]
+fn display_and_intel_text_parse_back() {
+    let symbols = SymbolTable::default();
+    for opcode in 0..=0xff_u8 {
+        let size = InstructionParser::bytes_to_read(opcode) + 1;
+        let bytes = &[opcode, 0x34, 0x12][..size];
+        let instruction = InstructionParser::parse_bytes(bytes).unwrap();
+
+        let displayed = instruction.to_string();
+        assert!(
+            displayed.parse::<Instruction>() == Ok(instruction),
+            "{displayed}"
+        );
+        let intel = format_intel_instruction(&instruction, &symbols);
+        assert!(intel.parse::<Instruction>() == Ok(instruction), "{intel}");
+    }
+
+    assert!("mvi b, 10 + 2".parse::<Instruction>() == Ok(Instruction::MVI(Register::B, 12)));
+    assert!("MOV A,X".parse::<Instruction>().is_err());
+}
+
+#[test]
+fn rst_1_is_a_single_byte() {
+    // RST 1; INR A; HLT
+    let parsed: Vec<_> = InstructionParser::new(&[0xcf, 0x3c, 0x76], 0).collect();
+    assert_eq!(parsed.len(), 3);
+    assert_eq!((parsed[0].0, parsed[0].1), (0, &[0xcf][..]));
+    assert!(matches!(
+        parsed[0].2,
+        Parsed::Instruction(Instruction::RST(1))
+    ));
+    assert!(matches!(
+        parsed[1].2,
+        Parsed::Instruction(Instruction::INR(Register::A))
+    ));
+
+    let mut program = vec![0; 0x0b];
+    // 0000: LXI SP, 2000H; RST 1; INR A; HLT
+    program[..6].copy_from_slice(&[0x31, 0x00, 0x20, 0xcf, 0x3c, 0x76]);
+    // 0008: MVI A, 41H; RET
+    program[0x08..].copy_from_slice(&[0x3e, 0x41, 0xc9]);
+
+    let mut cpu = CPU::new();
+    cpu.load_program(&program);
+    while !cpu.is_halted() {
+        cpu.fetch_decode_execute();
+    }
+    // Execution went on at the INR A right after the RST
+    assert_eq!(cpu.read_register(Register::A), 0x42);
+    assert_eq!(cpu.program_counter(), 0x0006);
+}
+
+#[test]
+fn instructions_cut_off_by_the_end_are_truncated() {
+    // NOP; JMP cut off after its low byte
+    let buffer = [0x00, 0xc3, 0x34];
+    let parser = InstructionParser::new(&buffer, 0x100);
+
+    let (bytes, parsed) = parser.decode_at(0x101).unwrap();
+    assert_eq!(bytes, &[0xc3, 0x34]);
+    assert!(matches!(parsed, Parsed::Truncated(rest) if rest == [0xc3, 0x34]));
+    assert!(parser.decode_at(0x103).is_none());
+    assert!(parser.decode_at(0xff).is_none());
+
+    let parsed: Vec<_> = parser.collect();
+    assert_eq!(parsed.len(), 2);
+    assert!(matches!(parsed[0].2, Parsed::Instruction(Instruction::NOP)));
+    assert_eq!((parsed[1].0, parsed[1].1), (0x101, &[0xc3, 0x34][..]));
+    assert!(matches!(parsed[1].2, Parsed::Truncated(_)));
+
+    assert!(InstructionParser::parse_bytes(&[0xc3, 0x34]).is_none());
+    assert!(InstructionParser::parse_bytes(&[]).is_none());
+    assert!(
+        InstructionParser::parse_bytes(&[0x3e, 0x01, 0x76])
+            == Some(Instruction::MVI(Register::A, 1))
+    );
+}
@@ -0,0 +1,374 @@
+use emulator::{
+    asm8080,
+    invaders::{
+        load_split_rom, Button, DipSwitches, Invaders, InvadersInputs, Mixer, Movie, SoundEvent,
+        CHIPS,
+    },
+};
+
+const ROM: &[u8] = include_bytes!("../../roms/invaders.concatenated");
+
+/// A scripted session: each step holds some buttons for a number of frames
+type Script<'a> = &'a [(usize, &'a [Button])];
+
+const INSERT_COIN_AND_START: Script = &[
+    (200, &[]),
+    (10, &[Button::Coin]),
+    (100, &[]),
+    (10, &[Button::P1Start]),
+    (300, &[]),
+];
+
+const FIRE: Script = &[(5, &[Button::P1Fire]), (45, &[])];
+
+const MOVE_RIGHT_AND_FIRE: Script = &[(30, &[Button::P1Right]), (5, &[Button::P1Fire]), (60, &[])];
+
+/// Runs `script` on `machine`, returning the frame hash after each step
+fn run_script(machine: &mut Invaders, script: Script) -> Vec<u64> {
+    let mut hashes = vec![];
+    for (frames, buttons) in script {
+        for button in *buttons {
+            machine.set_button(*button, true);
+        }
+        for _ in 0..*frames {
+            machine.run_frame();
+        }
+        for button in *buttons {
+            machine.set_button(*button, false);
+        }
+        hashes.push(machine.frame_hash());
+    }
+    hashes
+}
+
+#[test]
+fn attract_mode() {
+    let mut machine = Invaders::new(ROM, DipSwitches::default());
+    let hashes = run_script(&mut machine, &[(60, &[]), (300, &[]), (600, &[])]);
+    assert_eq!(
+        hashes,
+        [0x6f4cbcf2b980d912, 0x88ae3fe599c3146f, 0x54cd5fee92710703]
+    );
+}
+
+#[test]
+fn insert_coin_and_start() {
+    let mut machine = Invaders::new(ROM, DipSwitches::default());
+    let hashes = run_script(&mut machine, INSERT_COIN_AND_START);
+    assert_eq!(
+        hashes,
+        [
+            0x42790868d6bf1667,
+            0x42790868d6bf1667,
+            0xfc432fe4378baef3,
+            0xb2e66e9f1db4f321,
+            0x7761469b038017a5,
+        ]
+    );
+}
+
+#[test]
+fn fire_and_move() {
+    let mut machine = Invaders::new(ROM, DipSwitches::default());
+    run_script(&mut machine, INSERT_COIN_AND_START);
+
+    let hashes = run_script(&mut machine, FIRE);
+    assert_eq!(hashes, [0x5b33c40b03d24715, 0x6a10d3dc56cc2a9a]);
+
+    let hashes = run_script(&mut machine, MOVE_RIGHT_AND_FIRE);
+    assert_eq!(
+        hashes,
+        [0xb8b48e84c3e9c8a9, 0xf019f450229c9a5b, 0xbbf2313a580569e9]
+    );
+}
+
+#[test]
+fn dip_switches_change_the_game() {
+    let dip_switches = DipSwitches {
+        lives: 6,
+        ..Default::default()
+    };
+    let mut machine = Invaders::new(ROM, dip_switches);
+    let hashes = run_script(&mut machine, INSERT_COIN_AND_START);
+    // Same screens as with 3 lives until the ships in reserve are drawn
+    assert_eq!(
+        hashes,
+        [
+            0x42790868d6bf1667,
+            0x42790868d6bf1667,
+            0xfc432fe4378baef3,
+            0xb2e66e9f1db4f321,
+            0x5c3f37d32414fc68,
+        ]
+    );
+}
+
+#[test]
+fn dip_switches_are_read_from_a_configuration() {
+    let dip_switches: DipSwitches = "
+        # Settings of the arcade operator
+        lives = 5   # one more than usual
+
+        bonus_life = 1000
+        coin_info = false
+    "
+    .parse()
+    .unwrap();
+    assert_eq!(
+        dip_switches,
+        DipSwitches {
+            lives: 5,
+            bonus_life_at_1000: true,
+            coin_info: false,
+        }
+    );
+    assert_eq!("".parse(), Ok(DipSwitches::default()));
+
+    let error = |config: &str| config.parse::<DipSwitches>().unwrap_err();
+    assert_eq!(error("lives"), "Line 1: expected `key = value`");
+    assert_eq!(error("\nspeed = 2"), "Line 2: Unknown key `speed`");
+    assert_eq!(
+        error("lives = 7"),
+        "Line 1: Lives must be between 3 and 6, got 7"
+    );
+    assert_eq!(
+        error("lives = 2"),
+        "Line 1: Lives must be between 3 and 6, got 2"
+    );
+    assert_eq!(
+        error("lives = many"),
+        "Line 1: Invalid number of lives `many`"
+    );
+    assert_eq!(
+        error("bonus_life = 2000"),
+        "Line 1: Bonus life must be at 1000 or 1500, got 2000"
+    );
+    assert_eq!(
+        error("bonus_life = -1"),
+        "Line 1: Invalid bonus life score `-1`"
+    );
+    assert_eq!(
+        error("coin_info = yes"),
+        "Line 1: Expected true or false, got `yes`"
+    );
+
+    let mut dip_switches = DipSwitches::default();
+    assert!(dip_switches.set_lives(6).is_ok());
+    assert!(dip_switches.set_lives(7).is_err());
+    assert!(dip_switches.set_bonus_life(1500).is_ok());
+    assert!(dip_switches.set_bonus_life(1200).is_err());
+    assert_eq!(dip_switches.lives, 6);
+    assert!(!dip_switches.bonus_life_at_1000);
+}
+
+#[test]
+fn input_ports_round_trip() {
+    const BUTTONS: [Button; 10] = [
+        Button::Coin,
+        Button::P1Start,
+        Button::P2Start,
+        Button::P1Fire,
+        Button::P1Left,
+        Button::P1Right,
+        Button::P2Fire,
+        Button::P2Left,
+        Button::P2Right,
+        Button::Tilt,
+    ];
+
+    let mut inputs = InvadersInputs::default();
+    inputs.set(Button::P1Fire, true);
+    assert_eq!(inputs.ports(&DipSwitches::default()), [0x1e, 0x18, 0x00]);
+
+    for pressed in 0..1 << BUTTONS.len() {
+        let mut inputs = InvadersInputs::default();
+        for (index, button) in BUTTONS.iter().enumerate() {
+            inputs.set(*button, pressed & 1 << index != 0);
+        }
+        for lives in DipSwitches::MIN_LIVES..=DipSwitches::MAX_LIVES {
+            for (bonus_life_at_1000, coin_info) in
+                [(false, false), (false, true), (true, false), (true, true)]
+            {
+                let dip_switches = DipSwitches {
+                    lives,
+                    bonus_life_at_1000,
+                    coin_info,
+                };
+                let ports = inputs.ports(&dip_switches);
+                assert_eq!(
+                    InvadersInputs::from_ports(&ports),
+                    (inputs, dip_switches),
+                    "{ports:02x?}"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn movie_replays_a_scripted_session() {
+    let mut machine = Invaders::new(ROM, DipSwitches::default());
+    let mut movie = Movie::default();
+    for script in [INSERT_COIN_AND_START, MOVE_RIGHT_AND_FIRE] {
+        for (frames, buttons) in script {
+            for button in *buttons {
+                machine.set_button(*button, true);
+            }
+            for _ in 0..*frames {
+                movie.record_frame(&machine);
+                machine.run_frame();
+            }
+            for button in *buttons {
+                machine.set_button(*button, false);
+            }
+        }
+    }
+    movie.finish(&machine);
+
+    let movie: Movie = movie.to_string().parse().unwrap();
+    let replayed = movie.replay(ROM).unwrap();
+    assert_eq!(replayed.frame_hash(), machine.frame_hash());
+}
+
+#[test]
+fn malformed_movies_are_rejected() {
+    const HEADER: &str = "EIGHTYEIGHTY-MOVIE 1\nhash 5b6e07d40d98ad42\n";
+    let error = |movie: &str| movie.parse::<Movie>().unwrap_err();
+
+    let movie: Movie = format!("{HEADER}2 0e 08 00\n\n1 0e 09 00\n")
+        .parse()
+        .unwrap();
+    assert_eq!(
+        movie.frames,
+        [[0x0e, 0x08, 0x00], [0x0e, 0x08, 0x00], [0x0e, 0x09, 0x00]]
+    );
+    assert_eq!(movie.final_hash, 0x5b6e07d40d98ad42);
+
+    assert_eq!(error(""), "Line 1: expected `EIGHTYEIGHTY-MOVIE 1`");
+    assert_eq!(
+        error("EIGHTYEIGHTY-MOVIE 1\n"),
+        "Line 2: expected `hash <16 hex digits>`"
+    );
+    assert_eq!(
+        error("EIGHTYEIGHTY-MOVIE 1\nhash xyz\n"),
+        "Line 2: expected `hash <16 hex digits>`"
+    );
+    let invalid = "Line 3: expected `<repeat> <port 0> <port 1> <port 2>`";
+    assert_eq!(error(&format!("{HEADER}1 0e 08\n")), invalid);
+    assert_eq!(error(&format!("{HEADER}1 0e 08 00 00\n")), invalid);
+    assert_eq!(error(&format!("{HEADER}1 0e 0g 00\n")), invalid);
+    assert_eq!(error(&format!("{HEADER}1 0e 100 00\n")), invalid);
+    assert_eq!(error(&format!("{HEADER}-1 0e 08 00\n")), invalid);
+
+    // Rejected before the frames are expanded
+    let error = error(&format!("{HEADER}1 0e 08 00\n99999999999 00 08 00\n"));
+    assert!(
+        error.starts_with("Line 4: the movie is longer than"),
+        "{error}"
+    );
+}
+
+#[test]
+fn split_rom_chips_are_verified() {
+    let directory =
+        std::env::temp_dir().join(format!("eightyeighty-{}-split-rom", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    for (chip, bytes) in CHIPS.iter().zip(ROM.chunks(0x800)) {
+        std::fs::write(directory.join(chip.name), bytes).unwrap();
+    }
+    assert!(load_split_rom(&directory).unwrap() == ROM);
+
+    let mut bad = ROM[0x1000..0x1800].to_vec();
+    bad[100] ^= 0xff;
+    std::fs::write(directory.join("invaders.f"), bad).unwrap();
+    let error = load_split_rom(&directory).unwrap_err();
+    assert!(error.starts_with("invaders.f is a bad dump"), "{error}");
+
+    std::fs::remove_file(directory.join("invaders.g")).unwrap();
+    let error = load_split_rom(&directory).unwrap_err();
+    assert!(error.contains("invaders.g"), "{error}");
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn sounds_trigger_on_rising_edges_with_the_amplifier_on() {
+    let program = asm8080! {
+        // The shot bit is latched while the amplifier is off
+        MVI A, 0x02; OUT 3;
+        // Turning the amplifier on does not replay it
+        MVI A, 0x22; OUT 3;
+        MVI A, 0x23; OUT 3;
+        MVI A, 0x23; OUT 3;
+        MVI A, 0x01; OUT 5;
+        MVI A, 0x11; OUT 5;
+        // Nothing plays with the amplifier off
+        MVI A, 0x00; OUT 3;
+        MVI A, 0x13; OUT 5;
+        HLT
+    };
+    let mut machine = Invaders::new(&program, DipSwitches::default());
+    machine.run_frame();
+    assert_eq!(
+        machine.take_sound_events(),
+        [
+            SoundEvent::Ufo,
+            SoundEvent::FleetMovement1,
+            SoundEvent::UfoHit
+        ]
+    );
+    assert!(machine.take_sound_events().is_empty());
+}
+
+#[test]
+fn mixer_plays_samples_from_the_start_of_their_frame() {
+    const FRAME: usize = Mixer::SAMPLE_RATE as usize / 60;
+
+    let mut mixer = Mixer::new();
+    mixer.set_sample(SoundEvent::Shot, vec![0.5; FRAME + 100]);
+    mixer.set_sample(SoundEvent::Ufo, vec![0.25; 10]);
+
+    mixer.mix_frame(&[SoundEvent::Shot, SoundEvent::Ufo, SoundEvent::InvaderHit]);
+    assert_eq!(mixer.buffer().len(), FRAME + 100);
+    assert_eq!(mixer.buffer()[0], 0.75);
+    assert_eq!(mixer.buffer()[10], 0.5);
+
+    mixer.mix_frame(&[SoundEvent::Ufo]);
+    assert_eq!(mixer.buffer().len(), 2 * FRAME);
+    assert_eq!(mixer.buffer()[FRAME + 9], 0.75);
+    assert_eq!(mixer.buffer()[FRAME + 100], 0.0);
+
+    mixer.mix_frame(&[]);
+    assert_eq!(mixer.buffer().len(), 3 * FRAME);
+    assert!(mixer.buffer()[2 * FRAME..]
+        .iter()
+        .all(|value| *value == 0.0));
+}
+
+#[test]
+fn mixer_writes_16_bit_mono_wav() {
+    let mut mixer = Mixer::new();
+    mixer.set_sample(SoundEvent::Shot, vec![0.5, -2.0]);
+    mixer.mix_frame(&[SoundEvent::Shot]);
+
+    let path = std::env::temp_dir().join(format!("eightyeighty-{}-mixer.wav", std::process::id()));
+    mixer.write_wav(&path).unwrap();
+    let bytes = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let samples = mixer.buffer().len();
+    assert_eq!(&bytes[..4], b"RIFF");
+    assert_eq!(&bytes[8..16], b"WAVEfmt ");
+    // PCM, 1 channel, 44100 Hz, 88200 bytes per second, 2 byte blocks, 16 bits
+    assert_eq!(
+        &bytes[20..36],
+        &[1, 0, 1, 0, 0x44, 0xac, 0, 0, 0x88, 0x58, 1, 0, 2, 0, 16, 0]
+    );
+    assert_eq!(&bytes[36..40], b"data");
+    assert_eq!(
+        u32::from_le_bytes(bytes[40..44].try_into().unwrap()) as usize,
+        2 * samples
+    );
+    assert_eq!(bytes.len(), 44 + 2 * samples);
+    // 0.5 is scaled to i16, -2.0 is clamped
+    assert_eq!(&bytes[44..48], &[0xff, 0x3f, 0x01, 0x80]);
+}
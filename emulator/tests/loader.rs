@@ -0,0 +1,69 @@
+use std::path::Path;
+
+use emulator::{
+    asm8080,
+    cpu::CPU,
+    loader::{parse_intel_hex, parse_srec, write_intel_hex, Format, Image},
+};
+
+#[test]
+fn intel_hex_round_trips() {
+    let image = Image {
+        origin: 0x100,
+        bytes: asm8080! { MVI A, 1; REPT 20; INR A; ENDM; HLT },
+        start: Some(0x100),
+    };
+    let text = write_intel_hex(&image);
+    assert_eq!(
+        text.lines().next(),
+        Some(":100100003E013C3C3C3C3C3C3C3C3C3C3C3C3C3C68")
+    );
+    assert_eq!(parse_intel_hex(&text), Ok(image));
+}
+
+#[test]
+fn malformed_intel_hex_records_are_reported() {
+    let error = parse_intel_hex(":0300000001020300\n:00000001FF\n").unwrap_err();
+    assert_eq!(error, "Line 1: checksum is 00, expected F7");
+
+    let error = parse_intel_hex(":03000000010203F7\n").unwrap_err();
+    assert_eq!(error, "Missing the end of file record");
+}
+
+#[test]
+fn s_records_load_at_their_address() {
+    let text = "S00600004844521B\nS1060100C30001341\nS9030100FB\n";
+    let error = parse_srec(text).unwrap_err();
+    assert_eq!(
+        error,
+        "Line 2: expected pairs of hex digits after the record type"
+    );
+
+    let text = "S00600004844521B\nS1060100C3000134\nS9030100FB\n";
+    let image = parse_srec(text).unwrap();
+    assert_eq!(image.origin, 0x100);
+    assert_eq!(image.bytes, [0xc3, 0x00, 0x01]);
+    assert_eq!(image.start, Some(0x100));
+
+    assert_eq!(
+        Format::detect(Path::new("program"), text.as_bytes()),
+        Format::Srec
+    );
+    assert_eq!(
+        Format::detect(Path::new("program"), b":00000001FF\n"),
+        Format::IntelHex
+    );
+    assert_eq!(
+        Format::detect(Path::new("program"), &[0x3a, 0x00, 0x20]),
+        Format::Raw
+    );
+}
+
+#[test]
+fn programs_past_the_end_of_memory_are_rejected() {
+    let mut cpu = CPU::new();
+    assert!(cpu.load_at(0xfffe, &[1, 2]).is_ok());
+    assert_eq!(cpu.memory()[0xffff], 2);
+    assert!(cpu.load_at(0xfffe, &[1, 2, 3]).is_err());
+    assert!(Image::raw(0xff00, vec![0; 0x101]).is_err());
+}
@@ -0,0 +1,66 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+use emulator::{
+    assembler::assemble_file,
+    loader::{write_intel_hex, Image},
+};
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about=None)]
+struct Args {
+    input: PathBuf,
+
+    /// Flat binary output, or Intel HEX when its extension is .hex, the input with a .bin extension by default
+    #[arg(short = 'o')]
+    output: Option<PathBuf>,
+
+    /// Listing file with the address and bytes of every source line
+    #[arg(short = 'l', long)]
+    listing: Option<PathBuf>,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let assembly = match assemble_file(&args.input) {
+        Ok(assembly) => assembly,
+        Err(err) => {
+            println!("Error: {err}");
+            std::process::exit(1);
+        }
+    };
+
+    let output = args
+        .output
+        .unwrap_or_else(|| args.input.with_extension("bin"));
+    let is_hex = output
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("hex"));
+    let contents = if is_hex {
+        write_intel_hex(&Image {
+            origin: assembly.origin,
+            bytes: assembly.bytes.clone(),
+            start: assembly.start,
+        })
+        .into_bytes()
+    } else {
+        assembly.bytes.clone()
+    };
+    if let Err(err) = std::fs::write(&output, contents) {
+        println!("Error: {}: {err}", output.display());
+        std::process::exit(1);
+    }
+    if let Some(path) = args.listing {
+        if let Err(err) = std::fs::write(&path, assembly.listing.join("\n") + "\n") {
+            println!("Error: {}: {err}", path.display());
+            std::process::exit(1);
+        }
+    }
+    println!(
+        "{} bytes at {:04x} written to {}",
+        assembly.bytes.len(),
+        assembly.origin,
+        output.display()
+    );
+}
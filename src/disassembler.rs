@@ -1,107 +1,281 @@
-use std::{fs::OpenOptions, io::Read, io::Write, path::PathBuf};
+use std::{fs::OpenOptions, io::Write, path::PathBuf};
 
-use clap::Parser;
-use emulator::{instruction::Instruction, parser::InstructionParser};
+use clap::{Parser, ValueEnum};
+use emulator::address::{address_range, parse_address};
+use emulator::disassembler::{
+    call_graph_dot, follow_control_flow, format_instruction, json_listing, linear_sweep,
+    reassemblable_source, referenced_comment, with_comment, CrossReferences, Item, ReferenceKind,
+    SymbolTable, DATA_BYTES_PER_LINE,
+};
+use emulator::loader::{write_intel_hex, Image};
+
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq, Debug)]
+enum Format {
+    /// `address instruction` lines
+    Text,
+
+    /// A JSON array with one object per instruction or data run
+    Json,
+
+    /// Intel HEX records of the selected bytes, without disassembling them
+    Hex,
+}
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about=None)]
 struct Args {
-    input: String,
+    /// Raw binary, Intel HEX or Motorola S-records
+    input: PathBuf,
 
     #[arg(short = 'o')]
     output: Option<PathBuf>,
+
+    /// Output format of the listing
+    #[arg(long, value_enum, default_value_t = Format::Text, conflicts_with_all = ["reassemblable", "xref"])]
+    format: Format,
+
+    /// Address a raw binary input is loaded at, in hex
+    #[arg(long, value_parser = parse_address, default_value = "0000")]
+    origin: u16,
+
+    /// First address to disassemble, in hex
+    #[arg(long, value_parser = parse_address)]
+    start: Option<u16>,
+
+    /// Last address to disassemble, in hex
+    #[arg(long, value_parser = parse_address)]
+    end: Option<u16>,
+
+    /// Print the raw bytes of each instruction before it
+    #[arg(long)]
+    bytes: bool,
+
+    /// Follow jumps and calls from the entry points, printing unreached bytes as data
+    #[arg(long)]
+    follow: bool,
+
+    /// Extra entry point for --follow and --dot in hex, besides the start and the RST vectors
+    #[arg(long = "entry", value_parser = parse_address)]
+    entry_points: Vec<u16>,
+
+    /// Name jump targets L_XXXX and call targets SUB_XXXX
+    #[arg(long)]
+    labels: bool,
+
+    /// File of `address=name ; comment` lines naming routines and variables
+    #[arg(long)]
+    symbols: Option<PathBuf>,
+
+    /// Emit Intel syntax source that assembles back into the input
+    #[arg(long)]
+    reassemblable: bool,
+
+    /// List the jumps, calls, reads and writes referring to each address instead of the code
+    #[arg(long)]
+    xref: bool,
+
+    /// Write the call graph as a Graphviz DOT file
+    #[arg(long)]
+    dot: Option<PathBuf>,
 }
 
-fn read_file(path: PathBuf) -> Result<Vec<u8>, String> {
-    let mut vec = Vec::new();
-    let file = OpenOptions::new()
-        .read(true)
-        .write(false)
-        .append(false)
-        .open(path);
-    if file.is_err() {
-        return Err(format!(
-            "Could not open file: {:?}",
-            file.unwrap_err().to_string()
-        ));
+/// The raw bytes column of a listing line, blank for data whose bytes are the operands
+fn bytes_column(bytes: &[u8], show_bytes: bool) -> String {
+    if !show_bytes {
+        return String::new();
     }
+    let bytes: Vec<String> = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+    format!("{:8}  ", bytes.join(" "))
+}
 
-    let mut file = file.unwrap();
+/**
+Text lines of the listing.
 
-    match file.read_to_end(&mut vec) {
-        Ok(..) => {}
-        Err(err) => {
-            return Err(format!("Could not open file: {:?}", err.to_string()));
+Named addresses get a label line, data is split in lines of at most
+[`DATA_BYTES_PER_LINE`] bytes and at every label. With `show_bytes` the raw
+bytes of each instruction are printed between its address and its text.
+*/
+fn listing(items: &[Item], symbols: &SymbolTable, show_bytes: bool) -> Vec<String> {
+    let mut lines = vec![];
+    let push_label = |lines: &mut Vec<String>, address: u16| {
+        if let Some(symbol) = symbols.get(address) {
+            let label = format!("{}:", symbol.name);
+            lines.push(with_comment(label, symbol.comment.as_deref()));
         }
     };
 
-    Ok(vec)
+    for item in items {
+        match item {
+            Item::Code {
+                address,
+                bytes,
+                instruction,
+            } => {
+                push_label(&mut lines, *address);
+                let line = format!(
+                    "{address:04x} {}{}",
+                    bytes_column(bytes, show_bytes),
+                    format_instruction(instruction, symbols)
+                );
+                lines.push(with_comment(line, referenced_comment(instruction, symbols)));
+            }
+            Item::Data { address, bytes } => {
+                let mut data: Vec<String> = vec![];
+                let mut line_address = *address;
+                for (offset, byte) in bytes.iter().enumerate() {
+                    let byte_address = address.wrapping_add(offset as u16);
+                    let labelled = symbols.get(byte_address).is_some();
+                    if data.len() == DATA_BYTES_PER_LINE || (labelled && !data.is_empty()) {
+                        lines.push(format!(
+                            "{line_address:04x} {}DB {}",
+                            bytes_column(&[], show_bytes),
+                            data.join(",")
+                        ));
+                        data.clear();
+                    }
+                    if data.is_empty() {
+                        push_label(&mut lines, byte_address);
+                        line_address = byte_address;
+                    }
+                    data.push(format!("#${byte:02x}"));
+                }
+                if !data.is_empty() {
+                    lines.push(format!(
+                        "{line_address:04x} {}DB {}",
+                        bytes_column(&[], show_bytes),
+                        data.join(",")
+                    ));
+                }
+            }
+        }
+    }
+    lines
+}
+
+/// Text lines of the cross reference report, one block per referenced address
+fn xref_report(references: &CrossReferences, symbols: &SymbolTable) -> Vec<String> {
+    let mut lines = vec![];
+    for (address, references) in references.iter() {
+        match symbols.get(address) {
+            Some(symbol) => lines.push(format!("{address:04x} {}", symbol.name)),
+            None => lines.push(format!("{address:04x}")),
+        }
+        for reference in references {
+            let kind = match reference.kind {
+                ReferenceKind::Jump => "jump from",
+                ReferenceKind::Call => "call from",
+                ReferenceKind::Read => "read by",
+                ReferenceKind::Write => "written by",
+                ReferenceKind::Constant => "loaded by",
+            };
+            lines.push(format!("    {kind} {:04x}", reference.from));
+        }
+    }
+    lines
 }
 
-fn write_to_file(instructions: &Vec<InstructionAndAddress>, path: PathBuf) -> Result<(), String> {
+fn write_to_file(lines: &[String], path: PathBuf) -> Result<(), String> {
     let file = OpenOptions::new()
         .read(false)
         .write(true)
         .create(true)
+        .truncate(true)
         .append(false)
         .open(path);
-    if file.is_err() {
-        return Err(format!(
-            "Could not open file: {:?}",
-            file.unwrap_err().to_string()
-        ));
-    }
-    let mut file = file.unwrap();
-    for instruction_and_address in instructions {
-        if let Err(err) = writeln!(
-            file,
-            "{:04x} {}",
-            instruction_and_address.address, instruction_and_address.instruction
-        ) {
+    let mut file = match file {
+        Ok(file) => file,
+        Err(err) => {
+            return Err(format!("Could not open file: {:?}", err.to_string()));
+        }
+    };
+    for line in lines {
+        if let Err(err) = writeln!(file, "{line}") {
             return Err(format!("Could not write to file: {err}"));
         };
     }
     Ok(())
 }
 
-struct InstructionAndAddress {
-    pub address: usize,
-    pub instruction: Instruction,
-}
-
 fn main() {
     let args = Args::parse();
-    println!("{args:?}");
-    let input_path = args.input;
-    let bytes = match read_file(input_path.into()) {
-        Ok(bytes) => bytes,
+    let image = match Image::from_file(&args.input, args.origin) {
+        Ok(image) => image,
         Err(err) => {
             println!("Error: {err}");
             return;
         }
     };
 
-    let mut parser = InstructionParser::new(bytes);
+    let (program, origin) = match address_range(&image.bytes, image.origin, args.start, args.end) {
+        Ok(range) => range,
+        Err(err) => {
+            println!("Error: {err}");
+            return;
+        }
+    };
 
-    let mut instruction_vector = vec![];
+    // The start, reset and the RST vectors, where the hardware may start executing
+    let mut entry_points = vec![origin];
+    entry_points.extend(image.start);
+    entry_points.extend((0..8).map(|vector| vector * 8));
+    entry_points.extend(&args.entry_points);
 
-    let mut cursor = parser.cursor();
-    while let Some(insn) = parser.parse() {
-        instruction_vector.push(InstructionAndAddress {
-            address: cursor,
-            instruction: insn,
-        });
+    let items = if args.follow {
+        follow_control_flow(program, origin, &entry_points)
+    } else {
+        linear_sweep(program, origin)
+    };
 
-        cursor = parser.cursor();
+    let mut symbols = match &args.symbols {
+        Some(path) => {
+            let table = std::fs::read_to_string(path)
+                .map_err(|err| err.to_string())
+                .and_then(|symbols| symbols.parse::<SymbolTable>());
+            match table {
+                Ok(table) => table,
+                Err(err) => {
+                    println!("Error: {}: {err}", path.display());
+                    return;
+                }
+            }
+        }
+        None => SymbolTable::default(),
+    };
+    if args.labels {
+        symbols.add_branch_labels(&items);
     }
+    if let Some(path) = &args.dot {
+        if let Err(err) = std::fs::write(path, call_graph_dot(&items, &entry_points, &symbols)) {
+            println!("Error: {}: {err}", path.display());
+            return;
+        }
+    }
+
+    let lines = if args.format == Format::Hex {
+        let selection = Image {
+            origin,
+            bytes: program.to_vec(),
+            start: image.start,
+        };
+        write_intel_hex(&selection)
+            .lines()
+            .map(str::to_owned)
+            .collect()
+    } else if args.xref {
+        xref_report(&CrossReferences::new(&items), &symbols)
+    } else if args.reassemblable {
+        reassemblable_source(&items, origin, &symbols)
+    } else if args.format == Format::Json {
+        json_listing(&items, &symbols)
+    } else {
+        listing(&items, &symbols, args.bytes)
+    };
 
     if let Some(path) = args.output {
-        write_to_file(&instruction_vector, path).unwrap();
+        write_to_file(&lines, path).unwrap();
     } else {
-        for insn in &instruction_vector {
-            let addr = insn.address;
-            let instruction = insn.instruction;
-            println!("{addr:04x} {instruction}");
+        for line in &lines {
+            println!("{line}");
         }
     }
 }
@@ -0,0 +1,73 @@
+use std::path::Path;
+
+use emulator::invaders::{DipSwitches, Invaders, Mixer, Movie};
+use log::{debug, info, warn};
+
+use crate::{save_movie, MovieMode};
+
+/**
+Runs the Space Invaders `rom` without a screen or sound card, mixing the
+triggered sounds into `wav` when given.
+
+The machine runs for `frames` frames, or for the whole movie when playing one.
+*/
+pub fn run(
+    rom: &[u8],
+    dip_switches: DipSwitches,
+    frames: usize,
+    samples: Option<&Path>,
+    wav: Option<&Path>,
+    movie_mode: &MovieMode,
+) -> Result<(), String> {
+    let mut machine = Invaders::new(rom, dip_switches);
+    let mut mixer = Mixer::new();
+    let mut recording = Movie::default();
+
+    let frames = match movie_mode {
+        MovieMode::Play(movie) => movie.frames.len(),
+        _ => frames,
+    };
+
+    if let Some(directory) = samples {
+        for event in mixer.load_samples(directory)? {
+            warn!(
+                "No {}.wav in {}, {event:?} will be silent",
+                event.sample_name(),
+                directory.display()
+            );
+        }
+    }
+
+    for frame in 0..frames {
+        if let MovieMode::Play(movie) = movie_mode {
+            movie.apply_frame(&mut machine, frame);
+        }
+        recording.record_frame(&machine);
+        machine.run_frame();
+        let events = machine.take_sound_events();
+        for event in &events {
+            debug!("Frame {frame}: {event:?}");
+        }
+        mixer.mix_frame(&events);
+    }
+
+    recording.finish(&machine);
+    save_movie(movie_mode, &recording)?;
+    if let MovieMode::Play(movie) = movie_mode {
+        movie.verify(&machine)?;
+        info!(
+            "Replay matches the movie, final frame hash {:016x}",
+            movie.final_hash
+        );
+    }
+
+    if let Some(path) = wav {
+        mixer.write_wav(path)?;
+        info!(
+            "Wrote {} samples to {}",
+            mixer.buffer().len(),
+            path.display()
+        );
+    }
+    Ok(())
+}
@@ -1,7 +1,8 @@
 use fern::colors::{Color, ColoredLevelConfig};
 use log::Level;
 
-pub fn log_init(log_level: Level) -> Result<(), log::SetLoggerError> {
+/// Logs to a dated file and, when `to_stdout` is set, to the standard output
+pub fn log_init(log_level: Level, to_stdout: bool) -> Result<(), log::SetLoggerError> {
     let colors = ColoredLevelConfig::new()
         .info(Color::Green)
         .warn(Color::Yellow)
@@ -38,10 +39,12 @@ pub fn log_init(log_level: Level) -> Result<(), log::SetLoggerError> {
         })
         .chain(fern::log_file(filename).unwrap());
 
-    fern::Dispatch::new()
+    let mut dispatcher = fern::Dispatch::new()
         .level(log_level.to_level_filter())
-        .chain(stdout_dispatcher)
-        .chain(file_dispatcher)
-        .apply()?;
+        .chain(file_dispatcher);
+    if to_stdout {
+        dispatcher = dispatcher.chain(stdout_dispatcher);
+    }
+    dispatcher.apply()?;
     Ok(())
 }
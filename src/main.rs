@@ -1,24 +1,223 @@
 #![deny(clippy::all)]
 
+mod headless;
 mod logs;
-use std::{fs::OpenOptions, io::Read, path::PathBuf, str::FromStr};
+mod terminal;
+mod tui;
+use std::{path::PathBuf, str::FromStr};
 
 use clap::Parser;
-use emulator::cpu::CPU;
+use emulator::{
+    address::parse_address,
+    altair::Altair,
+    cpm::{Cpm, CpmSystem, Disk},
+    cpu::CPU,
+    invaders::{load_split_rom, DipSwitches, Movie},
+    loader::Image,
+};
 use log::trace;
 use logs::log_init;
+use tui::Glyphs;
 
 #[derive(Parser)]
 struct Arguments {
-    /// The binary file to be executed
+    /// The raw binary, Intel HEX or Motorola S-record file to be executed, or a directory with the
+    /// invaders.h, .g, .f and .e ROM chips
     file: PathBuf,
+
+    /// Address a raw binary is loaded at, in hex
+    #[arg(long, value_parser = parse_address, default_value = "0000")]
+    origin: u16,
+
+    /// Run the file as a CP/M .COM program, with the console on the terminal
+    #[arg(long, conflicts_with_all = ["tui", "headless"])]
+    cpm: bool,
+
+    /// Directory holding the files of the CP/M program, on every drive
+    #[arg(long, default_value = ".")]
+    cpm_dir: PathBuf,
+
+    /// Boot CP/M 2.2 from the file, an 8" IBM 3740 disk image in drive A
+    #[arg(long, conflicts_with_all = ["tui", "headless", "cpm"])]
+    cpm_boot: bool,
+
+    /// Disk images in drives B, C and D with --cpm-boot, created when missing
+    #[arg(long, requires = "cpm_boot")]
+    cpm_disk: Vec<PathBuf>,
+
+    /// Command line arguments of the CP/M program
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true, requires = "cpm")]
+    cpm_arguments: Vec<String>,
+
+    /// Run the file on an Altair 8800, with the serial boards on the terminal
+    #[arg(long, conflicts_with_all = ["tui", "headless", "cpm", "cpm_boot"])]
+    altair: bool,
+
+    /// RAM of the Altair in KiB, from address 0
+    #[arg(long, default_value_t = 64, requires = "altair")]
+    ram: usize,
+
+    /// Front panel sense switches A8 to A15 of the Altair, in hex
+    #[arg(long, value_parser = parse_switches, default_value = "00", requires = "altair")]
+    sense_switches: u8,
+
+    /// Run the file as the Space Invaders ROM, drawing the screen in the terminal
+    #[arg(long)]
+    tui: bool,
+
+    /// Run the file as the Space Invaders ROM without a screen or sound card
+    #[arg(long, conflicts_with = "tui")]
+    headless: bool,
+
+    /// Number of frames to run with --headless
+    #[arg(long, default_value_t = 600)]
+    frames: usize,
+
+    /// Directory with the sound samples (ufo.wav, shot.wav, ...) mixed with --headless
+    #[arg(long)]
+    samples: Option<PathBuf>,
+
+    /// WAV file receiving the audio mixed with --headless
+    #[arg(long)]
+    wav: Option<PathBuf>,
+
+    /// Record the inputs of the --tui or --headless session to a movie file
+    #[arg(long, conflicts_with = "play")]
+    record: Option<PathBuf>,
+
+    /// Replay the inputs of a movie file, verifying the final frame hash
+    #[arg(long)]
+    play: Option<PathBuf>,
+
+    /// Characters used to draw the screen with --tui
+    #[arg(long, value_enum, default_value_t = Glyphs::Braille)]
+    glyphs: Glyphs,
+
+    /// Space Invaders DIP switch configuration file, with `key = value` lines
+    #[arg(long)]
+    dip_config: Option<PathBuf>,
+
+    /// Ships at the start of a game (3-6), overrides the configuration file
+    #[arg(long)]
+    lives: Option<u8>,
+
+    /// Score awarding the extra ship (1000 or 1500), overrides the configuration file
+    #[arg(long)]
+    bonus_life: Option<u16>,
+
+    /// Show the coin info on the attract screen, overrides the configuration file
+    #[arg(long)]
+    coin_info: Option<bool>,
+}
+
+fn dip_switches(arguments: &Arguments) -> Result<DipSwitches, String> {
+    let mut dip_switches = match &arguments.dip_config {
+        Some(path) => match std::fs::read_to_string(path) {
+            Ok(config) => DipSwitches::from_str(&config)
+                .map_err(|err| format!("{}: {err}", path.display()))?,
+            Err(err) => return Err(format!("Could not read {}: {err}", path.display())),
+        },
+        None => DipSwitches::default(),
+    };
+
+    if let Some(lives) = arguments.lives {
+        dip_switches.set_lives(lives)?;
+    }
+    if let Some(points) = arguments.bonus_life {
+        dip_switches.set_bonus_life(points)?;
+    }
+    if let Some(coin_info) = arguments.coin_info {
+        dip_switches.coin_info = coin_info;
+    }
+    Ok(dip_switches)
+}
+
+fn parse_switches(switches: &str) -> Result<u8, String> {
+    let value = parse_address(switches)?;
+    u8::try_from(value)
+        .map_err(|_| format!("Invalid sense switches {switches}: more than 8 switches"))
+}
+
+/// What happens to the inputs of a Space Invaders session
+pub enum MovieMode {
+    None,
+    Record(PathBuf),
+    Play(Movie),
+}
+
+fn movie_mode(arguments: &Arguments) -> Result<MovieMode, String> {
+    if let Some(path) = &arguments.record {
+        return Ok(MovieMode::Record(path.clone()));
+    }
+    match &arguments.play {
+        Some(path) => match std::fs::read_to_string(path) {
+            Ok(movie) => Movie::from_str(&movie)
+                .map(MovieMode::Play)
+                .map_err(|err| format!("{}: {err}", path.display())),
+            Err(err) => Err(format!("Could not read {}: {err}", path.display())),
+        },
+        None => Ok(MovieMode::None),
+    }
+}
+
+/// Writes the movie being recorded, if any
+fn save_movie(mode: &MovieMode, movie: &Movie) -> Result<(), String> {
+    if let MovieMode::Record(path) = mode {
+        std::fs::write(path, movie.to_string())
+            .map_err(|err| format!("Could not write {}: {err}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Runs the .COM program of `arguments` until it returns to CP/M
+fn run_cpm(arguments: &Arguments) -> Result<(), String> {
+    let program = std::fs::read(&arguments.file)
+        .map_err(|err| format!("Could not read {}: {err}", arguments.file.display()))?;
+    let console = terminal::HostConsole::new().map_err(|err| format!("Terminal error: {err}"))?;
+    let mut cpm = Cpm::new(
+        &program,
+        &arguments.cpm_arguments,
+        &arguments.cpm_dir,
+        console,
+    )?;
+    cpm.run();
+    Ok(())
+}
+
+/// Boots CP/M from the disk images of `arguments`, saving them when the console is closed
+fn boot_cpm(arguments: &Arguments) -> Result<(), String> {
+    let disks = std::iter::once(&arguments.file)
+        .chain(&arguments.cpm_disk)
+        .map(|path| Disk::open(path))
+        .collect::<Result<Vec<Disk>, String>>()?;
+    let console = terminal::HostConsole::new().map_err(|err| format!("Terminal error: {err}"))?;
+    let mut system = CpmSystem::new(disks, console)?;
+    system.run();
+    system.save()
+}
+
+/// Runs `image` on an Altair, from its start address or its origin
+fn run_altair(arguments: &Arguments, image: &Image) -> Result<(), String> {
+    let console = terminal::HostConsole::new().map_err(|err| format!("Terminal error: {err}"))?;
+    let mut altair = Altair::new(arguments.ram, console)?;
+    altair.load(image.origin, &image.bytes)?;
+    altair.set_sense_switches(arguments.sense_switches);
+    altair.set_program_counter(image.start.unwrap_or(image.origin));
+    altair.run();
+    Ok(())
 }
 
 fn main() {
     let log_level = std::env::var("LOG_LEVEL").unwrap_or_else(|_| "info".to_owned());
     let level = log::Level::from_str(&log_level).unwrap_or(log::Level::Info);
 
-    match log_init(level) {
+    let arguments = Arguments::parse();
+
+    // Logging to stdout would draw over the terminal screen
+    match log_init(
+        level,
+        !arguments.tui && !arguments.cpm && !arguments.cpm_boot && !arguments.altair,
+    ) {
         Ok(_) => {
             trace!("Initialized logging");
         }
@@ -27,27 +226,76 @@ fn main() {
         }
     };
 
-    let arguments = Arguments::parse();
-    let file = arguments.file;
+    if arguments.cpm {
+        if let Err(err) = run_cpm(&arguments) {
+            panic!("CP/M session failed: {err}")
+        }
+        return;
+    }
+    if arguments.cpm_boot {
+        if let Err(err) = boot_cpm(&arguments) {
+            panic!("CP/M session failed: {err}")
+        }
+        return;
+    }
 
-    let mut file = match OpenOptions::new().read(true).open(file) {
-        Ok(file) => file,
+    let image = if arguments.file.is_dir() {
+        load_split_rom(&arguments.file).and_then(|rom| Image::raw(0, rom))
+    } else {
+        Image::from_file(&arguments.file, arguments.origin)
+    };
+    let image = match image {
+        Ok(image) => image,
         Err(err) => {
-            panic!("Error opening file: {err}")
+            panic!("Error loading file: {err}")
         }
     };
 
-    let mut vector = vec![];
+    if arguments.altair {
+        if let Err(err) = run_altair(&arguments, &image) {
+            panic!("Altair session failed: {err}")
+        }
+        return;
+    }
 
-    match file.read_to_end(&mut vector) {
-        Ok(_) => {}
-        Err(err) => {
-            panic!("Error reading file: {err}")
+    if arguments.tui || arguments.headless {
+        let vector = image.memory();
+        let dip_switches = match dip_switches(&arguments) {
+            Ok(dip_switches) => dip_switches,
+            Err(err) => {
+                panic!("Invalid DIP switches: {err}")
+            }
+        };
+
+        let movie_mode = match movie_mode(&arguments) {
+            Ok(movie_mode) => movie_mode,
+            Err(err) => {
+                panic!("Invalid movie: {err}")
+            }
+        };
+
+        if arguments.headless {
+            if let Err(err) = headless::run(
+                &vector,
+                dip_switches,
+                arguments.frames,
+                arguments.samples.as_deref(),
+                arguments.wav.as_deref(),
+                &movie_mode,
+            ) {
+                panic!("Headless run failed: {err}")
+            }
+        } else if let Err(err) = tui::run(&vector, dip_switches, arguments.glyphs, &movie_mode) {
+            panic!("Terminal session failed: {err}")
         }
+        return;
     }
 
     let mut cpu = CPU::new();
-    cpu.load_program(&vector);
+    if let Err(err) = cpu.load_at(image.origin, &image.bytes) {
+        panic!("Error loading file: {err}")
+    }
+    cpu.set_program_counter(image.start.unwrap_or(image.origin));
 
     loop {
         cpu.fetch_decode_execute();
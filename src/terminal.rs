@@ -0,0 +1,152 @@
+use std::{
+    collections::VecDeque,
+    io::{stdin, stdout, IsTerminal, Read, Stdout, Write},
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+    time::Duration,
+};
+
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    terminal,
+};
+use emulator::console::Console;
+
+/// ^], which closes the console as telnet does, since ^C belongs to the machine
+const QUIT: u8 = 0x1d;
+
+/// Where the characters typed come from
+enum Input {
+    /// Keys of a terminal in raw mode
+    Keyboard,
+
+    /// Bytes of a pipe or file, read by a thread so that the status can be polled
+    Stream(Receiver<u8>),
+}
+
+/**
+The terminal running the emulator as a [`Console`]: the keyboard is read in raw
+mode, or the standard input when it is not a terminal, with line feeds turned
+into carriage returns.
+*/
+pub struct HostConsole {
+    input: Input,
+    pending: VecDeque<u8>,
+    closed: bool,
+    stdout: Stdout,
+}
+
+impl HostConsole {
+    pub fn new() -> std::io::Result<HostConsole> {
+        let input = if stdin().is_terminal() {
+            terminal::enable_raw_mode()?;
+            Input::Keyboard
+        } else {
+            let (sender, receiver) = mpsc::channel();
+            thread::spawn(move || {
+                for byte in stdin().lock().bytes() {
+                    let Ok(byte) = byte else { break };
+                    let byte = if byte == b'\n' { b'\r' } else { byte };
+                    if sender.send(byte).is_err() {
+                        break;
+                    }
+                }
+            });
+            Input::Stream(receiver)
+        };
+        Ok(HostConsole {
+            input,
+            pending: VecDeque::new(),
+            closed: false,
+            stdout: stdout(),
+        })
+    }
+
+    /// Takes the input available, waiting for some when `wait` is set
+    fn fill(&mut self, wait: bool) {
+        while self.pending.is_empty() && !self.closed {
+            match &self.input {
+                Input::Keyboard => {
+                    if !wait && !event::poll(Duration::ZERO).unwrap_or(false) {
+                        return;
+                    }
+                    match event::read() {
+                        Ok(Event::Key(key)) if key.kind != KeyEventKind::Release => {
+                            match key_byte(key) {
+                                Some(QUIT) => self.closed = true,
+                                Some(byte) => self.pending.push_back(byte),
+                                None => {}
+                            }
+                        }
+                        Ok(_) => {}
+                        Err(_) => self.closed = true,
+                    }
+                }
+                Input::Stream(receiver) => {
+                    let byte = if wait {
+                        receiver.recv().map_err(|_| TryRecvError::Disconnected)
+                    } else {
+                        receiver.try_recv()
+                    };
+                    match byte {
+                        Ok(byte) => self.pending.push_back(byte),
+                        Err(TryRecvError::Empty) => return,
+                        Err(TryRecvError::Disconnected) => self.closed = true,
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Drop for HostConsole {
+    fn drop(&mut self) {
+        let _ = self.stdout.flush();
+        if matches!(self.input, Input::Keyboard) {
+            let _ = terminal::disable_raw_mode();
+        }
+    }
+}
+
+impl Console for HostConsole {
+    fn status(&mut self) -> bool {
+        let _ = self.stdout.flush();
+        self.fill(false);
+        !self.pending.is_empty()
+    }
+
+    fn read(&mut self) -> Option<u8> {
+        let _ = self.stdout.flush();
+        self.fill(true);
+        self.pending.pop_front()
+    }
+
+    fn write(&mut self, byte: u8) {
+        let _ = self.stdout.write_all(&[byte]);
+    }
+
+    fn closed(&self) -> bool {
+        self.closed && self.pending.is_empty()
+    }
+}
+
+/// The character of a key, the arrows sending the WordStar cursor keys
+fn key_byte(key: KeyEvent) -> Option<u8> {
+    let byte = match key.code {
+        KeyCode::Char(char) if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            (char.to_ascii_uppercase() as u8) & 0x1f
+        }
+        KeyCode::Char(char) if char.is_ascii() => char as u8,
+        KeyCode::Enter => 0x0d,
+        KeyCode::Backspace => 0x08,
+        KeyCode::Tab => 0x09,
+        KeyCode::Esc => 0x1b,
+        KeyCode::Delete => 0x7f,
+        KeyCode::Up => 0x05,
+        KeyCode::Down => 0x18,
+        KeyCode::Left => 0x13,
+        KeyCode::Right => 0x04,
+        _ => return None,
+    };
+    Some(byte)
+}
@@ -0,0 +1,225 @@
+use std::{
+    collections::BTreeMap,
+    io::{stdout, Stdout, Write},
+    thread,
+    time::{Duration, Instant},
+};
+
+use clap::ValueEnum;
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute, queue,
+    style::Print,
+    terminal,
+};
+use emulator::invaders::{Button, DipSwitches, Invaders, Movie, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+use crate::{save_movie, MovieMode};
+
+const FRAME_DURATION: Duration = Duration::from_micros(1_000_000 / 60);
+
+/// Terminals only report key presses, so a button is held down for this many frames
+const HOLD_FRAMES: u32 = 6;
+
+/// Characters used to draw the screen
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum Glyphs {
+    /// 2x4 pixels per character, 112x64 characters
+    Braille,
+
+    /// 1x2 pixels per character, 224x128 characters
+    HalfBlock,
+}
+
+/// Puts the terminal in raw mode on an alternate screen and restores it when dropped
+struct TerminalGuard {
+    stdout: Stdout,
+}
+
+impl TerminalGuard {
+    fn new() -> std::io::Result<TerminalGuard> {
+        let mut stdout = stdout();
+        terminal::enable_raw_mode()?;
+        execute!(
+            stdout,
+            terminal::EnterAlternateScreen,
+            terminal::Clear(terminal::ClearType::All),
+            cursor::Hide
+        )?;
+        Ok(TerminalGuard { stdout })
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(self.stdout, cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// What a key press means to the front-end
+enum KeyAction {
+    Press(Button),
+    Quit,
+    Ignore,
+}
+
+fn key_action(key: KeyEvent) -> KeyAction {
+    if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+        return KeyAction::Quit;
+    }
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('q') => KeyAction::Quit,
+        KeyCode::Char('c') => KeyAction::Press(Button::Coin),
+        KeyCode::Char('1') => KeyAction::Press(Button::P1Start),
+        KeyCode::Char('2') => KeyAction::Press(Button::P2Start),
+        KeyCode::Char(' ') => KeyAction::Press(Button::P1Fire),
+        KeyCode::Left => KeyAction::Press(Button::P1Left),
+        KeyCode::Right => KeyAction::Press(Button::P1Right),
+        KeyCode::Char('w') => KeyAction::Press(Button::P2Fire),
+        KeyCode::Char('a') => KeyAction::Press(Button::P2Left),
+        KeyCode::Char('d') => KeyAction::Press(Button::P2Right),
+        KeyCode::Char('t') => KeyAction::Press(Button::Tilt),
+        _ => KeyAction::Ignore,
+    }
+}
+
+fn braille_line(machine: &Invaders, row: usize) -> String {
+    // Bit of each dot in the braille block, indexed by [y][x]
+    const DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+    (0..SCREEN_WIDTH / 2)
+        .map(|column| {
+            let mut bits = 0;
+            for (dy, dots) in DOTS.iter().enumerate() {
+                for (dx, dot) in dots.iter().enumerate() {
+                    if machine.pixel(column * 2 + dx, row * 4 + dy) {
+                        bits |= dot;
+                    }
+                }
+            }
+            char::from_u32(0x2800 + bits).unwrap()
+        })
+        .collect()
+}
+
+fn half_block_line(machine: &Invaders, row: usize) -> String {
+    (0..SCREEN_WIDTH)
+        .map(|x| {
+            let top = machine.pixel(x, row * 2);
+            let bottom = machine.pixel(x, row * 2 + 1);
+            match (top, bottom) {
+                (true, true) => '█',
+                (true, false) => '▀',
+                (false, true) => '▄',
+                (false, false) => ' ',
+            }
+        })
+        .collect()
+}
+
+fn draw(stdout: &mut Stdout, machine: &Invaders, glyphs: Glyphs) -> std::io::Result<()> {
+    let (rows, line): (usize, fn(&Invaders, usize) -> String) = match glyphs {
+        Glyphs::Braille => (SCREEN_HEIGHT / 4, braille_line),
+        Glyphs::HalfBlock => (SCREEN_HEIGHT / 2, half_block_line),
+    };
+
+    for row in 0..rows {
+        queue!(
+            stdout,
+            cursor::MoveTo(0, row as u16),
+            Print(line(machine, row))
+        )?;
+    }
+    queue!(
+        stdout,
+        cursor::MoveTo(0, rows as u16),
+        Print("c: coin  1/2: start  P1 space/arrows  P2 w/a/d  t: tilt  q: quit")
+    )?;
+    stdout.flush()
+}
+
+/**
+Runs the Space Invaders `rom` drawing the screen on the terminal until the user
+quits or the movie being played ends.
+
+While a movie plays the keyboard can only quit.
+*/
+pub fn run(
+    rom: &[u8],
+    dip_switches: DipSwitches,
+    glyphs: Glyphs,
+    movie_mode: &MovieMode,
+) -> Result<(), String> {
+    let mut machine = Invaders::new(rom, dip_switches);
+    let mut recording = Movie::default();
+
+    let played_to_end = play(&mut machine, glyphs, movie_mode, &mut recording)
+        .map_err(|err| format!("Terminal error: {err}"))?;
+
+    recording.finish(&machine);
+    save_movie(movie_mode, &recording)?;
+    if let MovieMode::Play(movie) = movie_mode {
+        if played_to_end {
+            movie.verify(&machine)?;
+        }
+    }
+    Ok(())
+}
+
+/// The frame loop, returns whether the movie being played reached its end
+fn play(
+    machine: &mut Invaders,
+    glyphs: Glyphs,
+    movie_mode: &MovieMode,
+    recording: &mut Movie,
+) -> std::io::Result<bool> {
+    let mut guard = TerminalGuard::new()?;
+
+    // Frames left before each pressed button is released
+    let mut held: BTreeMap<Button, u32> = BTreeMap::new();
+
+    let mut frame = 0;
+    loop {
+        let frame_start = Instant::now();
+
+        while event::poll(Duration::ZERO)? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Release {
+                    continue;
+                }
+                match key_action(key) {
+                    KeyAction::Press(button) => {
+                        held.insert(button, HOLD_FRAMES);
+                    }
+                    KeyAction::Quit => return Ok(false),
+                    KeyAction::Ignore => {}
+                }
+            }
+        }
+
+        if let MovieMode::Play(movie) = movie_mode {
+            if !movie.apply_frame(machine, frame) {
+                return Ok(true);
+            }
+        } else {
+            // Keep pressing buttons with frames left, release the others
+            held.retain(|button, frames| {
+                let pressed = *frames > 0;
+                machine.set_button(*button, pressed);
+                *frames = frames.saturating_sub(1);
+                pressed
+            });
+        }
+
+        recording.record_frame(machine);
+        machine.run_frame();
+        draw(&mut guard.stdout, machine, glyphs)?;
+
+        if let Some(remaining) = FRAME_DURATION.checked_sub(frame_start.elapsed()) {
+            thread::sleep(remaining);
+        }
+        frame += 1;
+    }
+}